@@ -19,6 +19,11 @@ pub enum DecodeError {
     #[error("invalid protocol state: {0}")]
     #[diagnostic(help("Protocol states must be 1 (Status), 2 (Login), or 3 (Transfer)"))]
     InvalidProtocolState(VarInt),
+
+    /// A packet's length was negative, too big, or too small to hold its ID.
+    #[error("invalid packet length: {0}")]
+    #[diagnostic(help("Packets must be at most 2097151 bytes long, including their ID"))]
+    InvalidPacketLength(VarInt),
 }
 
 /// Trait for types that can be decoded from a Minecraft client.
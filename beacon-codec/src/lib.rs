@@ -18,10 +18,15 @@ pub mod decode;
 /// Common types used by the Minecraft protocol.
 pub mod types {
     pub use json::Json;
+    pub use remaining::Remaining;
     pub use varint::VarInt;
 
+    mod array;
+    mod boolean;
     mod json;
     mod number;
+    mod option;
+    mod remaining;
     mod string;
     mod varint;
 }
@@ -0,0 +1,13 @@
+//! See: <https://minecraft.wiki/w/Java_Edition_protocol/Data_types#Type:Prefixed_Array>
+
+use crate::{prelude::*, types::VarInt};
+
+impl<T: Encode> Encode for Vec<T> {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        VarInt(self.len() as i32).encode(write).await?;
+        for item in self {
+            item.encode(write).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+//! See: <https://minecraft.wiki/w/Java_Edition_protocol/Data_types#Type:Boolean>
+
+use crate::prelude::*;
+
+impl Decode for bool {
+    async fn decode<R: AsyncRead + Unpin>(read: &mut R) -> Result<Self, DecodeError> {
+        Ok(read.read_u8().await? != 0)
+    }
+}
+
+impl Encode for bool {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        write.write_u8(*self as u8).await?;
+        Ok(())
+    }
+}
@@ -25,4 +25,4 @@ macro_rules! num {
     };
 }
 
-num!(u16, u128, i64);
+num!(u8, u16, u128, i32, i64, f32, f64);
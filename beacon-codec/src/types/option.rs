@@ -0,0 +1,22 @@
+//! See: <https://minecraft.wiki/w/Java_Edition_protocol/Data_types#Type:Prefixed_Optional>
+
+use crate::prelude::*;
+
+impl<T: Decode> Decode for Option<T> {
+    async fn decode<R: AsyncRead + Unpin>(read: &mut R) -> Result<Self, DecodeError> {
+        match bool::decode(read).await? {
+            true => Ok(Some(T::decode(read).await?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        self.is_some().encode(write).await?;
+        if let Some(value) = self {
+            value.encode(write).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use crate::prelude::*;
+
+/// A byte array that takes up the rest of the packet, so is not prefixed with its length.
+#[derive(Clone, Debug, Default, Deref, From, PartialEq, Eq)]
+pub struct Remaining(pub Vec<u8>);
+
+impl Decode for Remaining {
+    async fn decode<R: AsyncRead + Unpin>(read: &mut R) -> Result<Self, DecodeError> {
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).await?;
+        Ok(Self(data))
+    }
+}
+
+impl Encode for Remaining {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        write.write_all(&self.0).await?;
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ const SEGMENT: u8 = 0x7F;
 const CONTINUE: u8 = 0x80;
 
 /// Variable-length data encoding a [two's complement signed 32-bit integer](std::primitive::i32)
-#[derive(Clone, Copy, Debug, Deref, Display, From, LowerHex, PartialEq, UpperHex)]
+#[derive(Clone, Copy, Debug, Deref, Display, Eq, From, Hash, LowerHex, PartialEq, UpperHex)]
 pub struct VarInt(pub i32);
 
 impl VarInt {
@@ -17,6 +17,28 @@ impl VarInt {
             n => (31 - n.leading_zeros() as usize) / 7 + 1, // ceil(log2(n + 1) / 7)
         }
     }
+
+    /// Encode the [VarInt] synchronously, appending it to a buffer.
+    pub fn put(&self, buf: &mut Vec<u8>) {
+        let (bytes, len) = self.to_bytes();
+        buf.extend_from_slice(&bytes[..len]);
+    }
+
+    /// Returns the encoded bytes, and how many of them are used.
+    fn to_bytes(self) -> ([u8; 5], usize) {
+        let mut bytes = [0; 5];
+        let mut value = self.0 as u32;
+        let segment = SEGMENT as u32;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            if (value & !segment) == 0 {
+                *byte = value as u8;
+                return (bytes, i + 1);
+            }
+            *byte = ((value & segment) as u8) | CONTINUE;
+            value >>= 7;
+        }
+        unreachable!("a u32 fits in 5 segments")
+    }
 }
 
 impl Decode for VarInt {
@@ -37,16 +59,9 @@ impl Decode for VarInt {
 
 impl Encode for VarInt {
     async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
-        let mut value = self.0 as u32;
-        let segment = SEGMENT as u32;
-        loop {
-            if (value & !segment) == 0 {
-                write.write_u8(value as u8).await?;
-                return Ok(());
-            }
-            write.write_u8(((value & segment) as u8) | CONTINUE).await?;
-            value >>= 7;
-        }
+        let (bytes, len) = self.to_bytes();
+        write.write_all(&bytes[..len]).await?;
+        Ok(())
     }
 }
 
@@ -88,6 +103,10 @@ mod tests {
             let mut buf = Vec::new();
             data.encode(&mut buf).await?;
             assert_eq!(&buf[..], *expected);
+
+            let mut buf = Vec::new();
+            data.put(&mut buf);
+            assert_eq!(&buf[..], *expected);
         }
         Ok(())
     }
@@ -0,0 +1,89 @@
+use beacon_core::{
+    App, Ban, BannedPlayers, PlayerBan,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+
+use crate::NO_ARGS;
+
+/// `/ban <targets> [<reason>]` and `/pardon <targets>`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("ban").requires(PermissionLevel::ADMIN).then(
+            argument("targets", ArgumentType::GameProfile)
+                .executes(|ctx| ban(ctx, None))
+                .then(argument("reason", ArgumentType::Message).executes(|ctx| {
+                    let reason = ctx.get("reason");
+                    ban(ctx, Some(reason))
+                })),
+        ),
+    );
+    app.add_command(
+        literal("pardon").requires(PermissionLevel::ADMIN).then(
+            argument("targets", ArgumentType::GameProfile)
+                .suggests(|world, _, _| {
+                    let bans = world.resource::<BannedPlayers>();
+                    bans.iter().map(|ban| ban.profile.name.clone()).collect()
+                })
+                .executes(pardon),
+        ),
+    );
+}
+
+fn ban(ctx: &mut CommandContext, reason: Option<String>) -> Result<(), CommandError> {
+    let source = ctx.sender.name(ctx.world);
+    let mut banned = false;
+    for profile in ctx.profiles("targets")? {
+        let mut bans = ctx.world.resource_mut::<BannedPlayers>();
+        if bans.contains(&profile.uuid) {
+            continue;
+        }
+        let ban = Ban::new(source.clone(), reason.clone());
+        let feedback = Text::translate(
+            "commands.ban.success",
+            [profile.name.clone(), ban.reason.clone()],
+        );
+        bans.insert(PlayerBan {
+            profile: profile.clone(),
+            ban,
+        });
+        banned = true;
+        ctx.feedback(feedback);
+
+        if let Some(player) = crate::find_player(ctx.world, profile.uuid) {
+            let reason = Text::translate("multiplayer.disconnect.banned", NO_ARGS);
+            crate::kick(ctx.world, player, reason);
+        }
+    }
+
+    match banned {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.ban.failed",
+            NO_ARGS,
+        ))),
+    }
+}
+
+fn pardon(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let mut pardoned = false;
+    for profile in ctx.profiles("targets")? {
+        if ctx
+            .world
+            .resource_mut::<BannedPlayers>()
+            .remove(&profile.uuid)
+            .is_some()
+        {
+            pardoned = true;
+            ctx.feedback(Text::translate("commands.pardon.success", [profile.name]));
+        }
+    }
+
+    match pardoned {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.pardon.failed",
+            NO_ARGS,
+        ))),
+    }
+}
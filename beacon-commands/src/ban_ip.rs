@@ -0,0 +1,112 @@
+use std::net::IpAddr;
+
+use beacon_core::{
+    App, Ban, BannedIps, IpBan,
+    command::{
+        ArgumentType, CommandContext, CommandError, PermissionLevel, StringKind, argument, literal,
+    },
+};
+use beacon_net::{conn::RemoteAddr, player::PlayerIdentity, text::Text};
+use bevy_ecs::prelude::*;
+
+use crate::NO_ARGS;
+
+/// `/ban-ip <target> [<reason>]` and `/pardon-ip <target>`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("ban-ip").requires(PermissionLevel::ADMIN).then(
+            argument("target", ArgumentType::String(StringKind::Word))
+                .suggests(|world, _, _| {
+                    let mut query = world.query::<&PlayerIdentity>();
+                    query.iter(world).map(|id| id.name.clone()).collect()
+                })
+                .executes(|ctx| ban_ip(ctx, None))
+                .then(argument("reason", ArgumentType::Message).executes(|ctx| {
+                    let reason = ctx.get("reason");
+                    ban_ip(ctx, Some(reason))
+                })),
+        ),
+    );
+    app.add_command(
+        literal("pardon-ip").requires(PermissionLevel::ADMIN).then(
+            argument("target", ArgumentType::String(StringKind::Word))
+                .suggests(|world, _, _| {
+                    let bans = world.resource::<BannedIps>();
+                    bans.iter().map(|ban| ban.ip.to_string()).collect()
+                })
+                .executes(pardon_ip),
+        ),
+    );
+}
+
+fn ban_ip(ctx: &mut CommandContext, reason: Option<String>) -> Result<(), CommandError> {
+    // the target is either an address, or a player whose address is banned
+    let target: String = ctx.get("target");
+    let ip = match target.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => {
+            let mut query = ctx.world.query::<(&PlayerIdentity, &RemoteAddr)>();
+            query
+                .iter(ctx.world)
+                .find(|(identity, _)| identity.name.eq_ignore_ascii_case(&target))
+                .map(|(_, addr)| addr.ip())
+                .ok_or_else(|| {
+                    CommandError::new(Text::translate("commands.banip.invalid", NO_ARGS))
+                })?
+        }
+    };
+
+    let source = ctx.sender.name(ctx.world);
+    let mut bans = ctx.world.resource_mut::<BannedIps>();
+    if bans.contains(&ip) {
+        return Err(CommandError::new(Text::translate(
+            "commands.banip.failed",
+            NO_ARGS,
+        )));
+    }
+    let ban = Ban::new(source, reason);
+    let feedback = Text::translate(
+        "commands.banip.success",
+        [ip.to_string(), ban.reason.clone()],
+    );
+    bans.insert(IpBan { ip, ban });
+    ctx.feedback(feedback);
+
+    // kick everyone connected from the address
+    let mut query = ctx.world.query::<(Entity, &PlayerIdentity, &RemoteAddr)>();
+    let affected: Vec<(Entity, String)> = query
+        .iter(ctx.world)
+        .filter(|(_, _, addr)| addr.ip() == ip)
+        .map(|(entity, identity, _)| (entity, identity.name.clone()))
+        .collect();
+    if !affected.is_empty() {
+        let names = crate::join(affected.iter().map(|(_, name)| name.as_str()));
+        ctx.feedback(Text::translate(
+            "commands.banip.info",
+            [affected.len().to_string(), names],
+        ));
+    }
+    for (player, _) in affected {
+        let reason = Text::translate("multiplayer.disconnect.ip_banned", NO_ARGS);
+        crate::kick(ctx.world, player, reason);
+    }
+    Ok(())
+}
+
+fn pardon_ip(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let ip: IpAddr = ctx
+        .get::<String>("target")
+        .parse()
+        .map_err(|_| CommandError::new(Text::translate("commands.pardonip.invalid", NO_ARGS)))?;
+    if ctx.world.resource_mut::<BannedIps>().remove(&ip).is_none() {
+        return Err(CommandError::new(Text::translate(
+            "commands.pardonip.failed",
+            NO_ARGS,
+        )));
+    }
+    ctx.feedback(Text::translate(
+        "commands.pardonip.success",
+        [ip.to_string()],
+    ));
+    Ok(())
+}
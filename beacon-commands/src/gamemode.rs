@@ -0,0 +1,75 @@
+use beacon_core::{
+    App,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::{
+    client::{command::SystemChat, world::GameEvent},
+    conn::PacketSender,
+    player::GameMode,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+
+use crate::{NO_ARGS, PLAYERS};
+
+/// `/gamemode <gamemode> [<target>]`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("gamemode")
+            .requires(PermissionLevel::GAMEMASTER)
+            .then(
+                argument("gamemode", ArgumentType::Gamemode)
+                    .executes(|ctx| {
+                        let player = ctx.sender.entity().ok_or_else(|| {
+                            CommandError::new(Text::translate(
+                                "permissions.requires.player",
+                                NO_ARGS,
+                            ))
+                        })?;
+                        set_game_mode(ctx, vec![player])
+                    })
+                    .then(argument("target", PLAYERS).executes(|ctx| {
+                        let players = ctx.players("target")?;
+                        set_game_mode(ctx, players)
+                    })),
+            ),
+    );
+}
+
+fn set_game_mode(ctx: &mut CommandContext, players: Vec<Entity>) -> Result<(), CommandError> {
+    let mode: GameMode = ctx.get("gamemode");
+    let name = || Text::translate(format!("gameMode.{}", mode.name()), NO_ARGS);
+    for player in players {
+        // players already in the game mode are left alone
+        if ctx.world.get::<GameMode>(player) == Some(&mode) {
+            continue;
+        }
+        ctx.world.entity_mut(player).insert(mode);
+
+        // tell the player's client, and the player if someone else changed their game mode
+        if let Some(sender) = ctx.world.get::<PacketSender>(player) {
+            let event = GameEvent {
+                event: GameEvent::CHANGE_GAME_MODE,
+                value: mode.id() as f32,
+            };
+            let mut packets = vec![event.blocking_raw()];
+            if ctx.sender.entity() != Some(player) {
+                let message = Text::translate("gameMode.changed", [name()]);
+                packets.push(SystemChat::new(message).blocking_raw());
+            }
+            for packet in packets.into_iter().flatten() {
+                let _ = sender.send(packet);
+            }
+        }
+
+        let feedback = match ctx.sender.entity() == Some(player) {
+            true => Text::translate("commands.gamemode.success.self", [name()]),
+            false => {
+                let player = crate::display_name(ctx.world, player);
+                Text::translate("commands.gamemode.success.other", [player, name()])
+            }
+        };
+        ctx.feedback(feedback);
+    }
+    Ok(())
+}
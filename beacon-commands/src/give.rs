@@ -0,0 +1,66 @@
+use beacon_core::{
+    App,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+use bevy_ecs::prelude::*;
+
+use crate::PLAYERS;
+
+/// Event triggered on a player who is given items with `/give`. Beacon has no inventories yet, so
+/// a plugin that adds them should observe this to put the items in the player's inventory.
+#[derive(EntityEvent, Debug, Clone)]
+pub struct GiveItem {
+    /// The player.
+    pub entity: Entity,
+    /// The item's ID, e.g. `minecraft:diamond`.
+    pub item: String,
+    /// How many of the item to give.
+    pub count: i32,
+}
+
+/// `/give <targets> <item> [<count>]`
+pub(crate) fn register(app: &mut App) {
+    let count = ArgumentType::Integer {
+        min: Some(1),
+        max: None,
+    };
+    app.add_command(
+        literal("give").requires(PermissionLevel::GAMEMASTER).then(
+            argument("targets", PLAYERS).then(
+                argument("item", ArgumentType::ItemStack)
+                    .executes(|ctx| give(ctx, 1))
+                    .then(argument("count", count).executes(|ctx| {
+                        let count = ctx.get("count");
+                        give(ctx, count)
+                    })),
+            ),
+        ),
+    );
+}
+
+fn give(ctx: &mut CommandContext, count: i32) -> Result<(), CommandError> {
+    let item: String = ctx.get("item");
+    let players = ctx.players("targets")?;
+    for &entity in &players {
+        ctx.world.trigger(GiveItem {
+            entity,
+            item: item.clone(),
+            count,
+        });
+    }
+
+    let feedback = match players[..] {
+        [player] => {
+            let player = crate::display_name(ctx.world, player);
+            let with: [Text; 3] = [count.to_string().into(), item.into(), player];
+            Text::translate("commands.give.success.single", with)
+        }
+        _ => Text::translate(
+            "commands.give.success.multiple",
+            [count.to_string(), item, players.len().to_string()],
+        ),
+    };
+    ctx.feedback(feedback);
+    Ok(())
+}
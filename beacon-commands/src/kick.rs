@@ -0,0 +1,37 @@
+use beacon_core::{
+    App,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+
+use crate::{NO_ARGS, PLAYERS};
+
+/// `/kick <targets> [<reason>]`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("kick").requires(PermissionLevel::ADMIN).then(
+            argument("targets", PLAYERS)
+                .executes(|ctx| kick(ctx, None))
+                .then(argument("reason", ArgumentType::Message).executes(|ctx| {
+                    let reason = ctx.get("reason");
+                    kick(ctx, Some(reason))
+                })),
+        ),
+    );
+}
+
+fn kick(ctx: &mut CommandContext, reason: Option<String>) -> Result<(), CommandError> {
+    let reason = reason.map_or_else(
+        || Text::translate("multiplayer.disconnect.kicked", NO_ARGS),
+        Text::from,
+    );
+    for player in ctx.players("targets")? {
+        let name = crate::display_name(ctx.world, player);
+        crate::kick(ctx.world, player, reason.clone());
+        ctx.feedback(Text::translate(
+            "commands.kick.success",
+            [name, reason.clone()],
+        ));
+    }
+    Ok(())
+}
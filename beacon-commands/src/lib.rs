@@ -0,0 +1,250 @@
+//! # beacon-commands
+//!
+//! The commands operators use to run a beacon server, such as `/kick`, `/whitelist` and `/stop`.
+
+use beacon_codec::ProtocolState;
+use beacon_core::{App, BeaconPlugin, PlayerList, command::ArgumentType};
+use beacon_net::{
+    client::command::SystemChat,
+    conn::PacketSender,
+    entity::{CustomName, EntityType},
+    player::PlayerIdentity,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+use miette::Result;
+
+pub use crate::give::GiveItem;
+
+#[macro_use]
+extern crate tracing;
+
+mod ban;
+mod ban_ip;
+mod gamemode;
+mod give;
+mod kick;
+mod list;
+mod op;
+mod say;
+mod stop;
+mod teleport;
+mod time;
+mod weather;
+mod whitelist;
+
+/// An argument that chooses any number of players.
+const PLAYERS: ArgumentType = ArgumentType::Entity {
+    single: false,
+    players_only: true,
+};
+
+/// An argument that chooses any number of entities.
+const ENTITIES: ArgumentType = ArgumentType::Entity {
+    single: false,
+    players_only: false,
+};
+
+/// An argument that chooses a single entity.
+const ENTITY: ArgumentType = ArgumentType::Entity {
+    single: true,
+    players_only: false,
+};
+
+/// The arguments of a translated message without placeholders.
+const NO_ARGS: [Text; 0] = [];
+
+/// Adds the everyday commands for running a server: `/ban`, `/ban-ip`, `/deop`, `/gamemode`,
+/// `/give`, `/kick`, `/list`, `/op`, `/pardon`, `/pardon-ip`, `/say`, `/stop`, `/teleport`,
+/// `/time`, `/weather` and `/whitelist`.
+///
+/// Each command requires the same [PermissionLevel](beacon_core::command::PermissionLevel) as in
+/// vanilla, and its feedback is translated by each client.
+pub struct CommandsPlugin;
+
+impl BeaconPlugin for CommandsPlugin {
+    fn build(&self, app: &mut App) -> Result<()> {
+        ban::register(app);
+        ban_ip::register(app);
+        gamemode::register(app);
+        give::register(app);
+        kick::register(app);
+        list::register(app);
+        op::register(app);
+        say::register(app);
+        stop::register(app);
+        teleport::register(app);
+        time::register(app);
+        weather::register(app);
+        whitelist::register(app);
+        Ok(())
+    }
+}
+
+/// The name to show for an entity in command feedback.
+fn display_name(world: &World, entity: Entity) -> Text {
+    if let Some(identity) = world.get::<PlayerIdentity>(entity) {
+        return identity.name.as_str().into();
+    }
+    if let Some(name) = world.get::<CustomName>(entity) {
+        return name.as_str().into();
+    }
+    world
+        .get::<EntityType>(entity)
+        .map_or_else(|| "Unknown".into(), |ty| ty.as_str().into())
+}
+
+/// The connection of the player with the given UUID, if they are connected.
+fn find_player(world: &World, uuid: u128) -> Option<Entity> {
+    world.resource::<PlayerList>().get(uuid)
+}
+
+/// Disconnect a player, showing them why.
+fn kick(world: &World, entity: Entity, reason: Text) {
+    let (Some(sender), Some(state)) = (
+        world.get::<PacketSender>(entity),
+        world.get::<ProtocolState>(entity),
+    ) else {
+        return;
+    };
+    if let Err(err) = sender.kick(*state, reason) {
+        warn!(%err, "failed to kick player");
+    }
+}
+
+/// Show a message in the chat of every player in the game.
+fn broadcast(world: &mut World, message: Text) {
+    let packet = match SystemChat::new(message).blocking_raw() {
+        Ok(packet) => packet,
+        Err(err) => {
+            warn!(%err, "failed to broadcast message");
+            return;
+        }
+    };
+    let mut query = world.query::<(&ProtocolState, &PacketSender)>();
+    for (state, sender) in query.iter(world) {
+        if *state == ProtocolState::Play {
+            let _ = sender.send(packet.clone());
+        }
+    }
+}
+
+/// Join names into a list, as vanilla shows them in feedback.
+fn join<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    names.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_config::Config;
+    use beacon_core::{
+        App, BannedPlayers, BeaconPlugin, BeaconServer, CommandSender, NetworkPlugin, Operators,
+        Whitelist, command::PermissionLevel, run_command,
+    };
+    use beacon_net::{
+        conn::{Connection, IncomingSender, OutgoingReceiver, PacketSender},
+        player::PlayerIdentity,
+    };
+    use bevy_ecs::prelude::*;
+    use miette::Result;
+
+    use super::CommandsPlugin;
+
+    /// Adds a configuration that listens on any free port.
+    struct TestConfig;
+
+    impl BeaconPlugin for TestConfig {
+        fn build(&self, app: &mut App) -> Result<()> {
+            let mut config = Config::load("missing.toml")?;
+            config.server.ip = [127, 0, 0, 1].into();
+            config.server.port = 0;
+            app.insert_resource(config);
+            Ok(())
+        }
+    }
+
+    /// Runs commands from the console against the world once the commands have been added.
+    struct Run(fn(&mut World));
+
+    impl BeaconPlugin for Run {
+        fn build(&self, app: &mut App) -> Result<()> {
+            (self.0)(app.world_mut());
+            Ok(())
+        }
+    }
+
+    /// Build a server with the commands, and run `test` against its world.
+    async fn run(test: fn(&mut World)) {
+        BeaconServer::builder()
+            .add_plugin(TestConfig)
+            .add_plugin(NetworkPlugin)
+            .add_plugin(CommandsPlugin)
+            .add_plugin(Run(test))
+            .build()
+            .await
+            .unwrap();
+    }
+
+    /// Connect a player called Steve. The connection stays open until the channels are dropped.
+    fn join(world: &mut World) -> (Entity, (IncomingSender, OutgoingReceiver)) {
+        let channels = Connection::spawn(world, ([127, 0, 0, 1], 1).into());
+        let entity = world
+            .query_filtered::<Entity, With<PacketSender>>()
+            .single(world)
+            .unwrap();
+        world.entity_mut(entity).insert(PlayerIdentity {
+            name: "Steve".to_string(),
+            uuid: 1,
+            properties: Vec::new(),
+        });
+        (entity, channels)
+    }
+
+    fn console(world: &mut World, command: &str) {
+        run_command(world, CommandSender::Console, command);
+    }
+
+    #[tokio::test]
+    async fn test_op() {
+        run(|world| {
+            let (player, _channels) = join(world);
+            console(world, "op Steve");
+            assert!(world.resource::<Operators>().contains(&1));
+            assert_eq!(world.get(player), Some(&PermissionLevel(4)));
+
+            console(world, "deop Steve");
+            assert!(world.resource::<Operators>().is_empty());
+            assert_eq!(world.get::<PermissionLevel>(player), None);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_ban() {
+        run(|world| {
+            let (player, _channels) = join(world);
+            console(world, "ban Steve griefing");
+            let ban = world.resource::<BannedPlayers>().get(&1).unwrap();
+            assert_eq!(ban.ban.reason, "griefing");
+            // banned players are kicked
+            assert!(world.get::<PacketSender>(player).unwrap().is_closed());
+
+            console(world, "pardon Steve");
+            assert!(world.resource::<BannedPlayers>().is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_whitelist() {
+        run(|world| {
+            let _channels = join(world);
+            console(world, "whitelist add Steve");
+            assert!(world.resource::<Whitelist>().contains(&1));
+
+            console(world, "whitelist remove Steve");
+            assert!(world.resource::<Whitelist>().is_empty());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,40 @@
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
+use beacon_core::{
+    App,
+    command::{CommandContext, CommandError, literal},
+};
+use beacon_net::{player::PlayerIdentity, text::Text};
+use uuid::Uuid;
+
+/// `/list` and `/list uuids`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("list")
+            .executes(|ctx| list(ctx, false))
+            .then(literal("uuids").executes(|ctx| list(ctx, true))),
+    );
+}
+
+fn list(ctx: &mut CommandContext, uuids: bool) -> Result<(), CommandError> {
+    let mut query = ctx.world.query::<(&PlayerIdentity, &ProtocolState)>();
+    let players: Vec<String> = query
+        .iter(ctx.world)
+        .filter(|(_, state)| **state == ProtocolState::Play)
+        .map(|(identity, _)| match uuids {
+            true => format!("{} ({})", identity.name, Uuid::from_u128(identity.uuid)),
+            false => identity.name.clone(),
+        })
+        .collect();
+
+    let max_players = ctx.world.resource::<Config>().server.max_players;
+    ctx.feedback(Text::translate(
+        "commands.list.players",
+        [
+            players.len().to_string(),
+            max_players.to_string(),
+            crate::join(players.iter().map(String::as_str)),
+        ],
+    ));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use beacon_config::Config;
+use beacon_core::{
+    App, Operator, Operators,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::{player::PlayerIdentity, text::Text};
+
+use crate::NO_ARGS;
+
+/// `/op <targets>` and `/deop <targets>`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("op").requires(PermissionLevel::ADMIN).then(
+            argument("targets", ArgumentType::GameProfile)
+                .suggests(|world, _, _| {
+                    let mut query = world.query::<&PlayerIdentity>();
+                    let players: Vec<_> = query
+                        .iter(world)
+                        .map(|id| (id.uuid, id.name.clone()))
+                        .collect();
+                    let operators = world.resource::<Operators>();
+                    players
+                        .into_iter()
+                        .filter(|(uuid, _)| !operators.contains(uuid))
+                        .map(|(_, name)| name)
+                        .collect()
+                })
+                .executes(op),
+        ),
+    );
+    app.add_command(
+        literal("deop").requires(PermissionLevel::ADMIN).then(
+            argument("targets", ArgumentType::GameProfile)
+                .suggests(|world, _, _| {
+                    let operators = world.resource::<Operators>();
+                    operators.iter().map(|op| op.profile.name.clone()).collect()
+                })
+                .executes(deop),
+        ),
+    );
+}
+
+fn op(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let level = ctx.world.resource::<Config>().server.op_permission_level;
+    let mut changed = false;
+    for profile in ctx.profiles("targets")? {
+        let mut operators = ctx.world.resource_mut::<Operators>();
+        if operators.contains(&profile.uuid) {
+            continue;
+        }
+        operators.insert(Operator {
+            profile: profile.clone(),
+            level,
+            bypasses_player_limit: false,
+        });
+        changed = true;
+
+        if let Some(player) = crate::find_player(ctx.world, profile.uuid) {
+            ctx.world.entity_mut(player).insert(PermissionLevel(level));
+        }
+        ctx.feedback(Text::translate("commands.op.success", [profile.name]));
+    }
+
+    match changed {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.op.failed",
+            NO_ARGS,
+        ))),
+    }
+}
+
+fn deop(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let mut changed = false;
+    for profile in ctx.profiles("targets")? {
+        let mut operators = ctx.world.resource_mut::<Operators>();
+        if operators.remove(&profile.uuid).is_none() {
+            continue;
+        }
+        changed = true;
+
+        if let Some(player) = crate::find_player(ctx.world, profile.uuid) {
+            ctx.world.entity_mut(player).remove::<PermissionLevel>();
+        }
+        ctx.feedback(Text::translate("commands.deop.success", [profile.name]));
+    }
+
+    match changed {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.deop.failed",
+            NO_ARGS,
+        ))),
+    }
+}
@@ -0,0 +1,19 @@
+use beacon_core::{
+    App,
+    command::{ArgumentType, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+
+/// `/say <message>`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(literal("say").requires(PermissionLevel::GAMEMASTER).then(
+        argument("message", ArgumentType::Message).executes(|ctx| {
+            let name = ctx.sender.name(ctx.world);
+            let message: String = ctx.get("message");
+            let announcement = Text::translate("chat.type.announcement", [name, message]);
+            info!("{announcement}");
+            crate::broadcast(ctx.world, announcement);
+            Ok::<_, CommandError>(())
+        }),
+    ));
+}
@@ -0,0 +1,21 @@
+use beacon_core::{
+    App, ServerHandle,
+    command::{PermissionLevel, literal},
+};
+use beacon_net::text::Text;
+
+use crate::NO_ARGS;
+
+/// `/stop`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("stop")
+            .requires(PermissionLevel::OWNER)
+            .executes(|ctx| {
+                ctx.feedback(Text::translate("commands.stop.stopping", NO_ARGS));
+                // players, and connections still logging in, are told as the server stops
+                ctx.world.resource::<ServerHandle>().shutdown();
+                Ok(())
+            }),
+    );
+}
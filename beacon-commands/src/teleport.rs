@@ -0,0 +1,122 @@
+use beacon_core::{
+    App,
+    command::{
+        ArgumentType, CommandContext, CommandError, Coordinates, PermissionLevel, argument, literal,
+    },
+};
+use beacon_net::{
+    entity::{Position, Rotation},
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+
+use crate::{ENTITIES, ENTITY, NO_ARGS};
+
+/// `/teleport`, and its alias `/tp`:
+/// - `/teleport <location>`
+/// - `/teleport <destination>`
+/// - `/teleport <targets> <location>`
+/// - `/teleport <targets> <destination>`
+pub(crate) fn register(app: &mut App) {
+    let node = app.add_command(
+        literal("teleport")
+            .requires(PermissionLevel::GAMEMASTER)
+            .then(argument("location", ArgumentType::Vec3).executes(|ctx| {
+                let entity = sender_entity(ctx)?;
+                to_location(ctx, vec![entity])
+            }))
+            .then(argument("destination", ENTITY).executes(|ctx| {
+                let entity = sender_entity(ctx)?;
+                to_entity(ctx, vec![entity])
+            }))
+            .then(
+                argument("targets", ENTITIES)
+                    .then(argument("location", ArgumentType::Vec3).executes(|ctx| {
+                        let targets = ctx.entities("targets")?;
+                        to_location(ctx, targets)
+                    }))
+                    .then(argument("destination", ENTITY).executes(|ctx| {
+                        let targets = ctx.entities("targets")?;
+                        to_entity(ctx, targets)
+                    })),
+            ),
+    );
+    app.add_command(
+        literal("tp")
+            .requires(PermissionLevel::GAMEMASTER)
+            .redirect(node),
+    );
+}
+
+/// The entity running the command, which is teleported if no targets are given.
+fn sender_entity(ctx: &CommandContext) -> Result<Entity, CommandError> {
+    ctx.sender
+        .entity()
+        .ok_or_else(|| CommandError::new(Text::translate("permissions.requires.entity", NO_ARGS)))
+}
+
+fn to_location(ctx: &mut CommandContext, targets: Vec<Entity>) -> Result<(), CommandError> {
+    // relative coordinates are relative to the sender, not to each target
+    let origin = ctx.sender.position(ctx.world);
+    let rotation = ctx.sender.rotation(ctx.world);
+    let location = ctx.get::<Coordinates>("location").resolve(
+        &[origin.x, origin.y, origin.z],
+        [rotation.yaw, rotation.pitch],
+    );
+    let position = Position::new(location[0], location[1], location[2]);
+    for &target in &targets {
+        ctx.world.entity_mut(target).insert(position);
+    }
+
+    let [x, y, z] = [position.x, position.y, position.z].map(|c| format!("{c:.6}"));
+    let feedback = match targets[..] {
+        [target] => {
+            let name = crate::display_name(ctx.world, target);
+            let with: [Text; 4] = [name, x.into(), y.into(), z.into()];
+            Text::translate("commands.teleport.success.location.single", with)
+        }
+        _ => Text::translate(
+            "commands.teleport.success.location.multiple",
+            [targets.len().to_string(), x, y, z],
+        ),
+    };
+    ctx.feedback(feedback);
+    Ok(())
+}
+
+fn to_entity(ctx: &mut CommandContext, targets: Vec<Entity>) -> Result<(), CommandError> {
+    let destination = ctx.entity("destination")?;
+    let position = ctx
+        .world
+        .get::<Position>(destination)
+        .copied()
+        .unwrap_or_default();
+    let rotation = ctx
+        .world
+        .get::<Rotation>(destination)
+        .copied()
+        .unwrap_or_default();
+    for &target in &targets {
+        ctx.world.entity_mut(target).insert((position, rotation));
+    }
+
+    let destination = crate::display_name(ctx.world, destination);
+    let feedback = match targets[..] {
+        [target] => {
+            let name = crate::display_name(ctx.world, target);
+            Text::translate(
+                "commands.teleport.success.entity.single",
+                [name, destination],
+            )
+        }
+        _ => {
+            let count = Text::from(targets.len().to_string());
+            Text::translate(
+                "commands.teleport.success.entity.multiple",
+                [count, destination],
+            )
+        }
+    };
+    ctx.feedback(feedback);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use beacon_core::{
+    App, DAY_LENGTH, WorldTime,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+
+/// The times of day that can be set by name.
+const TIMES: [(&str, i64); 4] = [
+    ("day", 1000),
+    ("noon", 6000),
+    ("night", 13000),
+    ("midnight", 18000),
+];
+
+/// `/time (set|add) <time>` and `/time query (daytime|gametime|day)`
+pub(crate) fn register(app: &mut App) {
+    let ticks = ArgumentType::Time { min: 0 };
+    let mut set = literal("set").then(argument("time", ticks.clone()).executes(|ctx| {
+        let time: i32 = ctx.get("time");
+        set_time(ctx, time.into())
+    }));
+    for (name, time) in TIMES {
+        set = set.then(literal(name).executes(move |ctx| set_time(ctx, time)));
+    }
+
+    app.add_command(
+        literal("time")
+            .requires(PermissionLevel::GAMEMASTER)
+            .then(set)
+            .then(literal("add").then(argument("time", ticks).executes(|ctx| {
+                let time: i32 = ctx.get("time");
+                let day_time = ctx.world.resource::<WorldTime>().day_time() + i64::from(time);
+                ctx.world.resource_mut::<WorldTime>().set_day_time(day_time);
+                ctx.feedback(Text::translate(
+                    "commands.time.set",
+                    [(day_time % DAY_LENGTH).to_string()],
+                ));
+                Ok(())
+            })))
+            .then(
+                literal("query")
+                    .then(
+                        literal("daytime")
+                            .executes(|ctx| query(ctx, |time| time.day_time() % DAY_LENGTH)),
+                    )
+                    .then(
+                        literal("gametime").executes(|ctx| {
+                            query(ctx, |time| time.game_time() % i64::from(i32::MAX))
+                        }),
+                    )
+                    .then(
+                        literal("day")
+                            .executes(|ctx| query(ctx, |time| time.day() % i64::from(i32::MAX))),
+                    ),
+            ),
+    );
+}
+
+/// Set the time of day, which starts the day count over like vanilla does.
+fn set_time(ctx: &mut CommandContext, time: i64) -> Result<(), CommandError> {
+    ctx.world.resource_mut::<WorldTime>().set_day_time(time);
+    ctx.feedback(Text::translate("commands.time.set", [time.to_string()]));
+    Ok(())
+}
+
+fn query(ctx: &mut CommandContext, value: fn(&WorldTime) -> i64) -> Result<(), CommandError> {
+    let value = value(ctx.world.resource::<WorldTime>());
+    ctx.feedback(Text::translate("commands.time.query", [value.to_string()]));
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use beacon_core::{
+    App, Weather, WeatherKind,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::text::Text;
+
+use crate::NO_ARGS;
+
+/// `/weather (clear|rain|thunder) [<duration>]`
+pub(crate) fn register(app: &mut App) {
+    let mut weather = literal("weather").requires(PermissionLevel::GAMEMASTER);
+    for kind in [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Thunder] {
+        weather = weather.then(
+            literal(kind.name())
+                .executes(move |ctx| set_weather(ctx, kind, None))
+                .then(
+                    argument("duration", ArgumentType::Time { min: 1 }).executes(move |ctx| {
+                        let duration: i32 = ctx.get("duration");
+                        set_weather(ctx, kind, u32::try_from(duration).ok())
+                    }),
+                ),
+        );
+    }
+    app.add_command(weather);
+}
+
+fn set_weather(
+    ctx: &mut CommandContext,
+    kind: WeatherKind,
+    duration: Option<u32>,
+) -> Result<(), CommandError> {
+    ctx.world.resource_mut::<Weather>().set(kind, duration);
+    let key = format!("commands.weather.set.{}", kind.name());
+    ctx.feedback(Text::translate(key, NO_ARGS));
+    Ok(())
+}
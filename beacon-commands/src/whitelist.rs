@@ -0,0 +1,134 @@
+use beacon_config::Config;
+use beacon_core::{
+    App, Whitelist,
+    command::{ArgumentType, CommandContext, CommandError, PermissionLevel, argument, literal},
+};
+use beacon_net::{player::PlayerIdentity, text::Text};
+
+use crate::NO_ARGS;
+
+/// `/whitelist (on|off|list|add <targets>|remove <targets>)`
+pub(crate) fn register(app: &mut App) {
+    app.add_command(
+        literal("whitelist")
+            .requires(PermissionLevel::ADMIN)
+            .then(literal("on").executes(|ctx| set_enabled(ctx, true)))
+            .then(literal("off").executes(|ctx| set_enabled(ctx, false)))
+            .then(literal("list").executes(list))
+            .then(
+                literal("add").then(
+                    argument("targets", ArgumentType::GameProfile)
+                        .suggests(|world, _, _| {
+                            let mut query = world.query::<&PlayerIdentity>();
+                            let players: Vec<_> = query
+                                .iter(world)
+                                .map(|id| (id.uuid, id.name.clone()))
+                                .collect();
+                            let whitelist = world.resource::<Whitelist>();
+                            players
+                                .into_iter()
+                                .filter(|(uuid, _)| !whitelist.contains(uuid))
+                                .map(|(_, name)| name)
+                                .collect()
+                        })
+                        .executes(add),
+                ),
+            )
+            .then(
+                literal("remove").then(
+                    argument("targets", ArgumentType::GameProfile)
+                        .suggests(|world, _, _| {
+                            let whitelist = world.resource::<Whitelist>();
+                            whitelist
+                                .iter()
+                                .map(|profile| profile.name.clone())
+                                .collect()
+                        })
+                        .executes(remove),
+                ),
+            ),
+    );
+}
+
+fn set_enabled(ctx: &mut CommandContext, enabled: bool) -> Result<(), CommandError> {
+    let mut config = ctx.world.resource_mut::<Config>();
+    if config.server.white_list == enabled {
+        let key = match enabled {
+            true => "commands.whitelist.alreadyOn",
+            false => "commands.whitelist.alreadyOff",
+        };
+        return Err(CommandError::new(Text::translate(key, NO_ARGS)));
+    }
+    config.server.white_list = enabled;
+
+    let key = match enabled {
+        true => "commands.whitelist.enabled",
+        false => "commands.whitelist.disabled",
+    };
+    ctx.feedback(Text::translate(key, NO_ARGS));
+    Ok(())
+}
+
+fn list(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let whitelist = ctx.world.resource::<Whitelist>();
+    let feedback = match whitelist.is_empty() {
+        true => Text::translate("commands.whitelist.none", NO_ARGS),
+        false => {
+            let names = crate::join(whitelist.iter().map(|profile| profile.name.as_str()));
+            Text::translate(
+                "commands.whitelist.list",
+                [whitelist.len().to_string(), names],
+            )
+        }
+    };
+    ctx.feedback(feedback);
+    Ok(())
+}
+
+fn add(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let mut changed = false;
+    for profile in ctx.profiles("targets")? {
+        let mut whitelist = ctx.world.resource_mut::<Whitelist>();
+        if whitelist.contains(&profile.uuid) {
+            continue;
+        }
+        let feedback = Text::translate("commands.whitelist.add.success", [profile.name.clone()]);
+        whitelist.insert(profile);
+        changed = true;
+        ctx.feedback(feedback);
+    }
+
+    match changed {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.whitelist.add.failed",
+            NO_ARGS,
+        ))),
+    }
+}
+
+fn remove(ctx: &mut CommandContext) -> Result<(), CommandError> {
+    let mut changed = false;
+    for profile in ctx.profiles("targets")? {
+        if ctx
+            .world
+            .resource_mut::<Whitelist>()
+            .remove(&profile.uuid)
+            .is_some()
+        {
+            changed = true;
+            ctx.feedback(Text::translate(
+                "commands.whitelist.remove.success",
+                [profile.name],
+            ));
+        }
+    }
+
+    match changed {
+        true => Ok(()),
+        false => Err(CommandError::new(Text::translate(
+            "commands.whitelist.remove.failed",
+            NO_ARGS,
+        ))),
+    }
+}
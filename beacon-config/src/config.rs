@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     net::Ipv4Addr,
     path::{Path, PathBuf},
 };
@@ -7,18 +8,42 @@ use bevy_ecs::prelude::*;
 use figment::{
     Figment,
     providers::{Format, Toml},
+    value::Dict,
 };
-use serde::Deserialize;
+use ipnet::IpNet;
+use serde::{Deserialize, de::DeserializeOwned};
 
-use crate::ConfigError;
+use crate::{ConfigError, VirtualHost};
 
-const DEFAULT_CONFIG: &str = include_str!("../../assets/beacon.default.toml");
+pub(crate) const DEFAULT_CONFIG: &str = include_str!("../../assets/beacon.default.toml");
 
 /// The configuration for the server.
 #[derive(Resource, Debug, Clone, Deserialize)]
 pub struct Config {
     /// Server configuration.
     pub server: ServerConfig,
+    /// Networking configuration.
+    pub network: NetworkConfig,
+    /// Player information forwarding configuration.
+    pub forwarding: ForwardingConfig,
+    /// GameSpy4 query configuration.
+    pub query: QueryConfig,
+    /// Remote console configuration.
+    pub rcon: RconConfig,
+    /// Server console configuration.
+    pub console: ConsoleConfig,
+    /// Tab list configuration.
+    #[serde(rename = "tab-list")]
+    pub tab_list: TabListConfig,
+    /// Server management protocol configuration.
+    pub management: ManagementConfig,
+    /// Virtual hosts, keyed by the hostname clients connect with, which may start with a `*.`
+    /// wildcard. See [Config::host].
+    #[serde(rename = "virtual-hosts")]
+    pub virtual_hosts: HashMap<String, VirtualHost>,
+    /// Any other sections, such as those added by plugins.
+    #[serde(flatten)]
+    sections: Dict,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -36,20 +61,257 @@ pub struct ServerConfig {
     pub motd: String,
     /// The maximum number of players allowed on the server.
     pub max_players: u32,
+    /// Whether to leave the sample of online players out of the server's status.
+    pub hide_online_players: bool,
+    /// Whether to refuse clients connecting with a hostname that matches no virtual host.
+    pub reject_unknown_hosts: bool,
+    /// How long a single tick may take, in milliseconds, before the watchdog reports it as hung.
+    /// Set to 0 or less to disable the watchdog.
+    pub max_tick_time: i64,
+    /// The permission level given to new operators, from 1 to 4.
+    pub op_permission_level: u8,
+    /// The permission level functions run commands with, from 1 to 4.
+    pub function_permission_level: u8,
+    /// Whether only players on the whitelist may join.
+    pub white_list: bool,
+    /// Whether to kick players who are not on the whitelist when it changes, while it is on.
+    pub enforce_whitelist: bool,
+}
+
+/// Networking configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NetworkConfig {
+    /// The maximum number of packets processed from a single connection each tick.
+    pub packets_per_tick: u32,
+    /// The maximum number of packets from a single connection waiting to be processed.
+    pub queue_size: usize,
+    /// What to do when a connection's queue is full.
+    pub overflow: OverflowPolicy,
+    /// The maximum number of packets a connection can send per second. 0 disables the limit.
+    pub rate_limit: u32,
+    /// The maximum number of bytes a connection can send per second. 0 disables the limit.
+    pub byte_rate_limit: u32,
+    /// The maximum number of connection attempts from a single IP address per minute. 0 disables
+    /// the limit.
+    pub connection_rate_limit: u32,
+    /// Whether connections start with a PROXY protocol header, sent by a load balancer.
+    pub proxy_protocol: ProxyProtocol,
+    /// The addresses, in CIDR notation, that are allowed to send PROXY protocol headers. Must not be
+    /// empty if the PROXY protocol is enabled.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+/// Whether connections start with a PROXY protocol header.
+///
+/// See: <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxyProtocol {
+    /// Headers are not read.
+    #[default]
+    Disabled,
+    /// Headers are read from trusted proxies if present.
+    Optional,
+    /// Every connection must come from a trusted proxy and start with a header.
+    Required,
+}
+
+/// Player information forwarding configuration, for servers behind a proxy such as Velocity or
+/// BungeeCord.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ForwardingConfig {
+    /// How the proxy forwards player information.
+    pub mode: ForwardingMode,
+    /// The secret shared with the proxy, used to verify modern forwarding. Must be set to use
+    /// modern forwarding.
+    pub secret: String,
+}
+
+/// Configuration for the GameSpy4 query protocol, which server lists use to find the server's
+/// status and online players over UDP.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct QueryConfig {
+    /// Whether to listen for queries.
+    pub enable: bool,
+    /// The UDP port to listen for queries on.
+    pub port: u16,
+    /// The maximum number of queries per second from a single IP address, or 0 for no limit.
+    pub rate_limit: u32,
+}
+
+/// Configuration for RCON, the remote console that runs commands sent over TCP.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RconConfig {
+    /// Whether to listen for RCON connections. RCON is only started if a password is set.
+    pub enable: bool,
+    /// The TCP port to listen for RCON connections on.
+    pub port: u16,
+    /// The password RCON clients must log in with.
+    pub password: String,
+    /// Whether to show the feedback of commands run over RCON to operators.
+    pub broadcast_to_ops: bool,
+    /// The maximum number of failed logins per minute from a single IP address, or 0 for no limit.
+    pub login_rate_limit: u32,
+}
+
+/// Configuration for the server console, which runs commands typed into the terminal.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConsoleConfig {
+    /// Whether to show the feedback of commands run from the console to operators.
+    pub broadcast_to_ops: bool,
+}
+
+/// Configuration for the list of players shown while holding the tab key.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TabListConfig {
+    /// Text shown above the players, or nothing if empty.
+    pub header: String,
+    /// Text shown below the players, or nothing if empty.
+    pub footer: String,
+}
+
+/// Configuration for the server management protocol, which lets tools manage the server with
+/// JSON-RPC over a WebSocket.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ManagementConfig {
+    /// Whether to listen for management connections.
+    pub enable: bool,
+    /// The IP address to listen for management connections on.
+    pub ip: Ipv4Addr,
+    /// The TCP port to listen for management connections on, or 0 for a random port.
+    pub port: u16,
+    /// The secret clients must send as a bearer token. If empty, a random secret is generated
+    /// every time the server starts.
+    pub secret: String,
+    /// How often to notify clients of the server's status, in seconds, or 0 to never.
+    pub status_heartbeat_interval: u64,
+    /// TLS configuration.
+    pub tls: ManagementTlsConfig,
+}
+
+/// TLS configuration for the server management protocol.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ManagementTlsConfig {
+    /// Whether to require TLS. A keystore must be set if it is.
+    pub enable: bool,
+    /// The path to the PKCS#12 keystore holding the certificate.
+    pub keystore: PathBuf,
+    /// The keystore's password.
+    pub password: String,
+}
+
+/// How a proxy forwards player information, such as their real address and UUID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardingMode {
+    /// Players connect directly, so nothing is forwarded.
+    #[default]
+    None,
+    /// BungeeCord's legacy IP forwarding, appended to the handshake's server address.
+    Legacy,
+    /// Velocity's modern forwarding, sent in a login plugin response signed with the secret.
+    Modern,
+}
+
+/// What to do when a connection sends packets faster than the server processes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Close the connection.
+    Kick,
+    /// Discard the packet.
+    Drop,
+    /// Stop reading from the connection until there is room in the queue.
+    #[default]
+    Backpressure,
 }
 
 // todo: proper error handling for incorrect fields
 impl Config {
     /// Load the configuration from a file, with defaults.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        let config: Config = Figment::new()
+        let mut config: Config = Figment::new()
             // load the default configuration
             .merge(Toml::string(DEFAULT_CONFIG))
             // override it with the user's configuration
             .merge(Toml::file(path))
             .extract()?;
+        config.validate()?;
 
         crate::favicon::load(&config.server.icon)?;
+        config.load_virtual_host_icons()?;
         Ok(config)
     }
+
+    /// Check for values that deserialize but cannot be used.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key, reason| Err(ConfigError::Invalid { key, reason });
+        if self.network.packets_per_tick == 0 {
+            return invalid("network.packets-per-tick", "must be at least 1");
+        }
+        if self.network.queue_size == 0 {
+            return invalid("network.queue-size", "must be at least 1");
+        }
+        if self.network.proxy_protocol != ProxyProtocol::Disabled
+            && self.network.trusted_proxies.is_empty()
+        {
+            let reason = "must list the proxies' addresses when the PROXY protocol is enabled";
+            return invalid("network.trusted-proxies", reason);
+        }
+        if self.forwarding.mode == ForwardingMode::Modern && self.forwarding.secret.is_empty() {
+            return invalid("forwarding.secret", "must be set to use modern forwarding");
+        }
+        Ok(())
+    }
+
+    /// Deserialize a section of the configuration that beacon itself does not use, such as one
+    /// added by a plugin. Returns `None` if the section is missing.
+    pub fn section<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        self.sections
+            .get(key)
+            .map(|section| section.deserialize().map_err(ConfigError::from))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment,
+        providers::{Format, Toml},
+    };
+
+    use crate::{Config, ConfigError, config::DEFAULT_CONFIG};
+
+    fn validate(toml: &str) -> Result<(), ConfigError> {
+        let config: Config = Figment::new()
+            .merge(Toml::string(DEFAULT_CONFIG))
+            .merge(Toml::string(toml))
+            .extract()
+            .unwrap();
+        config.validate()
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("").is_ok());
+        assert!(matches!(
+            validate("network.packets-per-tick = 0"),
+            Err(ConfigError::Invalid {
+                key: "network.packets-per-tick",
+                ..
+            })
+        ));
+        assert!(validate("network.queue-size = 0").is_err());
+        assert!(validate("network.proxy-protocol = \"optional\"").is_err());
+        assert!(validate("forwarding.mode = \"modern\"").is_err());
+    }
 }
@@ -0,0 +1,110 @@
+use std::{
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    sync::{
+        PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use image::{ImageFormat, imageops::FilterType};
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// The icon shown when the configured icon does not exist.
+const DEFAULT_FAVICON: &[u8] = include_bytes!("../../assets/favicon.default.png");
+
+/// The size of a server icon, in pixels.
+const ICON_SIZE: u32 = 64;
+
+/// The server's icon, encoded as a PNG data URI for the status response.
+pub static FAVICON: RwLock<Option<String>> = RwLock::new(None);
+
+/// Incremented every time [FAVICON] changes.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Errors that can occur while loading an icon.
+#[derive(Debug, Error, Diagnostic)]
+pub enum FaviconError {
+    /// The icon could not be read.
+    #[error("failed to read the icon at {}", path.display())]
+    #[diagnostic(help("check that the file is accessible"))]
+    Io {
+        /// The icon's path.
+        path: PathBuf,
+        /// The error that occurred.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The icon is not an image, or could not be encoded.
+    #[error("failed to load the icon at {}", path.display())]
+    #[diagnostic(help("check that the icon is a valid image"))]
+    Image {
+        /// The icon's path.
+        path: PathBuf,
+        /// The error that occurred.
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+/// Load the server's icon into [FAVICON], falling back to the default icon if there is no file at
+/// `path`. An empty path means the server has no icon.
+pub(crate) fn load(path: &Path) -> Result<(), FaviconError> {
+    let favicon = if path.as_os_str().is_empty() {
+        None
+    } else if path.exists() {
+        Some(encode(path)?)
+    } else {
+        Some(encode_bytes(DEFAULT_FAVICON, path)?)
+    };
+
+    set_favicon(favicon);
+    Ok(())
+}
+
+/// Replace the server's icon, which is not counted as a change if it is the same icon.
+pub fn set_favicon(favicon: Option<String>) {
+    let mut current = FAVICON.write().unwrap_or_else(PoisonError::into_inner);
+    if *current != favicon {
+        *current = favicon;
+        GENERATION.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Returns a number that changes every time the server's icon does, which is cheaper to check for
+/// changes than the icon itself.
+pub fn favicon_generation() -> u64 {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// Load an icon, scaling it to 64x64 if needed, and encode it as a PNG data URI.
+pub(crate) fn encode(path: &Path) -> Result<String, FaviconError> {
+    let bytes = std::fs::read(path).map_err(|source| FaviconError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    encode_bytes(&bytes, path)
+}
+
+/// Encode an icon read from `path`.
+fn encode_bytes(bytes: &[u8], path: &Path) -> Result<String, FaviconError> {
+    let image_error = |source| FaviconError::Image {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut icon = image::load_from_memory(bytes).map_err(image_error)?;
+    if icon.width() != ICON_SIZE || icon.height() != ICON_SIZE {
+        icon = icon.resize_exact(ICON_SIZE, ICON_SIZE, FilterType::Lanczos3);
+    }
+
+    let mut png = Vec::new();
+    icon.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(image_error)?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        BASE64_STANDARD.encode(png)
+    ))
+}
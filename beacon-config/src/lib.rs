@@ -9,13 +9,19 @@ use bevy_ecs::prelude::*;
 use miette::Diagnostic;
 use thiserror::Error;
 
-pub use crate::config::Config;
+pub use crate::config::{
+    Config, ConsoleConfig, ForwardingConfig, ForwardingMode, ManagementConfig, ManagementTlsConfig,
+    NetworkConfig, OverflowPolicy, ProxyProtocol, QueryConfig, RconConfig, TabListConfig,
+};
 pub use crate::favicon::*;
 use crate::reload::ConfigManager;
+pub use crate::reload::FileWatcher;
+pub use crate::vhost::{Host, VirtualHost};
 
 mod config;
 mod favicon;
 mod reload;
+mod vhost;
 
 /// Errors that can occur while managing configuration.
 #[derive(Debug, Error, Diagnostic)]
@@ -28,14 +34,46 @@ pub enum ConfigError {
     /// An error occurred while reading the configuration file.
     #[error("failed to read configuration file")]
     #[diagnostic(help("check that the file exists and is a valid TOML file"))]
-    Read(#[from] figment::Error),
+    Read(#[source] Box<figment::Error>),
+
+    /// A setting has a value that cannot be used.
+    #[error("invalid value for `{key}`: {reason}")]
+    #[diagnostic(help("correct the value in the configuration file"))]
+    Invalid {
+        /// The setting's key.
+        key: &'static str,
+        /// Why the value cannot be used.
+        reason: &'static str,
+    },
 
     /// An error occurred while managing the favicon.
     #[error(transparent)]
     #[diagnostic(transparent)]
     Favicon(#[from] favicon::FaviconError),
+
+    /// An error occurred while loading a virtual host's icon.
+    #[error("failed to load the icon for virtual host {host}")]
+    #[diagnostic(help("check that the icon exists and is a valid image"))]
+    VirtualHostIcon {
+        /// The name of the virtual host.
+        host: String,
+        /// The error that occurred.
+        #[source]
+        source: favicon::FaviconError,
+    },
 }
 
+// figment's errors are large, so they are boxed to keep results small
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        Self::Read(Box::new(err))
+    }
+}
+
+/// System set containing the configuration hot-reload system.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConfigSet;
+
 /// Add configuration to the ECS.
 pub fn ecs<P: AsRef<Path>>(
     world: &mut World,
@@ -46,7 +84,7 @@ pub fn ecs<P: AsRef<Path>>(
     world.insert_resource(config.clone());
 
     world.insert_resource(ConfigManager::new(path)?);
-    schedule.add_systems(ConfigManager::reload);
+    schedule.add_systems(ConfigManager::reload.in_set(ConfigSet));
 
     Ok(config)
 }
@@ -13,16 +13,16 @@ use crate::{Config, ConfigError};
 /// How long to wait after recieving a file change event before processing another.
 const DEBOUNCE_TIME: Duration = Duration::from_millis(500);
 
-#[derive(Resource)]
-pub struct ConfigManager {
+/// Watches a file for changes, reporting at most one change every [DEBOUNCE_TIME], as editors
+/// often write a file several times when saving it.
+pub struct FileWatcher {
     _watcher: notify::RecommendedWatcher,
     rx: Receiver<()>,
-    path: PathBuf,
 }
 
-impl ConfigManager {
-    /// Create a new configuration manager that watches the given path for changes.
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+impl FileWatcher {
+    /// Start watching the file at the given path, which must exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, notify::Error> {
         let (tx, rx) = flume::bounded(1);
 
         // create a file watcher
@@ -44,19 +44,38 @@ impl ConfigManager {
                 }
             }
         })?;
-        let path = path.as_ref().to_path_buf();
-        watcher.watch(&path, notify::RecursiveMode::NonRecursive)?;
+        watcher.watch(path.as_ref(), notify::RecursiveMode::NonRecursive)?;
 
         Ok(Self {
             _watcher: watcher,
             rx,
-            path,
+        })
+    }
+
+    /// Whether the file has changed since this was last called.
+    pub fn changed(&self) -> bool {
+        self.rx.try_recv().is_ok()
+    }
+}
+
+#[derive(Resource)]
+pub struct ConfigManager {
+    watcher: FileWatcher,
+    path: PathBuf,
+}
+
+impl ConfigManager {
+    /// Create a new configuration manager that watches the given path for changes.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Ok(Self {
+            watcher: FileWatcher::new(&path)?,
+            path: path.as_ref().to_path_buf(),
         })
     }
 
     /// Check if the configuration file has changed and reload it if necessary.
     pub fn reload(manager: Res<Self>, mut config: ResMut<Config>) {
-        if manager.rx.try_recv().is_ok() {
+        if manager.watcher.changed() {
             // reload config
             match Config::load(&manager.path) {
                 Ok(new_config) => *config = new_config,
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::{Config, ConfigError, favicon};
+
+/// Settings for clients connecting with a particular hostname, so that several servers can share
+/// one address. Anything left out falls back to the `[server]` settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VirtualHost {
+    /// Whether the server should report its status to this host.
+    pub status: Option<bool>,
+    /// The path to this host's icon.
+    pub icon: Option<PathBuf>,
+    /// This host's Message of the Day.
+    pub motd: Option<String>,
+    /// The maximum number of players shown to this host.
+    pub max_players: Option<u32>,
+    /// This host's icon, loaded and encoded for the status response.
+    #[serde(skip)]
+    pub favicon: Option<String>,
+}
+
+/// The settings for a client, from the virtual host matching the hostname it connected with, or
+/// the server if none do.
+#[derive(Debug, Clone, Copy)]
+pub struct Host<'a> {
+    /// The name of the matching virtual host, e.g. `*.example.com`.
+    pub name: Option<&'a str>,
+    /// Whether the server should report its status.
+    pub status: bool,
+    /// The Message of the Day.
+    pub motd: &'a str,
+    /// The maximum number of players.
+    pub max_players: u32,
+    /// The virtual host's encoded icon. If `None`, the server's icon is used.
+    pub favicon: Option<&'a str>,
+}
+
+impl Config {
+    /// Find the settings for a client that connected with `hostname`. Exact matches are preferred,
+    /// then the most specific wildcard, such as `*.example.com`, then a catch-all `*`.
+    ///
+    /// Returns `None` if the client should be rejected, because no virtual host matches and
+    /// `reject-unknown-hosts` is enabled. A missing hostname, sent by older clients, never matches.
+    pub fn host(&self, hostname: Option<&str>) -> Option<Host<'_>> {
+        let matching = hostname.and_then(|hostname| {
+            self.virtual_hosts
+                .iter()
+                .filter(|(pattern, _)| matches(pattern, hostname))
+                .max_by_key(|(pattern, _)| match pattern.starts_with('*') {
+                    true => pattern.len(),
+                    false => usize::MAX,
+                })
+        });
+
+        match matching {
+            Some((name, host)) => Some(self.virtual_host(name, host)),
+            None if self.server.reject_unknown_hosts => None,
+            None => Some(self.default_host()),
+        }
+    }
+
+    /// Every set of host settings: the server's own, used when no virtual host matches, followed
+    /// by each virtual host's.
+    pub fn hosts(&self) -> impl Iterator<Item = Host<'_>> {
+        let virtual_hosts = self
+            .virtual_hosts
+            .iter()
+            .map(|(name, host)| self.virtual_host(name, host));
+        std::iter::once(self.default_host()).chain(virtual_hosts)
+    }
+
+    /// The settings for clients matching no virtual host.
+    fn default_host(&self) -> Host<'_> {
+        Host {
+            name: None,
+            status: self.server.status,
+            motd: &self.server.motd,
+            max_players: self.server.max_players,
+            favicon: None,
+        }
+    }
+
+    /// The settings for a virtual host, falling back to the server's.
+    fn virtual_host<'a>(&'a self, name: &'a str, host: &'a VirtualHost) -> Host<'a> {
+        let server = &self.server;
+        Host {
+            name: Some(name),
+            status: host.status.unwrap_or(server.status),
+            motd: host.motd.as_deref().unwrap_or(&server.motd),
+            max_players: host.max_players.unwrap_or(server.max_players),
+            favicon: host.favicon.as_deref(),
+        }
+    }
+
+    /// Load the icon of every virtual host that has one.
+    pub(crate) fn load_virtual_host_icons(&mut self) -> Result<(), ConfigError> {
+        for (name, host) in &mut self.virtual_hosts {
+            let Some(path) = &host.icon else {
+                continue;
+            };
+            let favicon = favicon::encode(path).map_err(|source| ConfigError::VirtualHostIcon {
+                host: name.clone(),
+                source,
+            })?;
+            host.favicon = Some(favicon);
+        }
+        Ok(())
+    }
+}
+
+/// Whether a hostname matches a virtual host's name, which may start with a `*.` wildcard, or be a
+/// catch-all `*`. Hostnames are case-insensitive.
+fn matches(pattern: &str, hostname: &str) -> bool {
+    let hostname = hostname.to_ascii_lowercase();
+    match pattern.strip_prefix('*') {
+        Some("") => true,
+        Some(suffix) if suffix.starts_with('.') => {
+            hostname.len() > suffix.len() && hostname.ends_with(&suffix.to_ascii_lowercase())
+        }
+        _ => pattern.eq_ignore_ascii_case(&hostname),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment,
+        providers::{Format, Toml},
+    };
+
+    use crate::{Config, config::DEFAULT_CONFIG};
+
+    fn config(toml: &str) -> Config {
+        Figment::new()
+            .merge(Toml::string(DEFAULT_CONFIG))
+            .merge(Toml::string(toml))
+            .extract()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_host() {
+        let config = config(
+            r#"
+            [virtual-hosts."play.example.com"]
+            motd = "exact"
+            [virtual-hosts."*.example.com"]
+            motd = "wildcard"
+            max-players = 5
+            [virtual-hosts."*.eu.example.com"]
+            motd = "specific"
+            "#,
+        );
+
+        let motd = |hostname| config.host(hostname).unwrap().motd;
+        assert_eq!(motd(Some("PLAY.example.com")), "exact");
+        assert_eq!(motd(Some("lobby.example.com")), "wildcard");
+        assert_eq!(motd(Some("lobby.eu.example.com")), "specific");
+        assert_eq!(motd(Some("example.com")), "A Beacon Server");
+        assert_eq!(motd(None), "A Beacon Server");
+
+        let host = config.host(Some("lobby.example.com")).unwrap();
+        assert_eq!(host.name, Some("*.example.com"));
+        assert_eq!(host.max_players, 5);
+        assert!(host.status);
+    }
+
+    #[test]
+    fn test_reject_unknown_hosts() {
+        let config = config(
+            r#"
+            server.reject-unknown-hosts = true
+            [virtual-hosts."*"]
+            motd = "catch-all"
+            [virtual-hosts."play.example.com"]
+            status = false
+            "#,
+        );
+        assert!(!config.host(Some("play.example.com")).unwrap().status);
+        assert_eq!(config.host(Some("other.net")).unwrap().motd, "catch-all");
+        assert!(config.host(None).is_none());
+    }
+}
@@ -0,0 +1,434 @@
+use std::{net::IpAddr, path::Path, time::SystemTime};
+
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
+use beacon_net::{
+    conn::{PacketSender, RemoteAddr},
+    player::PlayerIdentity,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+pub use self::file::ListError;
+use self::file::ListFile;
+use crate::{App, PlayerLogin, TickSet, command::PermissionLevel};
+
+pub(crate) mod date;
+mod file;
+
+/// The operators, who are given a [PermissionLevel](crate::command::PermissionLevel) when they
+/// join.
+pub type Operators = AccessList<Operator>;
+/// The players allowed to join while the whitelist is on.
+pub type Whitelist = AccessList<GameProfile>;
+/// The players who may not join.
+pub type BannedPlayers = AccessList<PlayerBan>;
+/// The IP addresses that may not join.
+pub type BannedIps = AccessList<IpBan>;
+
+/// An entry of an [AccessList].
+pub trait ListEntry: Send + Sync + 'static {
+    /// What identifies the entry. A list has at most one entry with each key.
+    type Key: PartialEq;
+
+    /// The entry's key.
+    fn key(&self) -> Self::Key;
+}
+
+/// A list of players or addresses that the server treats differently, such as its operators or
+/// bans. Entries are kept in the order they were added.
+#[derive(Resource, Debug, Clone)]
+pub struct AccessList<T: ListEntry> {
+    entries: Vec<T>,
+}
+
+impl<T: ListEntry> Default for AccessList<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T: ListEntry> AccessList<T> {
+    /// The entry with the given key.
+    pub fn get(&self, key: &T::Key) -> Option<&T> {
+        self.entries.iter().find(|entry| entry.key() == *key)
+    }
+
+    /// Whether the list has an entry with the given key.
+    pub fn contains(&self, key: &T::Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Add an entry, replacing any entry with the same key. Returns the entry it replaced.
+    pub fn insert(&mut self, entry: T) -> Option<T> {
+        let key = entry.key();
+        match self.entries.iter_mut().find(|old| old.key() == key) {
+            Some(old) => Some(std::mem::replace(old, entry)),
+            None => {
+                self.entries.push(entry);
+                None
+            }
+        }
+    }
+
+    /// Remove the entry with the given key, returning it.
+    pub fn remove(&mut self, key: &T::Key) -> Option<T> {
+        let index = self.entries.iter().position(|entry| entry.key() == *key)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Iterate over the entries, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter()
+    }
+
+    /// How many entries the list has.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the list has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A player's name and UUID, which identify them even while they are offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameProfile {
+    /// The player's UUID.
+    #[serde(
+        serialize_with = "serialize_uuid",
+        deserialize_with = "deserialize_uuid"
+    )]
+    pub uuid: u128,
+    /// The player's username.
+    pub name: String,
+}
+
+impl From<&PlayerIdentity> for GameProfile {
+    fn from(identity: &PlayerIdentity) -> Self {
+        Self {
+            uuid: identity.uuid,
+            name: identity.name.clone(),
+        }
+    }
+}
+
+impl ListEntry for GameProfile {
+    type Key = u128;
+
+    fn key(&self) -> u128 {
+        self.uuid
+    }
+}
+
+/// An operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operator {
+    /// Who the operator is.
+    #[serde(flatten)]
+    pub profile: GameProfile,
+    /// The permission level the operator is given when they join.
+    pub level: u8,
+    /// Whether the operator can join when the server is full.
+    #[serde(rename = "bypassesPlayerLimit", default)]
+    pub bypasses_player_limit: bool,
+}
+
+impl ListEntry for Operator {
+    type Key = u128;
+
+    fn key(&self) -> u128 {
+        self.profile.uuid
+    }
+}
+
+/// Why and until when a player or address is banned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ban {
+    /// When the ban was made.
+    #[serde(with = "date")]
+    pub created: SystemTime,
+    /// Who made the ban, e.g. `Server` for the console.
+    pub source: String,
+    /// When the ban ends, or `None` if it is permanent.
+    #[serde(with = "date::expiry")]
+    pub expires: Option<SystemTime>,
+    /// Why the ban was made, which is shown to the banned player.
+    pub reason: String,
+}
+
+impl Ban {
+    /// The reason used when a ban is made without one.
+    pub const DEFAULT_REASON: &str = "Banned by an operator.";
+
+    /// A permanent ban, made now.
+    pub fn new(source: impl Into<String>, reason: Option<String>) -> Self {
+        Self {
+            created: SystemTime::now(),
+            source: source.into(),
+            expires: None,
+            reason: reason.unwrap_or_else(|| Self::DEFAULT_REASON.to_string()),
+        }
+    }
+
+    /// Whether the ban has ended.
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+}
+
+/// A banned player.
+#[derive(Debug, Clone, PartialEq, Eq, Deref, Serialize, Deserialize)]
+pub struct PlayerBan {
+    /// Who is banned.
+    #[serde(flatten)]
+    pub profile: GameProfile,
+    /// The ban's details.
+    #[deref]
+    #[serde(flatten)]
+    pub ban: Ban,
+}
+
+impl ListEntry for PlayerBan {
+    type Key = u128;
+
+    fn key(&self) -> u128 {
+        self.profile.uuid
+    }
+}
+
+/// A banned IP address.
+#[derive(Debug, Clone, PartialEq, Eq, Deref, Serialize, Deserialize)]
+pub struct IpBan {
+    /// The banned address.
+    pub ip: IpAddr,
+    /// The ban's details.
+    #[deref]
+    #[serde(flatten)]
+    pub ban: Ban,
+}
+
+impl ListEntry for IpBan {
+    type Key = IpAddr;
+
+    fn key(&self) -> IpAddr {
+        self.ip
+    }
+}
+
+/// Find a player's profile by name, among the players who are online or on one of the lists.
+/// Names are compared case-insensitively, as usernames are unique regardless of case.
+pub fn find_profile(world: &mut World, name: &str) -> Option<GameProfile> {
+    let mut query = world.query::<&PlayerIdentity>();
+    let online = query
+        .iter(world)
+        .find(|identity| identity.name.eq_ignore_ascii_case(name))
+        .map(GameProfile::from);
+
+    let matches = |profile: &&GameProfile| profile.name.eq_ignore_ascii_case(name);
+    online
+        .or_else(|| {
+            let operators = world.resource::<Operators>().iter();
+            operators.map(|op| &op.profile).find(matches).cloned()
+        })
+        .or_else(|| world.resource::<Whitelist>().iter().find(matches).cloned())
+        .or_else(|| {
+            let bans = world.resource::<BannedPlayers>().iter();
+            bans.map(|ban| &ban.profile).find(matches).cloned()
+        })
+}
+
+/// Keep the access lists in sync with vanilla's JSON files in the given directory, and check
+/// players against them when they log in.
+pub(crate) fn configure(app: &mut App, dir: &Path) -> Result<(), ListError> {
+    ListFile::<Operator>::add(app, dir.join("ops.json"))?;
+    ListFile::<GameProfile>::add(app, dir.join("whitelist.json"))?;
+    ListFile::<PlayerBan>::add(app, dir.join("banned-players.json"))?;
+    ListFile::<IpBan>::add(app, dir.join("banned-ips.json"))?;
+
+    app.add_observer(check_login)
+        .add_systems(TickSet::NetworkOut, (sync_permissions, enforce_whitelist));
+    Ok(())
+}
+
+/// Refuse players who are banned, or are not on the whitelist while it is on, once they have said
+/// who they are. Operators are given their permission level.
+fn check_login(
+    mut event: On<PlayerLogin>,
+    config: Res<Config>,
+    operators: Res<Operators>,
+    whitelist: Res<Whitelist>,
+    (mut banned_players, mut banned_ips): (ResMut<BannedPlayers>, ResMut<BannedIps>),
+    query: Query<(&PlayerIdentity, &RemoteAddr)>,
+    mut commands: Commands,
+) {
+    let Ok((identity, addr)) = query.get(event.entity) else {
+        return;
+    };
+    let uuid = identity.uuid;
+
+    let refusal = if let Some(reason) = ban_reason(&mut banned_players, &uuid) {
+        Some(Text::translate(
+            "multiplayer.disconnect.banned.reason",
+            [reason],
+        ))
+    } else if config.server.white_list && !whitelist.contains(&uuid) && !operators.contains(&uuid) {
+        Some(not_whitelisted())
+    } else {
+        ban_reason(&mut banned_ips, &addr.0.ip())
+            .map(|reason| Text::translate("multiplayer.disconnect.banned_ip.reason", [reason]))
+    };
+    if refusal.is_some() {
+        event.refusal = refusal;
+        return;
+    }
+
+    if let Some(op) = operators.get(&uuid) {
+        commands
+            .entity(event.entity)
+            .insert(PermissionLevel(op.level));
+    }
+}
+
+/// Why the entry with the given key is banned, if it is. Bans that have expired are removed.
+fn ban_reason<T>(bans: &mut ResMut<AccessList<T>>, key: &T::Key) -> Option<String>
+where
+    T: ListEntry + std::ops::Deref<Target = Ban>,
+{
+    let (expired, reason) = bans
+        .get(key)
+        .map(|ban| (ban.is_expired(), ban.reason.clone()))?;
+    if expired {
+        bans.remove(key);
+        return None;
+    }
+    Some(reason)
+}
+
+/// Give operators their permission level when the operator list changes, e.g. when `ops.json` is
+/// edited, and take it away from players who are no longer operators.
+fn sync_permissions(
+    operators: Res<Operators>,
+    query: Query<(Entity, &PlayerIdentity, Option<&PermissionLevel>)>,
+    mut commands: Commands,
+) {
+    if !operators.is_changed() {
+        return;
+    }
+    for (entity, identity, level) in query.iter() {
+        match (operators.get(&identity.uuid), level) {
+            (Some(op), level) if level != Some(&PermissionLevel(op.level)) => {
+                commands.entity(entity).insert(PermissionLevel(op.level));
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<PermissionLevel>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Kick players who are not on the whitelist when it changes or is turned on, if
+/// `enforce-whitelist` is on.
+fn enforce_whitelist(
+    config: Res<Config>,
+    operators: Res<Operators>,
+    whitelist: Res<Whitelist>,
+    query: Query<(&PlayerIdentity, &ProtocolState, &PacketSender)>,
+) -> Result<()> {
+    let server = &config.server;
+    if !(server.white_list && server.enforce_whitelist) {
+        return Ok(());
+    }
+    if !config.is_changed() && !whitelist.is_changed() {
+        return Ok(());
+    }
+
+    for (identity, state, sender) in query.iter() {
+        let uuid = identity.uuid;
+        if whitelist.contains(&uuid) || operators.contains(&uuid) || sender.is_closed() {
+            continue;
+        }
+        info!("kicking {}, who is not on the whitelist", identity.name);
+        sender.kick(*state, not_whitelisted())?;
+    }
+    Ok(())
+}
+
+/// What players who are not on the whitelist are told.
+fn not_whitelisted() -> Text {
+    Text::translate("multiplayer.disconnect.not_whitelisted", [] as [Text; 0])
+}
+
+/// Serialize a UUID in its hyphenated form, as vanilla's lists store them.
+fn serialize_uuid<S: Serializer>(uuid: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    Uuid::from_u128(*uuid).serialize(serializer)
+}
+
+fn deserialize_uuid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    Uuid::deserialize(deserializer).map(|uuid| uuid.as_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{AccessList, Ban, GameProfile, PlayerBan};
+
+    #[test]
+    fn test_access_list() {
+        let profile = |uuid, name: &str| GameProfile {
+            uuid,
+            name: name.to_string(),
+        };
+        let mut list = AccessList::default();
+        assert_eq!(list.insert(profile(1, "Steve")), None);
+        assert_eq!(list.insert(profile(2, "Alex")), None);
+        assert_eq!(list.insert(profile(1, "Steve2")), Some(profile(1, "Steve")));
+
+        let names: Vec<_> = list.iter().map(|profile| profile.name.as_str()).collect();
+        assert_eq!(names, ["Steve2", "Alex"]);
+        assert_eq!(list.remove(&2), Some(profile(2, "Alex")));
+        assert!(!list.contains(&2));
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn test_vanilla_format() {
+        let json = r#"[
+  {
+    "uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+    "name": "Notch",
+    "created": "2024-01-31 13:00:00 +0100",
+    "source": "Server",
+    "expires": "forever",
+    "reason": "Banned by an operator."
+  }
+]"#;
+        let bans: Vec<PlayerBan> = serde_json::from_str(json).unwrap();
+        let ban = PlayerBan {
+            profile: GameProfile {
+                uuid: 0x069a79f444e94726a5befca90e38aaf5,
+                name: "Notch".to_string(),
+            },
+            ban: Ban {
+                created: UNIX_EPOCH + Duration::from_secs(1_706_702_400),
+                source: "Server".to_string(),
+                expires: None,
+                reason: Ban::DEFAULT_REASON.to_string(),
+            },
+        };
+        assert_eq!(bans.first(), Some(&ban));
+
+        let json = serde_json::to_value(&ban).unwrap();
+        assert_eq!(json["uuid"], "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(json["created"], "2024-01-31 12:00:00 +0000");
+    }
+}
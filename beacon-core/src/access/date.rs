@@ -0,0 +1,160 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+/// What `expires` is set to for permanent bans.
+const FOREVER: &str = "forever";
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Format a time in UTC, like vanilla formats the dates in its ban lists, e.g.
+/// `2024-01-31 12:00:00 +0000`.
+pub(crate) fn format(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    let (days, time) = (
+        seconds.div_euclid(SECONDS_PER_DAY),
+        seconds.rem_euclid(SECONDS_PER_DAY),
+    );
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} +0000",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Parse a time, in any time zone.
+pub(crate) fn parse(date: &str) -> Option<SystemTime> {
+    let (date, rest) = date.trim().split_once(' ')?;
+    let (time, zone) = rest.split_once(' ')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // the zone is an offset from UTC, e.g. `+0100`
+    let (sign, offset) = match zone.split_at_checked(1)? {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return None,
+    };
+    let offset: i64 = offset.parse().ok().filter(|_| offset.len() == 4)?;
+    let offset = sign * (offset / 100 * 3600 + offset % 100 * 60);
+
+    let seconds =
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second
+            - offset;
+    match u64::try_from(seconds) {
+        Ok(seconds) => Some(UNIX_EPOCH + Duration::from_secs(seconds)),
+        Err(_) => Some(UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())),
+    }
+}
+
+/// The number of days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+///
+/// See: <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The date that is a number of days since 1970-01-01, as a year, month and day.
+///
+/// See: <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Serialize a time, for `#[serde(with = "date")]`.
+pub(crate) fn serialize<S: Serializer>(
+    time: &SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*time))
+}
+
+/// Deserialize a time, for `#[serde(with = "date")]`.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<SystemTime, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    parse(&date).ok_or_else(|| D::Error::custom(format!("invalid date {date:?}")))
+}
+
+/// A time that may be `forever`, for when bans expire.
+pub(crate) mod expiry {
+    use std::time::SystemTime;
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    use super::FOREVER;
+
+    /// Serialize an expiry, for `#[serde(with = "date::expiry")]`.
+    pub(crate) fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::serialize(time, serializer),
+            None => serializer.serialize_str(FOREVER),
+        }
+    }
+
+    /// Deserialize an expiry, for `#[serde(with = "date::expiry")]`.
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        if date == FOREVER {
+            return Ok(None);
+        }
+        match super::parse(&date) {
+            Some(time) => Ok(Some(time)),
+            None => Err(D::Error::custom(format!("invalid date {date:?}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{format, parse};
+
+    #[test]
+    fn test_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_706_702_400);
+        assert_eq!(format(time), "2024-01-31 12:00:00 +0000");
+        assert_eq!(parse("2024-01-31 12:00:00 +0000"), Some(time));
+        assert_eq!(parse("2024-01-31 13:30:00 +0130"), Some(time));
+        assert_eq!(format(UNIX_EPOCH), "1970-01-01 00:00:00 +0000");
+        assert_eq!(
+            format(UNIX_EPOCH - Duration::from_secs(1)),
+            "1969-12-31 23:59:59 +0000"
+        );
+        assert_eq!(parse("2024-02-30"), None);
+        assert_eq!(parse("2024-13-01 00:00:00 +0000"), None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    io::{self, ErrorKind},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use beacon_config::{ConfigSet, FileWatcher};
+use bevy_ecs::prelude::*;
+use miette::{Diagnostic, Report};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    App, TickSet,
+    access::{AccessList, ListEntry},
+};
+
+/// Errors that can occur while reading or writing an access list's file.
+#[derive(Debug, Error, Diagnostic)]
+pub enum ListError {
+    /// The file could not be read or written.
+    #[error("failed to access {}", path.display())]
+    #[diagnostic(help("check that the file is accessible"))]
+    Io {
+        /// The file's path.
+        path: PathBuf,
+        /// The error that occurred.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The file is not a list in vanilla's format.
+    #[error("failed to parse {}", path.display())]
+    #[diagnostic(help("check that the file is a JSON list in the same format as vanilla's"))]
+    Parse {
+        /// The file's path.
+        path: PathBuf,
+        /// The error that occurred.
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl<T: ListEntry + Serialize + DeserializeOwned> AccessList<T> {
+    /// Read a list from a file in vanilla's JSON format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ListError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| ListError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_json(&json).map_err(|source| ListError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Write the list to a file in vanilla's JSON format.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ListError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json()).map_err(|source| ListError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let entries: Vec<T> = serde_json::from_str(json)?;
+        let mut list = Self::default();
+        for entry in entries {
+            list.insert(entry);
+        }
+        Ok(list)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap_or_else(|_| "[]".into())
+    }
+}
+
+/// The file an [AccessList] is kept in sync with.
+#[derive(Resource)]
+pub(crate) struct ListFile<T> {
+    path: PathBuf,
+    watcher: Option<FileWatcher>,
+    /// What the file was last read or written as, so lists are only written when they change.
+    contents: String,
+    _entry: PhantomData<fn() -> T>,
+}
+
+impl<T: ListEntry + Serialize + DeserializeOwned> ListFile<T> {
+    /// Load a list from its file, creating an empty file if there is none. The list is reloaded
+    /// when the file changes, and saved when the list changes.
+    pub(crate) fn add(app: &mut App, path: PathBuf) -> Result<(), ListError> {
+        let list = match AccessList::<T>::load(&path) {
+            Ok(list) => list,
+            Err(ListError::Io { source, .. }) if source.kind() == ErrorKind::NotFound => {
+                let list = AccessList::default();
+                list.save(&path)?;
+                list
+            }
+            Err(err) => return Err(err),
+        };
+
+        let watcher = match FileWatcher::new(&path) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                warn!(path = %path.display(), %err, "changes to the file will not be loaded");
+                None
+            }
+        };
+        let file = Self {
+            path,
+            watcher,
+            contents: list.to_json(),
+            _entry: PhantomData,
+        };
+        app.insert_resource(file)
+            .insert_resource(list)
+            .add_systems(TickSet::PreTick, Self::reload.after(ConfigSet))
+            .add_systems(TickSet::PostTick, Self::save);
+        Ok(())
+    }
+
+    /// Reload the list when its file changes.
+    fn reload(mut file: ResMut<Self>, mut list: ResMut<AccessList<T>>) {
+        if !file.watcher.as_ref().is_some_and(FileWatcher::changed) {
+            return;
+        }
+        match AccessList::<T>::load(&file.path) {
+            Ok(new) => {
+                let contents = new.to_json();
+                if contents != file.contents {
+                    info!("reloaded {}", file.path.display());
+                    file.contents = contents;
+                    *list = new;
+                }
+            }
+            Err(err) => error!("{:?}", Report::new(err)),
+        }
+    }
+
+    /// Write the list to its file when it changes.
+    fn save(mut file: ResMut<Self>, list: Res<AccessList<T>>) {
+        if !list.is_changed() {
+            return;
+        }
+        let contents = list.to_json();
+        if contents == file.contents {
+            return;
+        }
+        match list.save(&file.path) {
+            Ok(()) => file.contents = contents,
+            Err(err) => error!("{:?}", Report::new(err)),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use beacon_config::Config;
+use miette::Result;
+
+use crate::{
+    AccessPlugin, App, BeaconPlugin, BeaconServer, ConfigPlugin, NetworkPlugin, ServerHandle,
+    ServerState, management::ManagementServer, query::QueryServer, rcon::RconServer,
+};
+
+/// Builder for a [BeaconServer], made up of [plugins](BeaconPlugin).
+#[derive(Default)]
+pub struct BeaconServerBuilder {
+    plugins: Vec<Box<dyn BeaconPlugin>>,
+}
+
+impl BeaconServerBuilder {
+    /// Add a plugin to the server. Plugins are built in the order they are added.
+    pub fn add_plugin<P: BeaconPlugin>(mut self, plugin: P) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Add the plugins that make up a vanilla beacon server, loading configuration from the given
+    /// path. The access lists are kept alongside the configuration file.
+    pub fn add_default_plugins<P: Into<PathBuf>>(self, config_path: P) -> Self {
+        let config_path = config_path.into();
+        let dir = config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        self.add_plugin(ConfigPlugin::new(config_path))
+            .add_plugin(AccessPlugin::new(dir))
+            .add_plugin(NetworkPlugin)
+    }
+
+    /// Build every plugin and bind the query, RCON and management servers.
+    pub async fn build(self) -> Result<BeaconServer> {
+        let mut app = App::new();
+
+        // build plugins
+        let mut names = HashSet::new();
+        for plugin in &self.plugins {
+            let name = plugin.name();
+            if !names.insert(name) {
+                miette::bail!("plugin {name} was added more than once");
+            }
+            debug!(plugin = name, "building plugin");
+            plugin.build(&mut app)?;
+        }
+
+        if !app.world.contains_resource::<Config>() {
+            miette::bail!(
+                help = "add the ConfigPlugin, or insert a Config resource from a plugin",
+                "no configuration has been loaded"
+            );
+        }
+
+        let plugins: Vec<_> = self.plugins.iter().map(|plugin| plugin.name()).collect();
+        let query = QueryServer::bind(&mut app, &plugins).await?;
+        let rcon = RconServer::bind(&mut app).await?;
+        let management = ManagementServer::bind(&mut app).await?;
+
+        let state = ServerState {
+            cancel_token: app.world.resource::<ServerHandle>().cancel_token.clone(),
+        };
+
+        Ok(BeaconServer {
+            management,
+            query,
+            rcon,
+            state: Arc::new(state),
+
+            world: app.world,
+            schedule: app.schedule,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use beacon_net::text::Text;
+    use bevy_ecs::prelude::*;
+    use miette::Result;
+    use serde::Deserialize;
+
+    use crate::{
+        AccessPlugin, App, BeaconPlugin, BeaconServer, CommandSender, ConfigPlugin, NetworkPlugin,
+        Operators, PlayerList, TickSet, command::literal, run_command,
+    };
+
+    /// A directory of its own for each test, with a configuration that listens on any free port.
+    fn dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("beacon-{test}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("beacon.toml"),
+            "[server]\nip = \"127.0.0.1\"\nport = 0\n\n[example]\ngreeting = \"hi\"\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[derive(Resource, Debug, Default, Deserialize)]
+    struct Example {
+        greeting: String,
+    }
+
+    #[derive(Resource, Default)]
+    struct Ticks(u32);
+
+    struct ExamplePlugin;
+
+    impl BeaconPlugin for ExamplePlugin {
+        fn build(&self, app: &mut App) -> Result<()> {
+            app.add_config_section::<Example>("example")?
+                .init_resource::<Ticks>()
+                .add_systems(TickSet::GameLogic, |mut ticks: ResMut<Ticks>| ticks.0 += 1);
+            app.add_command(literal("greet").executes(|ctx| {
+                let greeting = ctx.world.resource::<Example>().greeting.clone();
+                ctx.feedback(greeting);
+                Ok(())
+            }));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_plugins() {
+        let config = dir("duplicate").join("beacon.toml");
+        let err = BeaconServer::builder()
+            .add_plugin(ConfigPlugin::new(&config))
+            .add_plugin(ConfigPlugin::new(&config))
+            .build()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("added more than once"));
+    }
+
+    #[tokio::test]
+    async fn test_plugin() {
+        let config = dir("plugin").join("beacon.toml");
+        let mut server = BeaconServer::builder()
+            .add_plugin(ConfigPlugin::new(&config))
+            .add_plugin(ExamplePlugin)
+            .build()
+            .await
+            .unwrap();
+
+        server.tick(None);
+        assert_eq!(server.world.resource::<Ticks>().0, 1);
+        let output = run_command(&mut server.world, CommandSender::Console, "greet");
+        assert_eq!(output, [Text::from("hi")]);
+    }
+
+    #[tokio::test]
+    async fn test_custom_server() {
+        // a server without the network still needs its configuration
+        let err = BeaconServer::builder()
+            .add_plugin(ExamplePlugin)
+            .build()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("no configuration"));
+
+        // the access lists can be left out, or kept somewhere else
+        let dir = dir("custom");
+        let config = dir.join("beacon.toml");
+        std::fs::create_dir_all(dir.join("lists")).unwrap();
+        BeaconServer::builder()
+            .add_plugin(ConfigPlugin::new(&config))
+            .add_plugin(NetworkPlugin)
+            .build()
+            .await
+            .unwrap();
+        let mut server = BeaconServer::builder()
+            .add_plugin(ConfigPlugin::new(&config))
+            .add_plugin(AccessPlugin::new(dir.join("lists")))
+            .add_plugin(NetworkPlugin)
+            .add_plugin(ExamplePlugin)
+            .build()
+            .await
+            .unwrap();
+        assert!(dir.join("lists/ops.json").exists());
+        server.tick(None);
+        assert!(server.world.resource::<Operators>().is_empty());
+        assert!(server.world.resource::<PlayerList>().is_empty());
+    }
+}
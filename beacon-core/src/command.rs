@@ -0,0 +1,465 @@
+use std::net::SocketAddr;
+
+use beacon_codec::{ProtocolState, types::VarInt};
+use beacon_config::Config;
+use beacon_net::{
+    client::command::{CommandSuggestions, SuggestionMatch, SystemChat},
+    conn::PacketSender,
+    entity::{Position, Rotation},
+    packet::PacketEvent,
+    player::PlayerIdentity,
+    server::{ChatCommand, ChatCommandSigned, CommandSuggestion},
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+
+pub use self::argument::{
+    Argument, ArgumentType, Coordinates, FromArgument, StringKind, WorldCoordinate,
+};
+pub use self::dispatcher::{
+    CommandBuilder, CommandContext, CommandDispatcher, Executor, SuggestionProvider, argument,
+    literal,
+};
+pub use self::nbt::Nbt;
+pub use self::reader::{CommandError, StringReader};
+pub use self::selector::{
+    EntityArgument, EntitySelector, EntityTypeTags, Filter, Predicate, Range, SelectorKind,
+    SelectorPredicates, Sort,
+};
+use crate::{App, TickSet};
+
+mod argument;
+mod dispatcher;
+mod nbt;
+mod reader;
+mod selector;
+
+/// How much a sender is trusted to do, from 0 to 4. Players without one have level 0, and the
+/// console has level 4.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PermissionLevel(pub u8);
+
+impl PermissionLevel {
+    /// Commands anyone can use.
+    pub const ALL: u8 = 0;
+    /// Lets players bypass spawn protection.
+    pub const MODERATOR: u8 = 1;
+    /// Commands that change the game, such as `/gamemode`.
+    pub const GAMEMASTER: u8 = 2;
+    /// Commands that manage players, such as `/kick`.
+    pub const ADMIN: u8 = 3;
+    /// Every command, including `/stop`.
+    pub const OWNER: u8 = 4;
+}
+
+/// Who is running a command, which decides what it may do and who sees its feedback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandSender {
+    /// The server console.
+    Console,
+    /// A remote console client.
+    Rcon(SocketAddr),
+    /// A player.
+    Player(Entity),
+    /// A function, which runs at the configured `function-permission-level`.
+    Function,
+}
+
+impl CommandSender {
+    /// The sender's [PermissionLevel], which decides which commands it can use.
+    pub fn permission_level(&self, world: &World) -> u8 {
+        match self {
+            Self::Console | Self::Rcon(_) => PermissionLevel::OWNER,
+            Self::Player(entity) => world
+                .get::<PermissionLevel>(*entity)
+                .map_or(PermissionLevel::ALL, |level| level.0),
+            Self::Function => world.resource::<Config>().server.function_permission_level,
+        }
+    }
+
+    /// The sender's name, e.g. to record who made a ban.
+    pub fn name(&self, world: &World) -> String {
+        match self {
+            Self::Console | Self::Function => "Server".to_string(),
+            Self::Rcon(_) => "Rcon".to_string(),
+            Self::Player(entity) => world
+                .get::<PlayerIdentity>(*entity)
+                .map_or_else(|| "Server".to_string(), |identity| identity.name.clone()),
+        }
+    }
+
+    /// The entity running the command, if it is one.
+    pub fn entity(&self) -> Option<Entity> {
+        match self {
+            Self::Player(entity) => Some(*entity),
+            _ => None,
+        }
+    }
+
+    /// Where the command is being run from, which relative coordinates and selectors start from.
+    /// Senders that are not entities run commands from the world's origin.
+    pub fn position(&self, world: &World) -> Position {
+        self.entity()
+            .and_then(|entity| world.get::<Position>(entity))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Which way the sender is looking, which local coordinates are relative to.
+    pub fn rotation(&self, world: &World) -> Rotation {
+        self.entity()
+            .and_then(|entity| world.get::<Rotation>(entity))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The name to show operators this sender's feedback under, if it should be shown to them.
+    fn broadcast_name(&self, config: &Config) -> Option<&'static str> {
+        match self {
+            Self::Console if config.console.broadcast_to_ops => Some("Server"),
+            Self::Rcon(_) if config.rcon.broadcast_to_ops => Some("Rcon"),
+            _ => None,
+        }
+    }
+}
+
+impl App {
+    /// Register a command with the [CommandDispatcher], merging it with any command that starts
+    /// with the same literal. Returns the node the command starts at, which other commands can
+    /// [redirect](CommandBuilder::redirect) to.
+    pub fn add_command(&mut self, command: CommandBuilder) -> usize {
+        self.world
+            .resource_mut::<CommandDispatcher>()
+            .register(command)
+    }
+}
+
+/// Event triggered to run a command, before the [CommandDispatcher] runs it. Observers can handle
+/// commands the dispatcher does not know by writing their feedback to `output` and setting
+/// `handled`.
+#[derive(Event, Debug)]
+pub struct RunCommand {
+    /// Who is running the command.
+    pub sender: CommandSender,
+    /// The command, without a leading `/`.
+    pub command: String,
+    /// The feedback to show the sender, one message per line.
+    pub output: Vec<Text>,
+    /// Whether any observer recognised the command, so the dispatcher should not run it.
+    pub handled: bool,
+}
+
+/// Event triggered to suggest how to complete a partly typed command. Observers can add
+/// suggestions for commands the [CommandDispatcher] does not know.
+#[derive(Event, Debug)]
+pub struct CompleteCommand {
+    /// Who is typing the command.
+    pub sender: CommandSender,
+    /// The command typed so far, without a leading `/`.
+    pub command: String,
+    /// Suggestions to replace the last word of the command with.
+    pub suggestions: Vec<String>,
+}
+
+/// Event triggered to show a message to every operator, such as the feedback of a command run
+/// over RCON.
+#[derive(Event, Debug, Clone)]
+pub struct BroadcastToOps {
+    /// The message to show.
+    pub message: Text,
+}
+
+/// Run a command in the world, returning its feedback.
+pub fn run_command(world: &mut World, sender: CommandSender, command: &str) -> Vec<Text> {
+    let mut event = RunCommand {
+        sender,
+        command: command.trim_start_matches('/').to_string(),
+        output: Vec::new(),
+        handled: false,
+    };
+    world.trigger_ref(&mut event);
+    if !event.handled {
+        let dispatcher = world.resource::<CommandDispatcher>().clone();
+        match dispatcher.execute(world, event.sender.clone(), &event.command) {
+            Ok(output) => event.output.extend(output),
+            Err(err) => event.output.extend(err.lines()),
+        }
+    }
+
+    if let Some(name) = event.sender.broadcast_name(world.resource::<Config>()) {
+        for line in &event.output {
+            let message = Text::translate("chat.type.admin", [Text::from(name), line.clone()]);
+            // the console shows its own feedback
+            if event.sender != CommandSender::Console {
+                info!("{message}");
+            }
+            world.trigger(BroadcastToOps { message });
+        }
+    }
+    event.output
+}
+
+/// Suggest how to complete the last word of a partly typed command.
+pub fn complete_command(world: &mut World, sender: CommandSender, command: &str) -> Vec<String> {
+    let command = command.trim_start_matches('/');
+    let (start, suggestions) = suggest_command(world, sender, command);
+    let word = command.rfind(' ').map_or(0, |i| i + 1);
+    let mut suggestions: Vec<String> = suggestions
+        .into_iter()
+        .filter_map(|suggestion| {
+            let replaced = command.get(start..word)?;
+            suggestion.strip_prefix(replaced).map(str::to_string)
+        })
+        .collect();
+    suggestions.dedup();
+    suggestions
+}
+
+/// Suggest how to complete a partly typed command, without a leading `/`. Returns the byte offset
+/// that the suggestions replace the rest of the command from.
+fn suggest_command(
+    world: &mut World,
+    sender: CommandSender,
+    command: &str,
+) -> (usize, Vec<String>) {
+    let dispatcher = world.resource::<CommandDispatcher>().clone();
+    let (dispatched_start, dispatched) = dispatcher.suggest(world, &sender, command);
+
+    // observers suggest replacements for the last word
+    let mut event = CompleteCommand {
+        sender,
+        command: command.to_string(),
+        suggestions: Vec::new(),
+    };
+    world.trigger_ref(&mut event);
+    let word = command.rfind(' ').map_or(0, |i| i + 1);
+
+    let start = match event.suggestions.is_empty() {
+        true => dispatched_start,
+        false => dispatched_start.min(word),
+    };
+    let mut suggestions: Vec<String> = dispatched
+        .into_iter()
+        .map(|suggestion| (dispatched_start, suggestion))
+        .chain(
+            event
+                .suggestions
+                .into_iter()
+                .map(|suggestion| (word, suggestion)),
+        )
+        .map(|(offset, suggestion)| format!("{}{suggestion}", &command[start..offset]))
+        .collect();
+    suggestions.sort();
+    suggestions.dedup();
+    (start, suggestions)
+}
+
+/// Send players the commands they can use, and run the commands they send.
+pub(crate) fn configure_network(app: &mut App) {
+    app.add_packet_handler(handle_chat_command)
+        .add_packet_handler(handle_chat_command_signed)
+        .add_packet_handler(handle_command_suggestion)
+        .add_observer(broadcast_to_ops)
+        .add_systems(TickSet::NetworkOut, declare_commands);
+}
+
+/// Show messages broadcast to operators to every operator playing.
+fn broadcast_to_ops(
+    event: On<BroadcastToOps>,
+    query: Query<(&ProtocolState, &PermissionLevel, &PacketSender)>,
+) -> Result<()> {
+    let packet = SystemChat::new(event.message.clone()).blocking_raw()?;
+    for (state, level, sender) in query.iter() {
+        if *state == ProtocolState::Play && level.0 >= PermissionLevel::MODERATOR {
+            let _ = sender.send(packet.clone());
+        }
+    }
+    Ok(())
+}
+
+/// A connection that is told which commands it can use, with its permission level.
+type Receiver<'a> = (
+    Ref<'a, ProtocolState>,
+    Option<Ref<'a, PermissionLevel>>,
+    &'a PacketSender,
+);
+
+/// Send players the commands they can use when they join, when their permission level changes, and
+/// when commands are registered.
+fn declare_commands(
+    dispatcher: Res<CommandDispatcher>,
+    query: Query<Receiver>,
+    mut removed: RemovedComponents<PermissionLevel>,
+) -> Result<()> {
+    for (state, level, sender) in query.iter() {
+        let changed = dispatcher.is_changed()
+            || state.is_changed()
+            || level.as_ref().is_some_and(|level| level.is_changed());
+        if changed && *state == ProtocolState::Play {
+            let level = level.map_or(PermissionLevel::ALL, |level| level.0);
+            let _ = sender.send(dispatcher.graph(level).blocking_raw()?);
+        }
+    }
+
+    // players whose permission level was taken away
+    for entity in removed.read() {
+        if let Ok((state, None, sender)) = query.get(entity)
+            && *state == ProtocolState::Play
+            && !dispatcher.is_changed()
+        {
+            let _ = sender.send(dispatcher.graph(PermissionLevel::ALL).blocking_raw()?);
+        }
+    }
+    Ok(())
+}
+
+fn handle_chat_command(event: On<PacketEvent<ChatCommand>>, mut commands: Commands) {
+    let (entity, command) = (event.entity, event.packet.command.clone());
+    commands.queue(move |world: &mut World| player_command(world, entity, &command));
+}
+
+// secure chat is not supported, so signed commands are run like any other
+fn handle_chat_command_signed(event: On<PacketEvent<ChatCommandSigned>>, mut commands: Commands) {
+    let (entity, command) = (event.entity, event.packet.command.clone());
+    commands.queue(move |world: &mut World| player_command(world, entity, &command));
+}
+
+/// Run a command sent by a player, and show them its feedback in chat.
+fn player_command(world: &mut World, entity: Entity, command: &str) -> Result<()> {
+    if let Some(identity) = world.get::<PlayerIdentity>(entity) {
+        info!("{} issued server command: /{command}", identity.name);
+    }
+    let output = run_command(world, CommandSender::Player(entity), command);
+    // the connection may have closed in the meantime
+    if let Some(sender) = world.get::<PacketSender>(entity) {
+        for line in output {
+            let _ = sender.send(SystemChat::new(line).blocking_raw()?);
+        }
+    }
+    Ok(())
+}
+
+fn handle_command_suggestion(event: On<PacketEvent<CommandSuggestion>>, mut commands: Commands) {
+    let entity = event.entity;
+    let (id, text) = (event.packet.transaction_id, event.packet.text.clone());
+    commands.queue(move |world: &mut World| -> Result<()> {
+        let command = text.strip_prefix('/').unwrap_or(&text);
+        let (start, matches) = suggest_command(world, CommandSender::Player(entity), command);
+
+        // clients count in UTF-16 code units, including the leading slash
+        let start = text.len() - command.len() + start;
+        let utf16 = |s: &str| VarInt(s.encode_utf16().count() as i32);
+        let packet = CommandSuggestions {
+            id,
+            start: utf16(&text[..start]),
+            length: utf16(&text[start..]),
+            matches: matches
+                .into_iter()
+                .map(|text| SuggestionMatch {
+                    text,
+                    tooltip: None,
+                })
+                .collect(),
+        };
+        if let Some(sender) = world.get::<PacketSender>(entity) {
+            let _ = sender.send(packet.blocking_raw()?);
+        }
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::ProtocolState;
+    use beacon_config::Config;
+    use beacon_net::{
+        conn::{Connection, IncomingSender, OutgoingReceiver, RemoteAddr},
+        text::Text,
+    };
+    use bevy_ecs::{prelude::*, schedule::Schedule};
+
+    use super::{
+        BroadcastToOps, CommandDispatcher, CommandSender, PermissionLevel, RunCommand,
+        broadcast_to_ops, run_command,
+    };
+
+    /// The messages broadcast to operators.
+    #[derive(Resource, Default)]
+    struct Broadcasts(Vec<Text>);
+
+    /// A world that shows broadcasts to operators, and the schedule that sends their packets.
+    fn world() -> (World, Schedule) {
+        let mut world = World::new();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<CommandDispatcher>();
+        let mut schedule = Schedule::default();
+        beacon_net::ecs(&mut world, &mut schedule);
+        world.add_observer(broadcast_to_ops);
+        (world, schedule)
+    }
+
+    /// Spawn a playing connection, with the permission level given.
+    fn spawn(
+        world: &mut World,
+        port: u16,
+        level: Option<u8>,
+    ) -> (IncomingSender, OutgoingReceiver) {
+        let addr = ([127, 0, 0, 1], port).into();
+        let (incoming, outgoing) = Connection::spawn(world, addr);
+        let entity = world
+            .query::<(Entity, &RemoteAddr)>()
+            .iter(world)
+            .find(|(_, remote)| remote.0 == addr)
+            .map(|(entity, _)| entity)
+            .unwrap();
+        world.entity_mut(entity).insert(ProtocolState::Play);
+        if let Some(level) = level {
+            world.entity_mut(entity).insert(PermissionLevel(level));
+        }
+        (incoming, outgoing)
+    }
+
+    #[test]
+    fn test_broadcast_to_ops() {
+        let (mut world, mut schedule) = world();
+
+        let (_op_in, op_out) = spawn(&mut world, 1, Some(4));
+        let (_player_in, player_out) = spawn(&mut world, 2, None);
+        world.trigger(BroadcastToOps {
+            message: Text::from("hello"),
+        });
+        schedule.run(&mut world);
+
+        assert_eq!(op_out.try_recv().unwrap().len(), 1);
+        assert!(player_out.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_console_broadcast() {
+        let (mut world, mut schedule) = world();
+        world.add_observer(|mut event: On<RunCommand>| {
+            event.output.push(Text::from("pong"));
+            event.handled = true;
+        });
+        let (_op_in, op_out) = spawn(&mut world, 1, Some(4));
+
+        // the console's feedback is only shown to operators if configured
+        world.resource_mut::<Config>().console.broadcast_to_ops = false;
+        run_command(&mut world, CommandSender::Console, "ping");
+        schedule.run(&mut world);
+        assert!(op_out.try_recv().is_err());
+
+        world.resource_mut::<Config>().console.broadcast_to_ops = true;
+        world.init_resource::<Broadcasts>();
+        world.add_observer(
+            |event: On<BroadcastToOps>, mut broadcasts: ResMut<Broadcasts>| {
+                broadcasts.0.push(event.message.clone());
+            },
+        );
+        run_command(&mut world, CommandSender::Console, "ping");
+        schedule.run(&mut world);
+        assert_eq!(op_out.try_recv().unwrap().len(), 1);
+        let message = Text::translate("chat.type.admin", ["Server", "pong"].map(Text::from));
+        assert_eq!(world.resource::<Broadcasts>().0, [message]);
+    }
+}
@@ -0,0 +1,584 @@
+use beacon_net::player::GameMode;
+use bevy_ecs::world::World;
+use uuid::Uuid;
+
+use crate::command::{
+    reader::{CommandError, StringReader, is_unquoted_char},
+    selector::EntityArgument,
+};
+
+/// The error for coordinates that are partly relative to where the sender is looking.
+const MIXED_COORDINATES: &str =
+    "Cannot mix world & local coordinates (everything must either use ^ or not)";
+
+/// The chat colours that can be used in commands, in the order of their legacy codes.
+const COLORS: [&str; 17] = [
+    "black",
+    "dark_blue",
+    "dark_green",
+    "dark_aqua",
+    "dark_red",
+    "dark_purple",
+    "gold",
+    "gray",
+    "dark_gray",
+    "blue",
+    "green",
+    "aqua",
+    "red",
+    "light_purple",
+    "yellow",
+    "white",
+    "reset",
+];
+
+/// How much of a string argument is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringKind {
+    /// A single unquoted word.
+    Word,
+    /// A word, or a quoted phrase.
+    Phrase,
+    /// The rest of the command.
+    Greedy,
+}
+
+/// How an argument is parsed. Each type matches a parser in the protocol's
+/// `command_argument_type` registry, so clients can parse and suggest it too.
+///
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Command_data#Parsers>
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgumentType {
+    /// `true` or `false`.
+    Bool,
+    /// A float, optionally within bounds.
+    Float {
+        /// The smallest allowed value.
+        min: Option<f32>,
+        /// The largest allowed value.
+        max: Option<f32>,
+    },
+    /// A double, optionally within bounds.
+    Double {
+        /// The smallest allowed value.
+        min: Option<f64>,
+        /// The largest allowed value.
+        max: Option<f64>,
+    },
+    /// An integer, optionally within bounds.
+    Integer {
+        /// The smallest allowed value.
+        min: Option<i32>,
+        /// The largest allowed value.
+        max: Option<i32>,
+    },
+    /// A long integer, optionally within bounds.
+    Long {
+        /// The smallest allowed value.
+        min: Option<i64>,
+        /// The largest allowed value.
+        max: Option<i64>,
+    },
+    /// A string.
+    String(StringKind),
+    /// Entities, chosen with a selector such as `@e[type=pig]`, a player's name, or a UUID.
+    Entity {
+        /// Whether only a single entity may be chosen.
+        single: bool,
+        /// Whether only players may be chosen.
+        players_only: bool,
+    },
+    /// One or more player profiles, which may be offline.
+    GameProfile,
+    /// The position of a block, e.g. `~ ~1 ~`.
+    BlockPos,
+    /// The x and z position of a block column.
+    ColumnPos,
+    /// A position, e.g. `^ ^ ^1`.
+    Vec3,
+    /// An x and z position.
+    Vec2,
+    /// An item's ID, e.g. `minecraft:diamond_sword`.
+    ItemStack,
+    /// A chat colour's name.
+    Color,
+    /// The rest of the command, as a chat message.
+    Message,
+    /// A rotation, as yaw then pitch.
+    Rotation,
+    /// A resource location, e.g. `minecraft:stone`.
+    ResourceLocation,
+    /// A game mode's name.
+    Gamemode,
+    /// A duration in ticks, which can also be given in seconds or days, e.g. `5s`.
+    Time {
+        /// The smallest allowed number of ticks.
+        min: i32,
+    },
+    /// A UUID.
+    Uuid,
+}
+
+impl ArgumentType {
+    /// An integer without bounds.
+    pub const INTEGER: Self = Self::Integer {
+        min: None,
+        max: None,
+    };
+
+    /// A double without bounds.
+    pub const DOUBLE: Self = Self::Double {
+        min: None,
+        max: None,
+    };
+
+    /// The parser's ID in the protocol's `command_argument_type` registry.
+    pub(crate) fn parser_id(&self) -> i32 {
+        match self {
+            Self::Bool => 0,
+            Self::Float { .. } => 1,
+            Self::Double { .. } => 2,
+            Self::Integer { .. } => 3,
+            Self::Long { .. } => 4,
+            Self::String(_) => 5,
+            Self::Entity { .. } => 6,
+            Self::GameProfile => 7,
+            Self::BlockPos => 8,
+            Self::ColumnPos => 9,
+            Self::Vec3 => 10,
+            Self::Vec2 => 11,
+            Self::ItemStack => 14,
+            Self::Color => 16,
+            Self::Message => 20,
+            Self::Rotation => 29,
+            Self::ResourceLocation => 36,
+            Self::Gamemode => 42,
+            Self::Time { .. } => 43,
+            Self::Uuid => 56,
+        }
+    }
+
+    /// The parser's properties, encoded for the commands packet.
+    pub(crate) fn properties(&self) -> Vec<u8> {
+        fn bounds<const N: usize>(min: Option<[u8; N]>, max: Option<[u8; N]>) -> Vec<u8> {
+            let flags = min.is_some() as u8 | (max.is_some() as u8) << 1;
+            let mut buf = vec![flags];
+            buf.extend(min.into_iter().chain(max).flatten());
+            buf
+        }
+
+        match self {
+            Self::Float { min, max } => {
+                bounds(min.map(f32::to_be_bytes), max.map(f32::to_be_bytes))
+            }
+            Self::Double { min, max } => {
+                bounds(min.map(f64::to_be_bytes), max.map(f64::to_be_bytes))
+            }
+            Self::Integer { min, max } => {
+                bounds(min.map(i32::to_be_bytes), max.map(i32::to_be_bytes))
+            }
+            Self::Long { min, max } => bounds(min.map(i64::to_be_bytes), max.map(i64::to_be_bytes)),
+            Self::String(kind) => vec![*kind as u8],
+            Self::Entity {
+                single,
+                players_only,
+            } => vec![*single as u8 | (*players_only as u8) << 1],
+            Self::Time { min } => min.to_be_bytes().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Parse the argument.
+    pub fn parse(&self, reader: &mut StringReader) -> Result<Argument, CommandError> {
+        let start = reader.cursor;
+        let argument = match self {
+            Self::Bool => Argument::Bool(reader.read_bool()?),
+            Self::Float { min, max } => Argument::Float(check_bounds(
+                reader.read_float()?,
+                *min,
+                *max,
+                "Float",
+                reader,
+                start,
+            )?),
+            Self::Double { min, max } => Argument::Double(check_bounds(
+                reader.read_double()?,
+                *min,
+                *max,
+                "Double",
+                reader,
+                start,
+            )?),
+            Self::Integer { min, max } => Argument::Integer(check_bounds(
+                reader.read_int()?,
+                *min,
+                *max,
+                "Integer",
+                reader,
+                start,
+            )?),
+            Self::Long { min, max } => Argument::Long(check_bounds(
+                reader.read_long()?,
+                *min,
+                *max,
+                "Long",
+                reader,
+                start,
+            )?),
+            Self::String(StringKind::Word) => {
+                Argument::String(reader.read_unquoted_string().to_string())
+            }
+            Self::String(StringKind::Phrase) => Argument::String(reader.read_string()?),
+            Self::String(StringKind::Greedy) | Self::Message => {
+                let rest = reader.remaining().to_string();
+                reader.cursor = reader.input().len();
+                match self {
+                    Self::Message => Argument::Message(rest),
+                    _ => Argument::String(rest),
+                }
+            }
+            Self::Entity {
+                single,
+                players_only,
+            } => Argument::Entity(EntityArgument::parse(reader, *single, *players_only)?),
+            Self::GameProfile => Argument::Entity(EntityArgument::parse(reader, false, true)?),
+            Self::BlockPos => Argument::BlockPos(Coordinates::parse(reader, 3, true, false)?),
+            Self::ColumnPos => Argument::ColumnPos(Coordinates::parse(reader, 2, false, false)?),
+            Self::Vec3 => Argument::Vec3(Coordinates::parse(reader, 3, true, true)?),
+            Self::Vec2 => Argument::Vec2(Coordinates::parse(reader, 2, false, true)?),
+            Self::Rotation => Argument::Rotation(Coordinates::parse(reader, 2, false, true)?),
+            Self::ItemStack => {
+                let item = read_resource_location(reader)?;
+                if matches!(reader.peek(), Some('[' | '{')) {
+                    let message = "Item components are not supported yet";
+                    return Err(CommandError::at(message, reader));
+                }
+                Argument::ItemStack(item)
+            }
+            Self::Color => {
+                let name = reader.read_unquoted_string();
+                if !COLORS.contains(&name) {
+                    reader.cursor = start;
+                    let message = format!("Unknown colour '{name}'");
+                    return Err(CommandError::at(message, reader));
+                }
+                Argument::Color(name.to_string())
+            }
+            Self::ResourceLocation => Argument::ResourceLocation(read_resource_location(reader)?),
+            Self::Gamemode => {
+                let name = reader.read_unquoted_string();
+                let mode = GameMode::from_name(name).ok_or_else(|| {
+                    reader.cursor = start;
+                    CommandError::at(format!("Unknown game mode: {name}"), reader)
+                })?;
+                Argument::Gamemode(mode)
+            }
+            Self::Time { min } => {
+                let value = reader.read_float()?;
+                let unit = match reader.peek() {
+                    Some('d') => 24000.0,
+                    Some('s') => 20.0,
+                    Some('t') => 1.0,
+                    Some(' ') | None => 1.0,
+                    Some(_) => return Err(CommandError::at("Invalid unit", reader)),
+                };
+                if reader.peek().is_some_and(|c| c != ' ') {
+                    reader.read();
+                }
+                let ticks = (value * unit).round() as i32;
+                if ticks < *min {
+                    let message = format!("Tick count must not be less than {min}, found {ticks}");
+                    return Err(CommandError::at_cursor(message, reader, start));
+                }
+                Argument::Time(ticks)
+            }
+            Self::Uuid => {
+                let uuid = reader.read_while(|c| c.is_ascii_hexdigit() || c == '-');
+                let uuid = Uuid::try_parse(uuid).map_err(|_| {
+                    reader.cursor = start;
+                    CommandError::at("Invalid UUID", reader)
+                })?;
+                Argument::Uuid(uuid.as_u128())
+            }
+        };
+        Ok(argument)
+    }
+
+    /// Suggest values for the argument, given the part of it typed so far.
+    pub(crate) fn suggest(&self, world: &mut World, typed: &str) -> Vec<String> {
+        let suggestions: Vec<String> = match self {
+            Self::Bool => vec!["true".into(), "false".into()],
+            Self::Color => COLORS.iter().map(|name| name.to_string()).collect(),
+            Self::Gamemode => GameMode::ALL
+                .iter()
+                .map(|mode| mode.name().to_string())
+                .collect(),
+            Self::Entity { .. } | Self::GameProfile => EntityArgument::suggest(world, typed),
+            Self::BlockPos | Self::Vec3 if typed.is_empty() => vec!["~ ~ ~".into()],
+            Self::ColumnPos | Self::Vec2 | Self::Rotation if typed.is_empty() => {
+                vec!["~ ~".into()]
+            }
+            _ => Vec::new(),
+        };
+        suggestions
+            .into_iter()
+            .filter(|suggestion| suggestion.starts_with(typed))
+            .collect()
+    }
+}
+
+/// Check that a number is within its bounds.
+fn check_bounds<T: PartialOrd + std::fmt::Display>(
+    value: T,
+    min: Option<T>,
+    max: Option<T>,
+    kind: &str,
+    reader: &StringReader,
+    start: usize,
+) -> Result<T, CommandError> {
+    if let Some(min) = min.filter(|min| value < *min) {
+        let message = format!("{kind} must not be less than {min}, found {value}");
+        return Err(CommandError::at_cursor(message, reader, start));
+    }
+    if let Some(max) = max.filter(|max| value > *max) {
+        let message = format!("{kind} must not be more than {max}, found {value}");
+        return Err(CommandError::at_cursor(message, reader, start));
+    }
+    Ok(value)
+}
+
+/// Read a resource location, adding the `minecraft` namespace if it has none.
+pub(crate) fn read_resource_location(reader: &mut StringReader) -> Result<String, CommandError> {
+    let start = reader.cursor;
+    let location = reader.read_while(|c| is_unquoted_char(c) || matches!(c, ':' | '/'));
+    let (namespace, path) = location.split_once(':').unwrap_or(("minecraft", location));
+    let valid_namespace = namespace
+        .chars()
+        .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.'));
+    let valid_path = path
+        .chars()
+        .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.' | '/'));
+    if location.is_empty() || !valid_namespace || !valid_path {
+        reader.cursor = start;
+        return Err(CommandError::at("Invalid ID", reader));
+    }
+    Ok(format!("{namespace}:{path}"))
+}
+
+/// A parsed argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    /// See [ArgumentType::Bool].
+    Bool(bool),
+    /// See [ArgumentType::Float].
+    Float(f32),
+    /// See [ArgumentType::Double].
+    Double(f64),
+    /// See [ArgumentType::Integer].
+    Integer(i32),
+    /// See [ArgumentType::Long].
+    Long(i64),
+    /// See [ArgumentType::String].
+    String(String),
+    /// See [ArgumentType::Entity] and [ArgumentType::GameProfile].
+    Entity(EntityArgument),
+    /// See [ArgumentType::BlockPos].
+    BlockPos(Coordinates),
+    /// See [ArgumentType::ColumnPos].
+    ColumnPos(Coordinates),
+    /// See [ArgumentType::Vec3].
+    Vec3(Coordinates),
+    /// See [ArgumentType::Vec2].
+    Vec2(Coordinates),
+    /// See [ArgumentType::ItemStack].
+    ItemStack(String),
+    /// See [ArgumentType::Color].
+    Color(String),
+    /// See [ArgumentType::Message].
+    Message(String),
+    /// See [ArgumentType::Rotation].
+    Rotation(Coordinates),
+    /// See [ArgumentType::ResourceLocation].
+    ResourceLocation(String),
+    /// See [ArgumentType::Gamemode].
+    Gamemode(GameMode),
+    /// See [ArgumentType::Time], in ticks.
+    Time(i32),
+    /// See [ArgumentType::Uuid].
+    Uuid(u128),
+}
+
+/// A type that can be taken from a parsed argument, with
+/// [CommandContext::get](crate::command::CommandContext::get).
+pub trait FromArgument: Sized {
+    /// Take the value from the argument, if it is of the right type.
+    fn from_argument(argument: &Argument) -> Option<Self>;
+}
+
+macro_rules! from_argument {
+    ($($ty:ty => $($variant:ident)|+),* $(,)?) => {
+        $(
+            impl FromArgument for $ty {
+                fn from_argument(argument: &Argument) -> Option<Self> {
+                    match argument {
+                        $(Argument::$variant(value))|+ => Some(value.clone()),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_argument! {
+    bool => Bool,
+    f32 => Float,
+    f64 => Double,
+    i32 => Integer | Time,
+    i64 => Long,
+    String => String | Message | Color | ResourceLocation | ItemStack,
+    EntityArgument => Entity,
+    Coordinates => BlockPos | ColumnPos | Vec3 | Vec2 | Rotation,
+    GameMode => Gamemode,
+    u128 => Uuid,
+}
+
+/// A coordinate relative to the world's origin, or to the sender's position if it starts with `~`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldCoordinate {
+    /// The coordinate, or the offset from the sender's position.
+    pub value: f64,
+    /// Whether the coordinate is relative to the sender's position.
+    pub relative: bool,
+}
+
+/// Parsed coordinates, with two or three components.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coordinates {
+    /// Coordinates relative to the world's origin or to the sender, e.g. `~ 64 ~`.
+    World(Vec<WorldCoordinate>),
+    /// Coordinates relative to where the sender is looking, e.g. `^ ^ ^5`: left, up, then
+    /// forwards.
+    Local([f64; 3]),
+}
+
+impl Coordinates {
+    /// Parse `count` coordinates.
+    ///
+    /// Block positions must be whole numbers unless relative, and positions are moved to the
+    /// centre of a block when given as whole numbers.
+    fn parse(
+        reader: &mut StringReader,
+        count: usize,
+        allow_local: bool,
+        decimal: bool,
+    ) -> Result<Self, CommandError> {
+        let start = reader.cursor;
+        if allow_local && reader.peek() == Some('^') {
+            let mut local = [0.0; 3];
+            for (i, value) in local.iter_mut().enumerate() {
+                if i > 0 {
+                    expect_space(reader, start)?;
+                }
+                if !reader.eat('^') {
+                    return Err(CommandError::at(MIXED_COORDINATES, reader));
+                }
+                if reader.peek().is_some_and(|c| c != ' ') {
+                    *value = reader.read_double()?;
+                }
+            }
+            return Ok(Self::Local(local));
+        }
+
+        let mut coordinates = Vec::with_capacity(count);
+        for i in 0..count {
+            if i > 0 {
+                expect_space(reader, start)?;
+            }
+            if reader.peek() == Some('^') {
+                return Err(CommandError::at(MIXED_COORDINATES, reader));
+            }
+
+            let relative = reader.eat('~');
+            let value = if relative && reader.peek().is_none_or(|c| c == ' ') {
+                0.0
+            } else if decimal || relative {
+                let number_start = reader.cursor;
+                let value = reader.read_double()?;
+                // whole numbers are the corner of a block, so move x and z to its centre
+                let whole = !reader.input()[number_start..reader.cursor].contains('.');
+                let horizontal = i == 0 || i == count - 1;
+                if decimal && !relative && whole && horizontal && count == 3 {
+                    value + 0.5
+                } else {
+                    value
+                }
+            } else {
+                reader.read_int()? as f64
+            };
+            coordinates.push(WorldCoordinate { value, relative });
+        }
+        Ok(Self::World(coordinates))
+    }
+
+    /// Find the position these coordinates refer to, for a sender at `origin` looking in the
+    /// direction of `rotation` (yaw then pitch, in degrees).
+    pub fn resolve(&self, origin: &[f64], rotation: [f32; 2]) -> Vec<f64> {
+        match self {
+            Self::World(coordinates) => coordinates
+                .iter()
+                .zip(origin)
+                .map(|(coordinate, origin)| match coordinate.relative {
+                    true => origin + coordinate.value,
+                    false => coordinate.value,
+                })
+                .collect(),
+            Self::Local([left, up, forwards]) => {
+                let [yaw, pitch] = rotation.map(|angle| (angle as f64).to_radians());
+                let (f, g) = (
+                    (yaw + std::f64::consts::FRAC_PI_2).cos(),
+                    (yaw + std::f64::consts::FRAC_PI_2).sin(),
+                );
+                let (h, i) = ((-pitch).cos(), (-pitch).sin());
+                let (j, k) = (
+                    (-pitch + std::f64::consts::FRAC_PI_2).cos(),
+                    (-pitch + std::f64::consts::FRAC_PI_2).sin(),
+                );
+                let forward = [f * h, i, g * h];
+                let upward = [f * j, k, g * j];
+                // forward x upward, negated
+                let leftward = [
+                    -(forward[1] * upward[2] - forward[2] * upward[1]),
+                    -(forward[2] * upward[0] - forward[0] * upward[2]),
+                    -(forward[0] * upward[1] - forward[1] * upward[0]),
+                ];
+                (0..3)
+                    .map(|axis| {
+                        origin.get(axis).copied().unwrap_or_default()
+                            + forward[axis] * forwards
+                            + upward[axis] * up
+                            + leftward[axis] * left
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Find the block these coordinates refer to.
+    pub fn resolve_block(&self, origin: &[f64], rotation: [f32; 2]) -> Vec<i32> {
+        self.resolve(origin, rotation)
+            .into_iter()
+            .map(|value| value.floor() as i32)
+            .collect()
+    }
+}
+
+/// Coordinates are separated by a single space.
+fn expect_space(reader: &mut StringReader, start: usize) -> Result<(), CommandError> {
+    if reader.eat(' ') && reader.can_read() {
+        Ok(())
+    } else {
+        let message = "Incomplete (expected 3 coordinates)";
+        Err(CommandError::at_cursor(message, reader, start))
+    }
+}
@@ -0,0 +1,693 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use beacon_codec::types::VarInt;
+use beacon_net::{
+    client::command::{CommandNode, CommandNodeKind, DeclareCommands},
+    player::PlayerIdentity,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+
+use crate::{
+    access::{GameProfile, find_profile},
+    command::{
+        CommandSender,
+        argument::{Argument, ArgumentType, FromArgument},
+        reader::{CommandError, StringReader},
+        selector::EntityArgument,
+    },
+};
+
+/// The index of the root node, which every command is a child of.
+const ROOT: usize = 0;
+
+/// Runs a command, writing its feedback to the context.
+pub type Executor = Arc<dyn Fn(&mut CommandContext) -> Result<(), CommandError> + Send + Sync>;
+
+/// Suggests values for an argument, given who is typing it and the part typed so far.
+pub type SuggestionProvider =
+    Arc<dyn Fn(&mut World, &CommandSender, &str) -> Vec<String> + Send + Sync>;
+
+/// The tree of commands the server knows, which parses and runs commands, suggests how to
+/// complete them, and is sent to clients so they can do the same.
+///
+/// It works like Mojang's Brigadier: each command is a path from the root through literal words
+/// and typed arguments, ending at a node that can be executed. A node can redirect to another, so
+/// parsing continues from the other node's children, which is how aliases are made.
+///
+/// See: <https://github.com/Mojang/brigadier>
+#[derive(Resource, Clone)]
+pub struct CommandDispatcher {
+    nodes: Arc<Vec<Node>>,
+}
+
+impl Default for CommandDispatcher {
+    fn default() -> Self {
+        Self {
+            nodes: Arc::new(vec![Node::new(NodeKind::Root)]),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    kind: NodeKind,
+    children: Vec<usize>,
+    permission: u8,
+    executor: Option<Executor>,
+    redirect: Option<usize>,
+    suggests: Option<SuggestionProvider>,
+}
+
+#[derive(Clone, PartialEq)]
+enum NodeKind {
+    Root,
+    Literal(String),
+    Argument { name: String, ty: ArgumentType },
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        Self {
+            kind,
+            children: Vec::new(),
+            permission: 0,
+            executor: None,
+            redirect: None,
+            suggests: None,
+        }
+    }
+
+    /// Parse this node's part of the command, adding any argument to `arguments`.
+    fn parse(
+        &self,
+        reader: &mut StringReader,
+        arguments: &mut Vec<(String, Argument)>,
+    ) -> Result<(), CommandError> {
+        match &self.kind {
+            NodeKind::Root => Ok(()),
+            NodeKind::Literal(name) => {
+                let start = reader.cursor;
+                if reader.remaining().starts_with(name.as_str()) {
+                    reader.cursor += name.len();
+                    if reader.peek().is_none_or(|c| c == ' ') {
+                        return Ok(());
+                    }
+                    reader.cursor = start;
+                }
+                Err(CommandError::at(format!("Expected literal {name}"), reader))
+            }
+            NodeKind::Argument { name, ty } => {
+                arguments.push((name.clone(), ty.parse(reader)?));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A command being built, to [register](CommandDispatcher::register) with the dispatcher.
+#[must_use]
+pub struct CommandBuilder {
+    node: Node,
+    children: Vec<CommandBuilder>,
+}
+
+/// Start building a command node that matches a word exactly.
+pub fn literal(name: impl Into<String>) -> CommandBuilder {
+    CommandBuilder::new(NodeKind::Literal(name.into()))
+}
+
+/// Start building a command node that parses an argument.
+pub fn argument(name: impl Into<String>, ty: ArgumentType) -> CommandBuilder {
+    CommandBuilder::new(NodeKind::Argument {
+        name: name.into(),
+        ty,
+    })
+}
+
+impl CommandBuilder {
+    fn new(kind: NodeKind) -> Self {
+        Self {
+            node: Node::new(kind),
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a node that can follow this one.
+    pub fn then(mut self, child: CommandBuilder) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Only let senders with at least this permission level use the node.
+    pub fn requires(mut self, level: u8) -> Self {
+        self.node.permission = level;
+        self
+    }
+
+    /// Let the command end at this node, running `executor`.
+    pub fn executes(
+        mut self,
+        executor: impl Fn(&mut CommandContext) -> Result<(), CommandError> + Send + Sync + 'static,
+    ) -> Self {
+        self.node.executor = Some(Arc::new(executor));
+        self
+    }
+
+    /// Continue parsing from another node's children after this one, e.g. to make an alias of a
+    /// command. The node is one returned by [CommandDispatcher::register].
+    pub fn redirect(mut self, node: usize) -> Self {
+        self.node.redirect = Some(node);
+        self
+    }
+
+    /// Suggest values for this argument with `provider`, rather than the argument type's own
+    /// suggestions. Clients ask the server for them as they type.
+    pub fn suggests(
+        mut self,
+        provider: impl Fn(&mut World, &CommandSender, &str) -> Vec<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.node.suggests = Some(Arc::new(provider));
+        self
+    }
+}
+
+/// What a command is being run with.
+pub struct CommandContext<'w> {
+    /// The world the command is run in.
+    pub world: &'w mut World,
+    /// Who is running the command.
+    pub sender: CommandSender,
+    arguments: Vec<(String, Argument)>,
+    output: Vec<Text>,
+}
+
+impl CommandContext<'_> {
+    /// Show a message to the sender.
+    pub fn feedback(&mut self, message: impl Into<Text>) {
+        self.output.push(message.into());
+    }
+
+    /// A parsed argument, by name.
+    pub fn argument(&self, name: &str) -> Option<&Argument> {
+        self.arguments
+            .iter()
+            .rev()
+            .find(|(argument, _)| argument == name)
+            .map(|(_, value)| value)
+    }
+
+    /// A parsed argument's value, by name.
+    ///
+    /// # Panics
+    /// Panics if the command has no argument with that name and type, which is a mistake in how
+    /// the command was built.
+    pub fn get<T: FromArgument>(&self, name: &str) -> T {
+        self.argument(name)
+            .and_then(T::from_argument)
+            .unwrap_or_else(|| panic!("command has no argument {name} of the requested type"))
+    }
+
+    /// A parsed argument's value by name, if the command has it. Useful for optional arguments.
+    pub fn try_get<T: FromArgument>(&self, name: &str) -> Option<T> {
+        self.argument(name).and_then(T::from_argument)
+    }
+
+    /// The entities chosen by an entity argument, of which there must be at least one.
+    pub fn entities(&mut self, name: &str) -> Result<Vec<Entity>, CommandError> {
+        let entities = self
+            .get::<EntityArgument>(name)
+            .select(self.world, &self.sender)?;
+        match entities.is_empty() {
+            true => Err(not_found("argument.entity.notfound.entity")),
+            false => Ok(entities),
+        }
+    }
+
+    /// The players chosen by an entity argument, of which there must be at least one.
+    pub fn players(&mut self, name: &str) -> Result<Vec<Entity>, CommandError> {
+        let entities = self
+            .get::<EntityArgument>(name)
+            .select(self.world, &self.sender)?;
+        let players: Vec<Entity> = entities
+            .into_iter()
+            .filter(|entity| self.world.get::<PlayerIdentity>(*entity).is_some())
+            .collect();
+        match players.is_empty() {
+            true => Err(not_found("argument.entity.notfound.player")),
+            false => Ok(players),
+        }
+    }
+
+    /// The single entity chosen by an entity argument.
+    pub fn entity(&mut self, name: &str) -> Result<Entity, CommandError> {
+        Ok(self.entities(name)?[0])
+    }
+
+    /// The single player chosen by an entity argument.
+    pub fn player(&mut self, name: &str) -> Result<Entity, CommandError> {
+        Ok(self.players(name)?[0])
+    }
+
+    /// The profiles of the players chosen by a game profile argument. Players named in the
+    /// argument may be offline, as long as the server knows of them.
+    pub fn profiles(&mut self, name: &str) -> Result<Vec<GameProfile>, CommandError> {
+        let argument = self.get::<EntityArgument>(name);
+        if let EntityArgument::Name(name) = &argument {
+            return find_profile(self.world, name)
+                .map(|profile| vec![profile])
+                .ok_or_else(|| not_found("argument.player.unknown"));
+        }
+
+        let profiles: Vec<GameProfile> = argument
+            .select(self.world, &self.sender)?
+            .into_iter()
+            .filter_map(|entity| self.world.get::<PlayerIdentity>(entity))
+            .map(GameProfile::from)
+            .collect();
+        match (profiles.is_empty(), argument) {
+            (true, EntityArgument::Uuid(_)) => Err(not_found("argument.player.unknown")),
+            (true, _) => Err(not_found("argument.entity.notfound.player")),
+            (false, _) => Ok(profiles),
+        }
+    }
+}
+
+/// The result of parsing part of a command.
+struct Parse {
+    /// The last node that was parsed.
+    node: usize,
+    /// How far the command was parsed.
+    cursor: usize,
+    arguments: Vec<(String, Argument)>,
+    /// The errors from the nodes that could not be parsed after `node`.
+    errors: Vec<CommandError>,
+}
+
+impl CommandDispatcher {
+    /// Register a command, merging it with any command that starts with the same literal.
+    /// Returns the node the command starts at, which other commands can redirect to.
+    pub fn register(&mut self, command: CommandBuilder) -> usize {
+        let nodes = Arc::make_mut(&mut self.nodes);
+        Self::add(nodes, ROOT, command)
+    }
+
+    fn add(nodes: &mut Vec<Node>, parent: usize, builder: CommandBuilder) -> usize {
+        let existing = nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|child| nodes[*child].kind == builder.node.kind);
+
+        let id = match existing {
+            Some(id) => {
+                let node = &mut nodes[id];
+                let new = builder.node;
+                node.executor = new.executor.or(node.executor.take());
+                node.redirect = new.redirect.or(node.redirect);
+                node.suggests = new.suggests.or(node.suggests.take());
+                node.permission = new.permission;
+                id
+            }
+            None => {
+                nodes.push(builder.node);
+                let id = nodes.len() - 1;
+                nodes[parent].children.push(id);
+                id
+            }
+        };
+        for child in builder.children {
+            Self::add(nodes, id, child);
+        }
+        id
+    }
+
+    /// Parse and run a command, returning its feedback.
+    pub fn execute(
+        &self,
+        world: &mut World,
+        sender: CommandSender,
+        command: &str,
+    ) -> Result<Vec<Text>, CommandError> {
+        let level = sender.permission_level(world);
+        let reader = StringReader::new(command);
+        let parse = self.parse_nodes(ROOT, &reader, level, Vec::new());
+
+        let mut reader = reader;
+        reader.cursor = parse.cursor;
+        if reader.can_read() {
+            let mut errors = parse.errors;
+            return Err(match errors.len() {
+                1 => errors.remove(0),
+                _ if parse.node == ROOT => CommandError::at(
+                    "Unknown or incomplete command, see below for error",
+                    &reader,
+                ),
+                _ => CommandError::at("Incorrect argument for command", &reader),
+            });
+        }
+        let Some(executor) = self.nodes[parse.node].executor.clone() else {
+            let message = "Unknown or incomplete command, see below for error";
+            return Err(CommandError::at(message, &reader));
+        };
+
+        let mut context = CommandContext {
+            world,
+            sender,
+            arguments: parse.arguments,
+            output: Vec::new(),
+        };
+        executor(&mut context)?;
+        Ok(context.output)
+    }
+
+    /// Parse as much of the command as possible from `node`, trying each child that could follow
+    /// it and keeping the best result.
+    fn parse_nodes(
+        &self,
+        node: usize,
+        reader: &StringReader,
+        level: u8,
+        arguments: Vec<(String, Argument)>,
+    ) -> Parse {
+        let mut errors = Vec::new();
+        let mut potentials = Vec::new();
+
+        for child in self.relevant_children(node, reader, level) {
+            let mut reader = reader.clone();
+            let mut arguments = arguments.clone();
+            let parsed = self.nodes[child]
+                .parse(&mut reader, &mut arguments)
+                .and_then(|()| match reader.peek() {
+                    Some(c) if c != ' ' => Err(CommandError::at(
+                        "Expected whitespace to end one argument, but found trailing data",
+                        &reader,
+                    )),
+                    _ => Ok(()),
+                });
+            if let Err(err) = parsed {
+                errors.push(err);
+                continue;
+            }
+
+            // a redirect only needs a space to continue, since the node it redirects to may be
+            // executable itself
+            let redirect = self.nodes[child].redirect;
+            let needed = if redirect.is_some() { 1 } else { 2 };
+            if reader.remaining().len() >= needed {
+                reader.read();
+                let next = redirect.unwrap_or(child);
+                let mut parse = self.parse_nodes(next, &reader, level, arguments);
+                if parse.node == next && redirect.is_some() {
+                    // nothing parsed after the redirect, so the command ends at this node
+                    parse.node = child;
+                }
+                potentials.push(parse);
+            } else {
+                potentials.push(Parse {
+                    node: child,
+                    cursor: reader.cursor,
+                    arguments,
+                    errors: Vec::new(),
+                });
+            }
+        }
+
+        // prefer a parse that read the whole command, then one with fewer errors
+        potentials
+            .into_iter()
+            .min_by_key(|parse| (parse.cursor < reader.input().len(), parse.errors.len()))
+            .unwrap_or(Parse {
+                node,
+                cursor: reader.cursor,
+                arguments,
+                errors,
+            })
+    }
+
+    /// The children of a node that could be parsed next. If the next word is one of the node's
+    /// literals, only that literal is tried, so arguments cannot shadow it.
+    fn relevant_children(&self, node: usize, reader: &StringReader, level: u8) -> Vec<usize> {
+        let visible = self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .filter(|child| self.nodes[*child].permission <= level);
+        let word = reader.remaining().split(' ').next().unwrap_or_default();
+
+        let mut arguments = Vec::new();
+        for child in visible {
+            match &self.nodes[child].kind {
+                NodeKind::Literal(name) if name == word => return vec![child],
+                NodeKind::Literal(_) => {}
+                _ => arguments.push(child),
+            }
+        }
+        arguments
+    }
+
+    /// Suggest how to complete a partly typed command. Returns the byte offset in the command that
+    /// the suggestions replace the rest of the command from.
+    pub fn suggest(
+        &self,
+        world: &mut World,
+        sender: &CommandSender,
+        command: &str,
+    ) -> (usize, Vec<String>) {
+        let level = sender.permission_level(world);
+        let mut found = Vec::new();
+        let reader = StringReader::new(command);
+        self.suggest_nodes(world, sender, level, ROOT, &reader, &mut found);
+
+        // suggestions for different parts of the command all replace it from the earliest one
+        let start = found
+            .iter()
+            .map(|(start, _)| *start)
+            .min()
+            .unwrap_or(command.len());
+        let mut suggestions: Vec<String> = found
+            .into_iter()
+            .map(|(offset, text)| format!("{}{text}", &command[start..offset]))
+            .collect();
+        suggestions.sort();
+        suggestions.dedup();
+        (start, suggestions)
+    }
+
+    /// Find suggestions for the children of `node` that the rest of the command could be.
+    fn suggest_nodes(
+        &self,
+        world: &mut World,
+        sender: &CommandSender,
+        level: u8,
+        node: usize,
+        reader: &StringReader,
+        found: &mut Vec<(usize, String)>,
+    ) {
+        let visible: Vec<usize> = self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .filter(|child| self.nodes[*child].permission <= level)
+            .collect();
+
+        for child in visible {
+            let mut next = reader.clone();
+            let parsed = self.nodes[child].parse(&mut next, &mut Vec::new()).is_ok();
+            if parsed && next.eat(' ') {
+                let node = self.nodes[child].redirect.unwrap_or(child);
+                self.suggest_nodes(world, sender, level, node, &next, found);
+            } else if !parsed || !next.can_read() {
+                // the command ends within this node, so suggest what it could be
+                let typed = reader.remaining();
+                let suggestions = match &self.nodes[child].kind {
+                    NodeKind::Root => Vec::new(),
+                    NodeKind::Literal(name) => vec![name.clone()],
+                    NodeKind::Argument { ty, .. } => match &self.nodes[child].suggests {
+                        Some(provider) => provider(world, sender, typed),
+                        None => ty.suggest(world, typed),
+                    },
+                };
+                found.extend(
+                    suggestions
+                        .into_iter()
+                        .filter(|suggestion| suggestion.starts_with(typed))
+                        .map(|suggestion| (reader.cursor, suggestion)),
+                );
+            }
+        }
+    }
+
+    /// The commands that a sender with a permission level can use, as the packet that tells
+    /// clients about them.
+    pub fn graph(&self, level: u8) -> DeclareCommands {
+        // number the visible nodes breadth first, starting from the root
+        let mut indices = vec![None; self.nodes.len()];
+        let mut order = vec![ROOT];
+        indices[ROOT] = Some(0);
+        let mut queue = VecDeque::from([ROOT]);
+        while let Some(node) = queue.pop_front() {
+            for &child in &self.nodes[node].children {
+                if self.nodes[child].permission <= level && indices[child].is_none() {
+                    indices[child] = Some(order.len() as i32);
+                    order.push(child);
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        let nodes = order
+            .iter()
+            .map(|&id| {
+                let node = &self.nodes[id];
+                let kind = match &node.kind {
+                    NodeKind::Root => CommandNodeKind::Root,
+                    NodeKind::Literal(name) => CommandNodeKind::Literal { name: name.clone() },
+                    NodeKind::Argument { name, ty } => CommandNodeKind::Argument {
+                        name: name.clone(),
+                        parser: VarInt(ty.parser_id()),
+                        properties: ty.properties(),
+                        suggestions: node
+                            .suggests
+                            .as_ref()
+                            .map(|_| "minecraft:ask_server".to_string()),
+                    },
+                };
+                CommandNode {
+                    kind,
+                    executable: node.executor.is_some(),
+                    restricted: node.permission > 0,
+                    children: node
+                        .children
+                        .iter()
+                        .filter_map(|child| indices[*child].map(VarInt))
+                        .collect(),
+                    // a redirect to a node the sender cannot see is left out
+                    redirect: node
+                        .redirect
+                        .and_then(|redirect| indices[redirect].map(VarInt)),
+                }
+            })
+            .collect();
+        DeclareCommands {
+            nodes,
+            root_index: VarInt(0),
+        }
+    }
+}
+
+/// An error for when an argument chooses nothing, with the translation key of its message.
+fn not_found(key: &str) -> CommandError {
+    CommandError::new(Text::translate(key, [] as [Text; 0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_net::client::command::CommandNodeKind;
+    use bevy_ecs::world::World;
+
+    use super::{CommandDispatcher, argument, literal};
+    use crate::command::{ArgumentType, CommandSender, StringKind};
+
+    fn dispatcher() -> CommandDispatcher {
+        let mut dispatcher = CommandDispatcher::default();
+        let msg = dispatcher.register(literal("msg").then(
+            argument("target", ArgumentType::String(StringKind::Word)).then(
+                argument("message", ArgumentType::Message).executes(|ctx| {
+                    let target: String = ctx.get("target");
+                    let message: String = ctx.get("message");
+                    ctx.feedback(format!("{target}: {message}"));
+                    Ok(())
+                }),
+            ),
+        ));
+        dispatcher.register(literal("tell").redirect(msg));
+        dispatcher.register(literal("add").requires(2).then(
+            argument("a", ArgumentType::INTEGER).then(
+                argument("b", ArgumentType::INTEGER).executes(|ctx| {
+                    let sum = ctx.get::<i32>("a") + ctx.get::<i32>("b");
+                    ctx.feedback(sum.to_string());
+                    Ok(())
+                }),
+            ),
+        ));
+        dispatcher
+    }
+
+    #[test]
+    fn test_execute() {
+        let dispatcher = dispatcher();
+        let mut world = World::new();
+        let player = CommandSender::Player(world.spawn_empty().id());
+        let mut run = |sender: CommandSender, command: &str| {
+            dispatcher
+                .execute(&mut world, sender, command)
+                .map_err(|err| err.lines())
+        };
+
+        assert_eq!(run(CommandSender::Console, "add 1 2"), Ok(vec!["3".into()]));
+        assert_eq!(
+            run(player.clone(), "tell bob hi there"),
+            Ok(vec!["bob: hi there".into()])
+        );
+        assert_eq!(
+            run(CommandSender::Console, "add 1 x"),
+            Err(vec!["Expected integer".into(), "add 1 x<--[HERE]".into()])
+        );
+        assert_eq!(
+            run(CommandSender::Console, "add 1"),
+            Err(vec![
+                "Unknown or incomplete command, see below for error".into(),
+                "add 1<--[HERE]".into()
+            ])
+        );
+        // players below the required permission level cannot see the command
+        assert_eq!(
+            run(player, "add 1 2"),
+            Err(vec![
+                "Unknown or incomplete command, see below for error".into(),
+                "add 1 2<--[HERE]".into()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let dispatcher = dispatcher();
+        let mut world = World::new();
+        let mut suggest =
+            |command: &str| dispatcher.suggest(&mut world, &CommandSender::Console, command);
+
+        assert_eq!(
+            suggest(""),
+            (0, vec!["add".into(), "msg".into(), "tell".into()])
+        );
+        assert_eq!(suggest("t"), (0, vec!["tell".into()]));
+        assert_eq!(suggest("add 1 "), (6, vec![]));
+    }
+
+    #[test]
+    fn test_graph() {
+        let dispatcher = dispatcher();
+        let names = |level| {
+            let graph = dispatcher.graph(level);
+            let root = &graph.nodes[0];
+            root.children
+                .iter()
+                .map(|child| match &graph.nodes[child.0 as usize].kind {
+                    CommandNodeKind::Literal { name } => name.clone(),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(0), ["msg", "tell"]);
+        assert_eq!(names(4), ["msg", "tell", "add"]);
+
+        // the alias redirects to the command's node
+        let graph = dispatcher.graph(0);
+        assert_eq!(
+            graph.nodes[2].redirect,
+            Some(beacon_codec::types::VarInt(1))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use crate::command::reader::{CommandError, StringReader};
+
+/// An NBT tag, as written in a command, e.g. `{Tags:["a"],Pos:[0.0d,64.0d,0.0d]}`.
+///
+/// See: <https://minecraft.wiki/w/NBT_format#SNBT_format>
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+    /// A byte, e.g. `1b` or `true`.
+    Byte(i8),
+    /// A short, e.g. `1s`.
+    Short(i16),
+    /// An integer, e.g. `1`.
+    Int(i32),
+    /// A long, e.g. `1l`.
+    Long(i64),
+    /// A float, e.g. `1.0f`.
+    Float(f32),
+    /// A double, e.g. `1.0` or `1d`.
+    Double(f64),
+    /// A string, quoted or not.
+    String(String),
+    /// A list of tags, e.g. `[1,2,3]`.
+    List(Vec<Nbt>),
+    /// An array of bytes, e.g. `[B;1b,2b]`.
+    ByteArray(Vec<i8>),
+    /// An array of integers, e.g. `[I;1,2]`.
+    IntArray(Vec<i32>),
+    /// An array of longs, e.g. `[L;1l,2l]`.
+    LongArray(Vec<i64>),
+    /// Named tags, e.g. `{name:"value"}`.
+    Compound(BTreeMap<String, Nbt>),
+}
+
+impl Nbt {
+    /// Parse a compound, starting at its `{`.
+    pub fn parse_compound(reader: &mut StringReader) -> Result<Self, CommandError> {
+        if reader.peek() != Some('{') {
+            return Err(CommandError::at("Expected '{'", reader));
+        }
+        Self::parse(reader)
+    }
+
+    /// Parse any tag.
+    fn parse(reader: &mut StringReader) -> Result<Self, CommandError> {
+        reader.skip_whitespace();
+        match reader.peek() {
+            Some('{') => {
+                reader.read();
+                let mut compound = BTreeMap::new();
+                parse_elements(reader, '}', |reader| {
+                    let key = reader.read_string()?;
+                    if key.is_empty() {
+                        return Err(CommandError::at("Expected key", reader));
+                    }
+                    reader.skip_whitespace();
+                    reader.expect(':')?;
+                    compound.insert(key, Self::parse(reader)?);
+                    Ok(())
+                })?;
+                Ok(Self::Compound(compound))
+            }
+            Some('[') => {
+                reader.read();
+                let array = match reader.remaining().get(..2) {
+                    Some(prefix @ ("B;" | "I;" | "L;")) => prefix.chars().next(),
+                    _ => None,
+                };
+                if array.is_some() {
+                    reader.read();
+                    reader.read();
+                }
+
+                let mut list = Vec::new();
+                parse_elements(reader, ']', |reader| {
+                    let start = reader.cursor;
+                    let tag = Self::parse(reader)?;
+                    if array.is_some() && tag.as_integer().is_none() {
+                        let message = "Arrays can only contain integers";
+                        return Err(CommandError::at_cursor(message, reader, start));
+                    }
+                    list.push(tag);
+                    Ok(())
+                })?;
+
+                let integers = list.iter().filter_map(Self::as_integer);
+                let out_of_range = || CommandError::at("Array element out of range", reader);
+                Ok(match array {
+                    Some('B') => Self::ByteArray(
+                        integers
+                            .map(i8::try_from)
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| out_of_range())?,
+                    ),
+                    Some('I') => Self::IntArray(
+                        integers
+                            .map(i32::try_from)
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| out_of_range())?,
+                    ),
+                    Some(_) => Self::LongArray(integers.collect()),
+                    None => Self::List(list),
+                })
+            }
+            Some('"' | '\'') => Ok(Self::String(reader.read_quoted_string()?)),
+            _ => {
+                let value = reader.read_unquoted_string();
+                if value.is_empty() {
+                    return Err(CommandError::at("Expected value", reader));
+                }
+                Ok(Self::from_unquoted(value))
+            }
+        }
+    }
+
+    /// The tag an unquoted value stands for: a number if it looks like one, otherwise a string.
+    fn from_unquoted(value: &str) -> Self {
+        match value {
+            "true" => return Self::Byte(1),
+            "false" => return Self::Byte(0),
+            _ => {}
+        }
+        if !value.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) {
+            return Self::String(value.to_string());
+        }
+
+        let (number, suffix) = match value.char_indices().last() {
+            Some((i, suffix)) if suffix.is_ascii_alphabetic() => {
+                (&value[..i], Some(suffix.to_ascii_lowercase()))
+            }
+            _ => (value, None),
+        };
+        let tag = match suffix {
+            Some('b') => number.parse().map(Self::Byte).ok(),
+            Some('s') => number.parse().map(Self::Short).ok(),
+            Some('l') => number.parse().map(Self::Long).ok(),
+            Some('f') => number.parse().map(Self::Float).ok(),
+            Some('d') => number.parse().map(Self::Double).ok(),
+            Some(_) => None,
+            None => (number.parse().map(Self::Int).ok())
+                .or_else(|| number.parse().map(Self::Double).ok()),
+        };
+        tag.unwrap_or_else(|| Self::String(value.to_string()))
+    }
+
+    /// The value of a byte, short, int or long.
+    fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Self::Byte(value) => Some(value.into()),
+            Self::Short(value) => Some(value.into()),
+            Self::Int(value) => Some(value.into()),
+            Self::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Whether this tag has everything `other` has, like vanilla: compounds must have every key
+    /// in `other` with a matching value, and lists must have a match for every element of
+    /// `other`, but may have more. Anything else must be equal.
+    pub fn contains(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Compound(tag), Self::Compound(other)) => other
+                .iter()
+                .all(|(key, value)| tag.get(key).is_some_and(|tag| tag.contains(value))),
+            (Self::List(tag), Self::List(other)) if other.is_empty() => tag.is_empty(),
+            (Self::List(tag), Self::List(other)) => other
+                .iter()
+                .all(|value| tag.iter().any(|tag| tag.contains(value))),
+            _ => self == other,
+        }
+    }
+}
+
+/// Parse the comma separated elements of a compound or list, up to and including `end`.
+fn parse_elements(
+    reader: &mut StringReader,
+    end: char,
+    mut parse: impl FnMut(&mut StringReader) -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    reader.skip_whitespace();
+    while !reader.eat(end) {
+        if !reader.can_read() {
+            return Err(CommandError::at(format!("Expected '{end}'"), reader));
+        }
+        parse(reader)?;
+        reader.skip_whitespace();
+        if reader.eat(',') {
+            reader.skip_whitespace();
+        } else if reader.peek() != Some(end) {
+            return Err(CommandError::at(format!("Expected '{end}'"), reader));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Nbt;
+    use crate::command::StringReader;
+
+    fn parse(input: &str) -> Nbt {
+        Nbt::parse_compound(&mut StringReader::new(input)).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let Nbt::Compound(compound) =
+            parse(r#"{a:1b, "b c":[I;1,2], d:[1.5,2d], e:'x', f:3.0f, g:true, h:word}"#)
+        else {
+            panic!("expected a compound");
+        };
+        assert_eq!(compound["a"], Nbt::Byte(1));
+        assert_eq!(compound["b c"], Nbt::IntArray(vec![1, 2]));
+        assert_eq!(
+            compound["d"],
+            Nbt::List(vec![Nbt::Double(1.5), Nbt::Double(2.0)])
+        );
+        assert_eq!(compound["e"], Nbt::String("x".into()));
+        assert_eq!(compound["f"], Nbt::Float(3.0));
+        assert_eq!(compound["g"], Nbt::Byte(1));
+        assert_eq!(compound["h"], Nbt::String("word".into()));
+
+        let err = |input| Nbt::parse_compound(&mut StringReader::new(input)).unwrap_err();
+        assert_eq!(err("{a:1").message.to_string(), "Expected '}'");
+        assert_eq!(
+            err("{a:[B;300]}").message.to_string(),
+            "Array element out of range"
+        );
+        assert_eq!(err("[]").message.to_string(), "Expected '{'");
+    }
+
+    #[test]
+    fn test_contains() {
+        let data = parse(r#"{Tags:["a","b"],Pos:[1.0d,2.0d,3.0d],Name:"x"}"#);
+        assert!(data.contains(&parse("{}")));
+        assert!(data.contains(&parse(r#"{Tags:["b"]}"#)));
+        assert!(data.contains(&parse("{Pos:[1.0d,2.0d,3.0d],Name:x}")));
+        assert!(!data.contains(&parse("{Tags:[]}")));
+        assert!(!data.contains(&parse(r#"{Tags:["c"]}"#)));
+        // the type must match, too
+        assert!(!data.contains(&parse("{Pos:[1,2,3]}")));
+        assert!(!data.contains(&parse("{Missing:1}")));
+    }
+}
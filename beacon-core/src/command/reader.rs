@@ -0,0 +1,268 @@
+use std::{fmt, str::FromStr};
+
+use beacon_net::text::Text;
+
+/// How many characters of the command to show before the position of an error.
+const CONTEXT_AMOUNT: usize = 10;
+
+/// An error from parsing or running a command, shown to the sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    /// What went wrong.
+    pub message: Text,
+    /// The command and the position in it where parsing failed, if it failed.
+    pub input: Option<(String, usize)>,
+}
+
+impl CommandError {
+    /// An error that is not tied to a position in the command, e.g. from running it.
+    pub fn new(message: impl Into<Text>) -> Self {
+        Self {
+            message: message.into(),
+            input: None,
+        }
+    }
+
+    /// An error at the reader's current position.
+    pub fn at(message: impl Into<Text>, reader: &StringReader) -> Self {
+        Self::at_cursor(message, reader, reader.cursor)
+    }
+
+    /// An error at a position in the reader's input.
+    pub fn at_cursor(message: impl Into<Text>, reader: &StringReader, cursor: usize) -> Self {
+        Self {
+            message: message.into(),
+            input: Some((reader.input.to_string(), cursor)),
+        }
+    }
+
+    /// The position in the command where parsing failed, if it did.
+    pub fn cursor(&self) -> Option<usize> {
+        self.input.as_ref().map(|(_, cursor)| *cursor)
+    }
+
+    /// The error as lines of feedback: the message, then the command around the error, like
+    /// vanilla.
+    pub fn lines(&self) -> Vec<Text> {
+        let Some((input, cursor)) = &self.input else {
+            return vec![self.message.clone()];
+        };
+        let (before, after) = input.split_at(*cursor);
+        let start = before
+            .char_indices()
+            .rev()
+            .nth(CONTEXT_AMOUNT - 1)
+            .map_or(0, |(i, _)| i);
+        let ellipsis = if start > 0 { "..." } else { "" };
+        vec![
+            self.message.clone(),
+            Text::from(format!("{ellipsis}{}{after}<--[HERE]", &before[start..])),
+        ]
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.lines().iter().map(Text::to_string).collect();
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Reads a command one piece at a time, like Brigadier's `StringReader`.
+#[derive(Debug, Clone)]
+pub struct StringReader<'a> {
+    input: &'a str,
+    /// The byte offset of the next character to read.
+    pub cursor: usize,
+}
+
+impl<'a> StringReader<'a> {
+    /// Read from the start of the input.
+    pub fn new(input: &'a str) -> Self {
+        Self { input, cursor: 0 }
+    }
+
+    /// The whole input.
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// The input that has not been read yet.
+    pub fn remaining(&self) -> &'a str {
+        &self.input[self.cursor..]
+    }
+
+    /// Whether there is anything left to read.
+    pub fn can_read(&self) -> bool {
+        self.cursor < self.input.len()
+    }
+
+    /// The next character, without reading it.
+    pub fn peek(&self) -> Option<char> {
+        self.remaining().chars().next()
+    }
+
+    /// Read the next character.
+    pub fn read(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.cursor += c.len_utf8();
+        Some(c)
+    }
+
+    /// Read the next character if it is `c`.
+    pub fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.cursor += c.len_utf8();
+        }
+        eaten
+    }
+
+    /// Read the next character, which must be `c`.
+    pub fn expect(&mut self, c: char) -> Result<(), CommandError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(CommandError::at(format!("Expected '{c}'"), self))
+        }
+    }
+
+    /// Read characters while they match `f`.
+    pub fn read_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.cursor;
+        while self.peek().is_some_and(&f) {
+            self.read();
+        }
+        &self.input[start..self.cursor]
+    }
+
+    /// Skip any spaces.
+    pub fn skip_whitespace(&mut self) {
+        self.read_while(|c| c == ' ');
+    }
+
+    /// Read a word made of the characters allowed in an unquoted string.
+    pub fn read_unquoted_string(&mut self) -> &'a str {
+        self.read_while(is_unquoted_char)
+    }
+
+    /// Read a string surrounded by single or double quotes, with `\` escaping the quote.
+    pub fn read_quoted_string(&mut self) -> Result<String, CommandError> {
+        let Some(quote @ ('"' | '\'')) = self.peek() else {
+            return Err(CommandError::at("Expected quote to start a string", self));
+        };
+        self.read();
+        let mut string = String::new();
+        loop {
+            match self.read() {
+                Some('\\') => match self.read() {
+                    Some(c) if c == quote || c == '\\' => string.push(c),
+                    Some(c) => {
+                        self.cursor -= c.len_utf8();
+                        let message = format!("Invalid escape sequence '\\{c}' in quoted string");
+                        return Err(CommandError::at(message, self));
+                    }
+                    None => break,
+                },
+                Some(c) if c == quote => return Ok(string),
+                Some(c) => string.push(c),
+                None => break,
+            }
+        }
+        Err(CommandError::at("Unclosed quoted string", self))
+    }
+
+    /// Read a quoted or unquoted string.
+    pub fn read_string(&mut self) -> Result<String, CommandError> {
+        match self.peek() {
+            Some('"' | '\'') => self.read_quoted_string(),
+            _ => Ok(self.read_unquoted_string().to_string()),
+        }
+    }
+
+    /// Read `true` or `false`.
+    pub fn read_bool(&mut self) -> Result<bool, CommandError> {
+        let start = self.cursor;
+        match self.read_string()?.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            "" => Err(CommandError::at("Expected boolean", self)),
+            value => {
+                self.cursor = start;
+                let message =
+                    format!("Invalid boolean, expected 'true' or 'false' but found '{value}'");
+                Err(CommandError::at(message, self))
+            }
+        }
+    }
+
+    /// Read an integer.
+    pub fn read_int(&mut self) -> Result<i32, CommandError> {
+        self.read_number("integer")
+    }
+
+    /// Read a long integer.
+    pub fn read_long(&mut self) -> Result<i64, CommandError> {
+        self.read_number("long")
+    }
+
+    /// Read a float.
+    pub fn read_float(&mut self) -> Result<f32, CommandError> {
+        self.read_number("float")
+    }
+
+    /// Read a double.
+    pub fn read_double(&mut self) -> Result<f64, CommandError> {
+        self.read_number("double")
+    }
+
+    fn read_number<T: FromStr>(&mut self, kind: &str) -> Result<T, CommandError> {
+        let start = self.cursor;
+        let number = self.read_while(|c| c.is_ascii_digit() || c == '.' || c == '-');
+        if number.is_empty() {
+            return Err(CommandError::at(format!("Expected {kind}"), self));
+        }
+        number.parse().map_err(|_| {
+            self.cursor = start;
+            CommandError::at(format!("Invalid {kind} '{number}'"), self)
+        })
+    }
+}
+
+/// Whether a character can be part of an unquoted string.
+pub fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_net::text::Text;
+
+    use super::StringReader;
+
+    #[test]
+    fn test_read() {
+        let mut reader = StringReader::new(r#"word "quoted \" string" 12 -1.5 true"#);
+        assert_eq!(reader.read_unquoted_string(), "word");
+        reader.skip_whitespace();
+        assert_eq!(reader.read_string().unwrap(), r#"quoted " string"#);
+        reader.skip_whitespace();
+        assert_eq!(reader.read_int().unwrap(), 12);
+        reader.skip_whitespace();
+        assert_eq!(reader.read_double().unwrap(), -1.5);
+        reader.skip_whitespace();
+        assert!(reader.read_bool().unwrap());
+        assert!(!reader.can_read());
+
+        let mut reader = StringReader::new("weather clear 1.2.3");
+        reader.cursor = 14;
+        let err = reader.read_int().unwrap_err();
+        assert_eq!(
+            err.lines(),
+            ["Invalid integer '1.2.3'", "...her clear 1.2.3<--[HERE]"].map(Text::from)
+        );
+        assert_eq!(err.cursor(), Some(14));
+    }
+}
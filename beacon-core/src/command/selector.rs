@@ -0,0 +1,1049 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+use beacon_codec::ProtocolState;
+use beacon_net::{
+    entity::{CustomName, EntityType, Position, Rotation, Scores, Tags, Team},
+    player::{GameMode, PlayerIdentity},
+    text::Text,
+};
+use bevy_ecs::{prelude::*, query::QueryData};
+use rand::seq::SliceRandom;
+use uuid::Uuid;
+
+use crate::command::{
+    CommandSender, PermissionLevel,
+    argument::read_resource_location,
+    nbt::Nbt,
+    reader::{CommandError, StringReader},
+};
+
+/// The longest a player's name can be.
+const MAX_NAME_LENGTH: usize = 16;
+
+/// The type of every player.
+const PLAYER_TYPE: &str = "minecraft:player";
+
+/// The options a selector can have, in the order they are suggested.
+const OPTIONS: [&str; 19] = [
+    "name",
+    "distance",
+    "x",
+    "y",
+    "z",
+    "dx",
+    "dy",
+    "dz",
+    "x_rotation",
+    "y_rotation",
+    "limit",
+    "sort",
+    "gamemode",
+    "team",
+    "type",
+    "tag",
+    "scores",
+    "nbt",
+    "predicate",
+];
+
+/// Entities chosen by a command: with a selector, by a player's name, or by an entity's UUID.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityArgument {
+    /// Entities matching a selector, e.g. `@e[type=pig,limit=3]`.
+    Selector(Box<EntitySelector>),
+    /// A player, by name.
+    Name(String),
+    /// An entity, by UUID.
+    Uuid(u128),
+}
+
+impl EntityArgument {
+    /// Parse the argument, checking that the selector can only choose a single entity or only
+    /// players if the argument requires it.
+    pub(crate) fn parse(
+        reader: &mut StringReader,
+        single: bool,
+        players_only: bool,
+    ) -> Result<Self, CommandError> {
+        let start = reader.cursor;
+        if reader.peek() == Some('@') {
+            let selector = EntitySelector::parse(reader)?;
+            if single && selector.max_results() > 1 {
+                let what = if players_only { "player" } else { "entity" };
+                let message = format!(
+                    "Only one {what} is allowed, but the provided selector allows more than one"
+                );
+                return Err(CommandError::at_cursor(message, reader, start));
+            }
+            if players_only && selector.includes_entities() && selector.kind != SelectorKind::Sender
+            {
+                let message = concat!(
+                    "Only players may be affected by this command, ",
+                    "but the provided selector includes entities"
+                );
+                return Err(CommandError::at_cursor(message, reader, start));
+            }
+            return Ok(Self::Selector(Box::new(selector)));
+        }
+
+        let name = reader.read_while(|c| c != ' ');
+        if let Ok(uuid) = Uuid::try_parse(name) {
+            return Ok(Self::Uuid(uuid.as_u128()));
+        }
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            reader.cursor = start;
+            return Err(CommandError::at("Invalid name or UUID", reader));
+        }
+        Ok(Self::Name(name.to_string()))
+    }
+
+    /// Find the entities the argument chooses, which may be none.
+    pub fn select(
+        &self,
+        world: &mut World,
+        sender: &CommandSender,
+    ) -> Result<Vec<Entity>, CommandError> {
+        match self {
+            Self::Selector(selector) => {
+                if sender.permission_level(world) < PermissionLevel::GAMEMASTER {
+                    return Err(CommandError::new(Text::translate(
+                        "argument.entity.selector.not_allowed",
+                        [] as [Text; 0],
+                    )));
+                }
+                selector.select(world, sender)
+            }
+            Self::Name(name) => {
+                let mut query = world.query::<(Entity, &PlayerIdentity, &ProtocolState)>();
+                let found = query.iter(world).find(|(_, identity, state)| {
+                    **state == ProtocolState::Play && identity.name.eq_ignore_ascii_case(name)
+                });
+                Ok(found.map(|(entity, ..)| entity).into_iter().collect())
+            }
+            Self::Uuid(uuid) => {
+                let mut query = world.query::<(Entity, &PlayerIdentity, &ProtocolState)>();
+                let found = query.iter(world).find(|(_, identity, state)| {
+                    **state == ProtocolState::Play && identity.uuid == *uuid
+                });
+                Ok(found.map(|(entity, ..)| entity).into_iter().collect())
+            }
+        }
+    }
+
+    /// Suggest selectors, selector options and player names, given the part of the argument
+    /// typed so far.
+    pub(crate) fn suggest(world: &mut World, typed: &str) -> Vec<String> {
+        let Some(options) = typed.find('[') else {
+            let mut suggestions: Vec<String> = SelectorKind::ALL
+                .iter()
+                .map(|kind| kind.to_string())
+                .collect();
+            if let Some(kind) = SelectorKind::ALL
+                .iter()
+                .find(|kind| kind.to_string() == typed)
+            {
+                suggestions.push(format!("{kind}["));
+            }
+            let mut query = world.query::<(&PlayerIdentity, &ProtocolState)>();
+            suggestions.extend(
+                query
+                    .iter(world)
+                    .filter(|(_, state)| **state == ProtocolState::Play)
+                    .map(|(identity, _)| identity.name.clone()),
+            );
+            return suggestions;
+        };
+
+        // only the option being typed is completed
+        let current = typed[options..]
+            .rfind([',', '['])
+            .map_or(options, |i| options + i + 1);
+        let (before, current) = typed.split_at(current);
+        let before = format!(
+            "{before}{}",
+            &current[..current.len() - current.trim_start().len()]
+        );
+        let Some((option, value)) = current.trim_start().split_once('=') else {
+            return OPTIONS
+                .iter()
+                .map(|option| format!("{before}{option}="))
+                .collect();
+        };
+
+        let values: Vec<String> = match option.trim_end() {
+            "sort" => Sort::ALL
+                .iter()
+                .map(|sort| sort.name().to_string())
+                .collect(),
+            "gamemode" => GameMode::ALL
+                .iter()
+                .flat_map(|mode| [mode.name().to_string(), format!("!{}", mode.name())])
+                .collect(),
+            "type" => vec![PLAYER_TYPE.to_string(), format!("!{PLAYER_TYPE}")],
+            _ => Vec::new(),
+        };
+        let mut suggestions: Vec<String> = values
+            .into_iter()
+            .map(|value| format!("{before}{option}={value}"))
+            .collect();
+        if !value.trim().is_empty() {
+            suggestions.extend([format!("{typed},"), format!("{typed}]")]);
+        }
+        suggestions
+    }
+}
+
+/// Which entities a selector starts from, and its defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum SelectorKind {
+    /// `@p`, the nearest player.
+    #[display("@p")]
+    NearestPlayer,
+    /// `@r`, a random player.
+    #[display("@r")]
+    RandomPlayer,
+    /// `@a`, every player.
+    #[display("@a")]
+    AllPlayers,
+    /// `@e`, every entity.
+    #[display("@e")]
+    AllEntities,
+    /// `@s`, the entity running the command.
+    #[display("@s")]
+    Sender,
+    /// `@n`, the nearest entity.
+    #[display("@n")]
+    NearestEntity,
+}
+
+impl SelectorKind {
+    /// Every kind of selector.
+    pub const ALL: [Self; 6] = [
+        Self::NearestPlayer,
+        Self::RandomPlayer,
+        Self::AllPlayers,
+        Self::AllEntities,
+        Self::Sender,
+        Self::NearestEntity,
+    ];
+
+    /// Whether the selector only chooses players.
+    fn players_only(self) -> bool {
+        matches!(
+            self,
+            Self::NearestPlayer | Self::RandomPlayer | Self::AllPlayers
+        )
+    }
+}
+
+/// The order a selector chooses entities in, before its limit is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// Nearest first.
+    Nearest,
+    /// Furthest first.
+    Furthest,
+    /// In a random order.
+    Random,
+    /// In no particular order, which is the fastest.
+    Arbitrary,
+}
+
+impl Sort {
+    const ALL: [Self; 4] = [Self::Nearest, Self::Furthest, Self::Random, Self::Arbitrary];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "nearest",
+            Self::Furthest => "furthest",
+            Self::Random => "random",
+            Self::Arbitrary => "arbitrary",
+        }
+    }
+}
+
+/// A range of values, which either end may be left open, e.g. `1..5`, `..5` or `3`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range<T> {
+    /// The smallest value in the range.
+    pub min: Option<T>,
+    /// The largest value in the range.
+    pub max: Option<T>,
+}
+
+impl<T: PartialOrd + Copy + FromStr> Range<T> {
+    /// Whether the value is within the range.
+    pub fn contains(&self, value: T) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    fn parse(reader: &mut StringReader) -> Result<Self, CommandError> {
+        let start = reader.cursor;
+        let number = |reader: &mut StringReader| {
+            let number_start = reader.cursor;
+            while reader
+                .peek()
+                .is_some_and(|c| c.is_ascii_digit() || c == '-' || c == '.')
+                && !reader.remaining().starts_with("..")
+            {
+                reader.read();
+            }
+            let number = &reader.input()[number_start..reader.cursor];
+            match number {
+                "" => Ok(None),
+                number => number.parse().map(Some).map_err(|_| {
+                    reader.cursor = number_start;
+                    CommandError::at(format!("Invalid number '{number}'"), reader)
+                }),
+            }
+        };
+
+        let min = number(reader)?;
+        let max = match reader.remaining().starts_with("..") {
+            true => {
+                reader.cursor += 2;
+                number(reader)?
+            }
+            false => min,
+        };
+        if min.is_none() && max.is_none() {
+            reader.cursor = start;
+            return Err(CommandError::at(
+                "Expected value or range of values",
+                reader,
+            ));
+        }
+        if let (Some(min), Some(max)) = (min, max)
+            && min > max
+        {
+            reader.cursor = start;
+            return Err(CommandError::at("Min cannot be bigger than max", reader));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+impl Range<f32> {
+    /// Whether an angle is within the range, where the range wraps around, e.g. `170..-170`.
+    fn contains_angle(&self, angle: f32) -> bool {
+        let wrap = |angle: f32| (angle + 180.0).rem_euclid(360.0) - 180.0;
+        let angle = wrap(angle);
+        match (self.min.map(wrap), self.max.map(wrap)) {
+            (Some(min), Some(max)) if min > max => angle >= min || angle <= max,
+            (min, max) => min.is_none_or(|min| angle >= min) && max.is_none_or(|max| angle <= max),
+        }
+    }
+}
+
+/// A selector option's value, which can be negated with `!`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter<T> {
+    /// The value to match.
+    pub value: T,
+    /// Whether to match entities that do not have the value instead.
+    pub negated: bool,
+}
+
+impl<T> Filter<T> {
+    /// Whether the filter keeps an entity, given whether it has the value.
+    fn keeps(&self, matches: bool) -> bool {
+        matches != self.negated
+    }
+}
+
+/// A parsed target selector, such as `@a[distance=..10,sort=nearest]`.
+///
+/// See: <https://minecraft.wiki/w/Target_selectors>
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySelector {
+    /// Which entities the selector starts from.
+    pub kind: SelectorKind,
+    /// The most entities to choose.
+    pub limit: Option<usize>,
+    /// The order to choose entities in.
+    pub sort: Option<Sort>,
+    /// The position to search from, instead of the sender's position.
+    pub origin: [Option<f64>; 3],
+    /// The size of the box to search in, from the origin.
+    pub volume: [Option<f64>; 3],
+    /// How far from the origin entities may be.
+    pub distance: Option<Range<f64>>,
+    /// The pitch entities may be looking at.
+    pub x_rotation: Option<Range<f32>>,
+    /// The yaw entities may be looking at.
+    pub y_rotation: Option<Range<f32>>,
+    /// Entity types, e.g. `minecraft:pig`, or entity type tags starting with `#`.
+    pub types: Vec<Filter<String>>,
+    /// Names, of players or named entities.
+    pub names: Vec<Filter<String>>,
+    /// Scoreboard tags. An empty tag matches entities without any tags.
+    pub tags: Vec<Filter<String>>,
+    /// Team names. An empty name matches entities without a team.
+    pub teams: Vec<Filter<String>>,
+    /// Game modes, which only players have.
+    pub gamemodes: Vec<Filter<GameMode>>,
+    /// Score ranges, by objective.
+    pub scores: Vec<(String, Range<i32>)>,
+    /// NBT compounds that entities' data must contain. An entity's data is made from its
+    /// components, as `Pos`, `Rotation`, `Tags` and `CustomName`, along with `UUID` and
+    /// `playerGameType` for players.
+    pub nbt: Vec<Filter<Nbt>>,
+    /// Predicates entities must pass, by ID.
+    pub predicates: Vec<Filter<String>>,
+}
+
+/// The data of an entity that selectors can filter by.
+#[derive(QueryData)]
+struct Candidate {
+    entity: Entity,
+    identity: Option<&'static PlayerIdentity>,
+    state: Option<&'static ProtocolState>,
+    ty: Option<&'static EntityType>,
+    position: Option<&'static Position>,
+    rotation: Option<&'static Rotation>,
+    custom_name: Option<&'static CustomName>,
+    tags: Option<&'static Tags>,
+    team: Option<&'static Team>,
+    scores: Option<&'static Scores>,
+    gamemode: Option<&'static GameMode>,
+}
+
+impl CandidateItem<'_, '_> {
+    /// Whether the entity is a player, rather than a connection that is logging in.
+    fn is_player(&self) -> bool {
+        self.identity.is_some() && self.state == Some(&ProtocolState::Play)
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self.identity {
+            Some(identity) => Some(&identity.name),
+            None => self.custom_name.map(|name| name.as_str()),
+        }
+    }
+
+    /// The entity's data, for the `nbt` option.
+    fn nbt(&self) -> Nbt {
+        let position = self.position.copied().unwrap_or_default();
+        let rotation = self.rotation.copied().unwrap_or_default();
+        let mut data = BTreeMap::from([
+            (
+                "Pos".to_string(),
+                Nbt::List([position.x, position.y, position.z].map(Nbt::Double).into()),
+            ),
+            (
+                "Rotation".to_string(),
+                Nbt::List([rotation.yaw, rotation.pitch].map(Nbt::Float).into()),
+            ),
+        ]);
+        if let Some(tags) = self.tags.filter(|tags| !tags.is_empty()) {
+            let tags = tags.iter().cloned().map(Nbt::String).collect();
+            data.insert("Tags".to_string(), Nbt::List(tags));
+        }
+        if let Some(name) = self.custom_name {
+            data.insert("CustomName".to_string(), Nbt::String(name.0.clone()));
+        }
+        if let Some(identity) = self.identity {
+            let uuid = identity.uuid.to_be_bytes();
+            let uuid = uuid
+                .chunks(4)
+                .map(|int| i32::from_be_bytes(int.try_into().unwrap()))
+                .collect();
+            data.insert("UUID".to_string(), Nbt::IntArray(uuid));
+        }
+        if let Some(gamemode) = self.gamemode {
+            let gamemode = Nbt::Int(gamemode.id().into());
+            data.insert("playerGameType".to_string(), gamemode);
+        }
+        Nbt::Compound(data)
+    }
+}
+
+impl EntitySelector {
+    /// A selector without any options.
+    pub fn new(kind: SelectorKind) -> Self {
+        Self {
+            kind,
+            limit: None,
+            sort: None,
+            origin: [None; 3],
+            volume: [None; 3],
+            distance: None,
+            x_rotation: None,
+            y_rotation: None,
+            types: Vec::new(),
+            names: Vec::new(),
+            tags: Vec::new(),
+            teams: Vec::new(),
+            gamemodes: Vec::new(),
+            scores: Vec::new(),
+            nbt: Vec::new(),
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Parse a selector, starting at its `@`.
+    pub fn parse(reader: &mut StringReader) -> Result<Self, CommandError> {
+        let start = reader.cursor;
+        reader.expect('@')?;
+        let kind = match reader.read() {
+            Some('p') => SelectorKind::NearestPlayer,
+            Some('r') => SelectorKind::RandomPlayer,
+            Some('a') => SelectorKind::AllPlayers,
+            Some('e') => SelectorKind::AllEntities,
+            Some('s') => SelectorKind::Sender,
+            Some('n') => SelectorKind::NearestEntity,
+            Some(c) => {
+                reader.cursor = start;
+                return Err(CommandError::at(
+                    format!("Unknown selector type '@{c}'"),
+                    reader,
+                ));
+            }
+            None => return Err(CommandError::at("Missing selector type", reader)),
+        };
+
+        let mut selector = Self::new(kind);
+        if reader.eat('[') {
+            reader.skip_whitespace();
+            while !reader.eat(']') {
+                if !reader.can_read() {
+                    return Err(CommandError::at("Expected end of options", reader));
+                }
+                selector.parse_option(reader)?;
+                reader.skip_whitespace();
+                if reader.eat(',') {
+                    reader.skip_whitespace();
+                } else if reader.peek() != Some(']') {
+                    return Err(CommandError::at("Expected end of options", reader));
+                }
+            }
+        }
+        Ok(selector)
+    }
+
+    /// Parse a single `option=value`.
+    fn parse_option(&mut self, reader: &mut StringReader) -> Result<(), CommandError> {
+        let start = reader.cursor;
+        let option = reader.read_string()?;
+        reader.skip_whitespace();
+        if !reader.eat('=') {
+            reader.cursor = start;
+            let message = format!("Expected value for option '{option}'");
+            return Err(CommandError::at(message, reader));
+        }
+        reader.skip_whitespace();
+        let inapplicable = |reader: &StringReader| {
+            let message = format!("Option '{option}' isn't applicable here");
+            Err(CommandError::at_cursor(message, reader, start))
+        };
+        // options that can be negated may be given any number of times, but only once without
+        // being negated
+        let negated = |reader: &mut StringReader, filters: &[Filter<_>]| {
+            let negated = reader.eat('!');
+            reader.skip_whitespace();
+            let positive = filters
+                .iter()
+                .any(|filter: &Filter<String>| !filter.negated);
+            (negated, !negated && positive)
+        };
+
+        let value_start = reader.cursor;
+        match option.as_str() {
+            "name" => {
+                let (negated, repeated) = negated(reader, &self.names);
+                if repeated {
+                    return inapplicable(reader);
+                }
+                let value = reader.read_string()?;
+                self.names.push(Filter { value, negated });
+            }
+            "distance" if self.distance.is_none() => {
+                let distance = Range::parse(reader)?;
+                if distance.min.is_some_and(|min| min < 0.0) {
+                    let message = "Distance cannot be negative";
+                    return Err(CommandError::at_cursor(message, reader, value_start));
+                }
+                self.distance = Some(distance);
+            }
+            "x" | "y" | "z" | "dx" | "dy" | "dz" => {
+                let axis = match option.trim_start_matches('d') {
+                    "x" => 0,
+                    "y" => 1,
+                    _ => 2,
+                };
+                let values = match option.starts_with('d') {
+                    true => &mut self.volume,
+                    false => &mut self.origin,
+                };
+                if values[axis].is_some() {
+                    return inapplicable(reader);
+                }
+                values[axis] = Some(reader.read_double()?);
+            }
+            "x_rotation" if self.x_rotation.is_none() => {
+                self.x_rotation = Some(Range::parse(reader)?);
+            }
+            "y_rotation" if self.y_rotation.is_none() => {
+                self.y_rotation = Some(Range::parse(reader)?);
+            }
+            "limit" if self.kind != SelectorKind::Sender && self.limit.is_none() => {
+                let limit = reader.read_int()?;
+                if limit < 1 {
+                    let message = "Limit must be at least 1";
+                    return Err(CommandError::at_cursor(message, reader, value_start));
+                }
+                self.limit = Some(limit as usize);
+            }
+            "sort" if self.kind != SelectorKind::Sender && self.sort.is_none() => {
+                let name = reader.read_unquoted_string();
+                let sort = Sort::ALL.into_iter().find(|sort| sort.name() == name);
+                self.sort = Some(sort.ok_or_else(|| {
+                    let message = format!("Invalid or unknown sort type '{name}'");
+                    CommandError::at_cursor(message, reader, value_start)
+                })?);
+            }
+            "gamemode" => {
+                let negated = reader.eat('!');
+                reader.skip_whitespace();
+                if !negated && self.gamemodes.iter().any(|filter| !filter.negated) {
+                    return inapplicable(reader);
+                }
+                let mode_start = reader.cursor;
+                let name = reader.read_unquoted_string();
+                let value = GameMode::from_name(name).ok_or_else(|| {
+                    let message = format!("Invalid or unknown game mode '{name}'");
+                    CommandError::at_cursor(message, reader, mode_start)
+                })?;
+                self.gamemodes.push(Filter { value, negated });
+            }
+            "team" => {
+                let (negated, repeated) = negated(reader, &self.teams);
+                if repeated {
+                    return inapplicable(reader);
+                }
+                let value = reader.read_unquoted_string().to_string();
+                self.teams.push(Filter { value, negated });
+            }
+            "type" if !self.kind.players_only() => {
+                let negated = reader.eat('!');
+                reader.skip_whitespace();
+                // entities have one type, but may be in any number of tags
+                let limited = self
+                    .types
+                    .iter()
+                    .any(|filter| !filter.negated && !filter.value.starts_with('#'));
+                if !negated && limited {
+                    return inapplicable(reader);
+                }
+                let value = match reader.eat('#') {
+                    true => format!("#{}", read_resource_location(reader)?),
+                    false => read_resource_location(reader)?,
+                };
+                self.types.push(Filter { value, negated });
+            }
+            "tag" => {
+                let negated = reader.eat('!');
+                reader.skip_whitespace();
+                let value = reader.read_unquoted_string().to_string();
+                self.tags.push(Filter { value, negated });
+            }
+            "scores" if self.scores.is_empty() => {
+                reader.expect('{')?;
+                reader.skip_whitespace();
+                while !reader.eat('}') {
+                    if !reader.can_read() {
+                        return Err(CommandError::at("Expected '}'", reader));
+                    }
+                    let objective = reader.read_unquoted_string().to_string();
+                    reader.skip_whitespace();
+                    reader.expect('=')?;
+                    reader.skip_whitespace();
+                    self.scores.push((objective, Range::parse(reader)?));
+                    reader.skip_whitespace();
+                    if reader.eat(',') {
+                        reader.skip_whitespace();
+                    } else if reader.peek() != Some('}') {
+                        return Err(CommandError::at("Expected '}'", reader));
+                    }
+                }
+            }
+            "nbt" => {
+                let negated = reader.eat('!');
+                reader.skip_whitespace();
+                let value = Nbt::parse_compound(reader)?;
+                self.nbt.push(Filter { value, negated });
+            }
+            "predicate" => {
+                let negated = reader.eat('!');
+                reader.skip_whitespace();
+                let value = read_resource_location(reader)?;
+                self.predicates.push(Filter { value, negated });
+            }
+            option if OPTIONS.contains(&option) => return inapplicable(reader),
+            option => {
+                reader.cursor = start;
+                let message = format!("Unknown option '{option}'");
+                return Err(CommandError::at(message, reader));
+            }
+        }
+        Ok(())
+    }
+
+    /// The most entities the selector can choose.
+    pub fn max_results(&self) -> usize {
+        match self.kind {
+            SelectorKind::Sender => 1,
+            SelectorKind::NearestPlayer
+            | SelectorKind::RandomPlayer
+            | SelectorKind::NearestEntity => self.limit.unwrap_or(1),
+            SelectorKind::AllPlayers | SelectorKind::AllEntities => {
+                self.limit.unwrap_or(usize::MAX)
+            }
+        }
+    }
+
+    /// Whether the selector can choose entities that are not players.
+    pub fn includes_entities(&self) -> bool {
+        let players = self
+            .types
+            .iter()
+            .any(|filter| !filter.negated && filter.value == PLAYER_TYPE);
+        !self.kind.players_only() && !players
+    }
+
+    /// Find the entities the selector chooses, sorted and limited.
+    pub fn select(
+        &self,
+        world: &mut World,
+        sender: &CommandSender,
+    ) -> Result<Vec<Entity>, CommandError> {
+        let predicates = world.get_resource::<SelectorPredicates>().cloned();
+        let predicates = self
+            .predicates
+            .iter()
+            .map(|filter| {
+                let predicate = predicates
+                    .as_ref()
+                    .and_then(|predicates| predicates.0.get(&filter.value).cloned());
+                predicate
+                    .map(|predicate| (filter.negated, predicate))
+                    .ok_or_else(|| {
+                        CommandError::new(format!("Unknown predicate: {}", filter.value))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // search from the sender's position, unless the selector gives one
+        let sender_position = sender.position(world);
+        let origin = Position::new(
+            self.origin[0].unwrap_or(sender_position.x),
+            self.origin[1].unwrap_or(sender_position.y),
+            self.origin[2].unwrap_or(sender_position.z),
+        );
+
+        let mut query = world.query::<Candidate>();
+        let no_tags = EntityTypeTags::default();
+        let type_tags = world.get_resource::<EntityTypeTags>().unwrap_or(&no_tags);
+        let mut found: Vec<(Entity, f64)> = query
+            .iter(world)
+            .filter(|candidate| match self.kind {
+                SelectorKind::Sender => sender.entity() == Some(candidate.entity),
+                _ => true,
+            })
+            .filter(|candidate| self.matches(candidate, &origin, type_tags))
+            .map(|candidate| {
+                let position = candidate.position.copied().unwrap_or_default();
+                (candidate.entity, position.distance_squared(&origin))
+            })
+            .collect();
+        found.retain(|(entity, _)| {
+            predicates
+                .iter()
+                .all(|(negated, predicate)| predicate(world, *entity) != *negated)
+        });
+
+        let sort = self.sort.unwrap_or(match self.kind {
+            SelectorKind::NearestPlayer | SelectorKind::NearestEntity => Sort::Nearest,
+            SelectorKind::RandomPlayer => Sort::Random,
+            _ => Sort::Arbitrary,
+        });
+        match sort {
+            Sort::Nearest => found.sort_by(|(_, a), (_, b)| a.total_cmp(b)),
+            Sort::Furthest => found.sort_by(|(_, a), (_, b)| b.total_cmp(a)),
+            Sort::Random => found.shuffle(&mut rand::rng()),
+            Sort::Arbitrary => {}
+        }
+        found.truncate(self.max_results());
+        Ok(found.into_iter().map(|(entity, _)| entity).collect())
+    }
+
+    /// Whether an entity passes every filter of the selector, other than predicates.
+    fn matches(
+        &self,
+        candidate: &CandidateItem,
+        origin: &Position,
+        type_tags: &EntityTypeTags,
+    ) -> bool {
+        let player = candidate.is_player();
+        let ty = match player {
+            true => PLAYER_TYPE,
+            false => match candidate.ty {
+                Some(ty) => ty.as_str(),
+                // connections that have not logged in yet are not entities
+                None => return false,
+            },
+        };
+        if self.kind.players_only() && !player {
+            return false;
+        }
+        if !self
+            .types
+            .iter()
+            .all(|filter| match filter.value.strip_prefix('#') {
+                Some(tag) => filter.keeps(type_tags.contains(tag, ty)),
+                None => filter.keeps(filter.value == ty),
+            })
+        {
+            return false;
+        }
+
+        let name = candidate.name();
+        if !self
+            .names
+            .iter()
+            .all(|filter| filter.keeps(name == Some(filter.value.as_str())))
+        {
+            return false;
+        }
+
+        let position = candidate.position.copied().unwrap_or_default();
+        if let Some(distance) = &self.distance
+            && !distance.contains(position.distance_squared(origin).sqrt())
+        {
+            return false;
+        }
+        if self.volume.iter().any(Option::is_some) {
+            let [x, y, z] = [
+                (position.x, origin.x),
+                (position.y, origin.y),
+                (position.z, origin.z),
+            ];
+            // the box covers whole blocks, so it always includes the block at the origin
+            let inside = [x, y, z]
+                .into_iter()
+                .zip(self.volume)
+                .all(|((at, from), size)| {
+                    let size = size.unwrap_or_default();
+                    let (min, max) = (from + size.min(0.0), from + size.max(0.0) + 1.0);
+                    at >= min && at <= max
+                });
+            if !inside {
+                return false;
+            }
+        }
+
+        let rotation = candidate.rotation.copied().unwrap_or_default();
+        if let Some(range) = &self.x_rotation
+            && !range.contains_angle(rotation.pitch)
+        {
+            return false;
+        }
+        if let Some(range) = &self.y_rotation
+            && !range.contains_angle(rotation.yaw)
+        {
+            return false;
+        }
+
+        // only players have game modes
+        let gamemode = player.then(|| candidate.gamemode.copied().unwrap_or_default());
+        if !self.gamemodes.is_empty() && gamemode.is_none() {
+            return false;
+        }
+        if !self
+            .gamemodes
+            .iter()
+            .all(|filter| filter.keeps(gamemode == Some(filter.value)))
+        {
+            return false;
+        }
+
+        let team = candidate.team.map(|team| team.as_str());
+        let teams_match = self.teams.iter().all(|filter| match filter.value.as_str() {
+            "" => filter.keeps(team.is_none()),
+            value => filter.keeps(team == Some(value)),
+        });
+        let tags = candidate.tags.map(|tags| &tags.0);
+        let tags_match = self.tags.iter().all(|filter| match filter.value.as_str() {
+            "" => filter.keeps(tags.is_none_or(|tags| tags.is_empty())),
+            value => filter.keeps(tags.is_some_and(|tags| tags.contains(value))),
+        });
+        let scores_match = self.scores.iter().all(|(objective, range)| {
+            let score = candidate.scores.and_then(|scores| scores.get(objective));
+            score.is_some_and(|score| range.contains(*score))
+        });
+        let nbt_match = self.nbt.is_empty() || {
+            let data = candidate.nbt();
+            self.nbt
+                .iter()
+                .all(|filter| filter.keeps(data.contains(&filter.value)))
+        };
+        teams_match && tags_match && scores_match && nbt_match
+    }
+}
+
+/// The entity types in each entity type tag, by tag ID, which selectors can filter by, e.g.
+/// `@e[type=#minecraft:skeletons]`. Like vanilla, a tag that has not been added has no types.
+#[derive(Resource, Clone, Default)]
+pub struct EntityTypeTags(HashMap<String, HashSet<String>>);
+
+impl EntityTypeTags {
+    /// Add entity types to a tag, e.g. `minecraft:skeletons`.
+    pub fn add(
+        &mut self,
+        tag: impl Into<String>,
+        types: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        let tag = self.0.entry(tag.into()).or_default();
+        tag.extend(types.into_iter().map(Into::into));
+    }
+
+    /// Whether a tag has an entity type.
+    pub fn contains(&self, tag: &str, ty: &str) -> bool {
+        self.0.get(tag).is_some_and(|types| types.contains(ty))
+    }
+}
+
+/// Whether an entity passes a predicate.
+pub type Predicate = Arc<dyn Fn(&World, Entity) -> bool + Send + Sync>;
+
+/// The predicates that selectors can filter entities with, by ID, e.g.
+/// `@e[predicate=example:on_fire]`.
+#[derive(Resource, Clone, Default)]
+pub struct SelectorPredicates(HashMap<String, Predicate>);
+
+impl SelectorPredicates {
+    /// Add a predicate, replacing any existing predicate with the same ID.
+    pub fn add(
+        &mut self,
+        id: impl Into<String>,
+        predicate: impl Fn(&World, Entity) -> bool + Send + Sync + 'static,
+    ) {
+        self.0.insert(id.into(), Arc::new(predicate));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::ProtocolState;
+    use beacon_net::{
+        entity::{EntityType, Position, Tags},
+        player::{GameMode, PlayerIdentity},
+    };
+    use bevy_ecs::world::World;
+
+    use super::{EntityArgument, EntitySelector, EntityTypeTags, Range, SelectorKind, Sort};
+    use crate::command::{CommandSender, StringReader};
+
+    fn parse(input: &str) -> Result<EntitySelector, (String, Option<usize>)> {
+        EntitySelector::parse(&mut StringReader::new(input))
+            .map_err(|err| (err.message.to_string(), err.cursor()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let selector =
+            parse("@e[type=!pig, distance=..5.5,scores={kills=1..},sort=furthest]").unwrap();
+        assert_eq!(selector.kind, SelectorKind::AllEntities);
+        assert_eq!(selector.types[0].value, "minecraft:pig");
+        assert!(selector.types[0].negated);
+        let range = Range {
+            min: None,
+            max: Some(5.5),
+        };
+        assert_eq!(selector.distance, Some(range));
+        assert_eq!(selector.scores[0].0, "kills");
+        assert_eq!(selector.sort, Some(Sort::Furthest));
+
+        let err = |message: &str, cursor| Err((message.to_string(), Some(cursor)));
+        assert_eq!(parse("@x"), err("Unknown selector type '@x'", 0));
+        assert_eq!(
+            parse("@a[type=pig]"),
+            err("Option 'type' isn't applicable here", 3)
+        );
+        assert_eq!(parse("@e[limit=0]"), err("Limit must be at least 1", 9));
+        assert_eq!(
+            parse("@e[distance=5..1]"),
+            err("Min cannot be bigger than max", 12)
+        );
+        assert_eq!(
+            parse("@e[name=a,name=b]"),
+            err("Option 'name' isn't applicable here", 10)
+        );
+        assert_eq!(parse("@e[colour=red]"), err("Unknown option 'colour'", 3));
+        assert_eq!(parse("@e[tag=a"), err("Expected end of options", 8));
+
+        let single = EntityArgument::parse(&mut StringReader::new("@e"), true, false);
+        assert_eq!(
+            single.unwrap_err().message.to_string(),
+            "Only one entity is allowed, but the provided selector allows more than one"
+        );
+        let players = EntityArgument::parse(&mut StringReader::new("@e[type=player]"), false, true);
+        assert!(players.is_ok());
+    }
+
+    #[test]
+    fn test_select() {
+        let mut world = World::new();
+        let mut tags = EntityTypeTags::default();
+        tags.add("minecraft:pigs", ["minecraft:pig"]);
+        world.insert_resource(tags);
+        let mut player = |name: &str, x, mode| {
+            let identity = PlayerIdentity {
+                name: name.to_string(),
+                uuid: 0,
+                properties: Vec::new(),
+            };
+            let position = Position::new(x, 0.0, 0.0);
+            world
+                .spawn((identity, ProtocolState::Play, position, mode))
+                .id()
+        };
+        let near = player("near", 1.0, GameMode::Creative);
+        let far = player("far", 10.0, GameMode::Survival);
+        let pig = world
+            .spawn((
+                EntityType("minecraft:pig".into()),
+                Position::new(5.0, 0.0, 0.0),
+                Tags(["fed".to_string()].into()),
+            ))
+            .id();
+
+        let mut select = |input: &str| {
+            let selector = parse(input).unwrap();
+            selector
+                .select(&mut world, &CommandSender::Console)
+                .unwrap()
+        };
+        assert_eq!(select("@p"), [near]);
+        assert_eq!(select("@a[sort=furthest]"), [far, near]);
+        assert_eq!(select("@e[sort=nearest,limit=2]"), [near, pig]);
+        assert_eq!(select("@n[type=!player]"), [pig]);
+        assert_eq!(select("@e[tag=fed]"), [pig]);
+        assert_eq!(select("@a[gamemode=!creative]"), [far]);
+        assert_eq!(select("@e[x=10,distance=..1]"), [far]);
+        assert_eq!(select("@e[x=4,dx=6,sort=nearest]"), [pig, far]);
+        assert_eq!(select("@s"), []);
+
+        assert_eq!(
+            select(r#"@e[nbt={Tags:["fed"],Pos:[5.0d,0.0d,0.0d]}]"#),
+            [pig]
+        );
+        assert_eq!(select("@a[nbt=!{playerGameType:1}]"), [far]);
+        assert_eq!(select("@e[type=#minecraft:cows]"), []);
+        assert_eq!(select("@e[type=#pigs,type=!#minecraft:cows]"), [pig]);
+    }
+}
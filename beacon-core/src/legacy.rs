@@ -1,35 +1,445 @@
+//! The server list ping and login used by clients before 1.7.
+//!
+//! See: <https://minecraft.wiki/w/Java_Edition_protocol/Server_List_Ping#Legacy_Server_List_Ping>
+
+use std::time::Duration;
+
+use beacon_config::Config;
 use beacon_data::{LATEST_SUPPORTED_VERSION, PROTOCOL_VERSION};
+use beacon_net::player::OnlinePlayers;
+use bevy_ecs::{prelude::*, system::SystemState};
 use bytes::{BufMut, BytesMut};
-use tokio::{io::AsyncWriteExt, net::tcp::OwnedWriteHalf};
-
-pub async fn handle(
-    v2: bool,
-    mut writer: OwnedWriteHalf,
-    motd: &str,
-    online: u32,
-    max_players: u32,
-) {
-    let payload = if v2 {
-        // 1.4-1.6
-        // todo: validate input
-        format!(
-            "§1\0{}\0{}\0{}\0{}\0{}",
-            PROTOCOL_VERSION, LATEST_SUPPORTED_VERSION, motd, online, max_players
-        )
-    } else {
-        // pre-1.4
-        format!("{}§{}§{}", motd, online, max_players)
-    }
-    .encode_utf16()
-    .flat_map(|c| c.to_be_bytes())
-    .collect::<Vec<_>>();
-
-    // respond
-    let len = payload.len() as u16 / 2;
-    let mut buf = BytesMut::with_capacity(3 + payload.len());
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    time::timeout,
+};
+
+use crate::{WorldQueue, peek::PeekReader};
+
+/// The channel 1.6 clients send the hostname and protocol version they are pinging on.
+const PING_HOST_CHANNEL: &str = "MC|PingHost";
+
+/// The longest string old clients accept in a kick packet, in UTF-16 code units.
+const MAX_STRING_LENGTH: usize = 256;
+
+/// The longest hostname accepted in a 1.6 ping, like the modern handshake.
+const MAX_HOSTNAME_LENGTH: usize = 255;
+
+/// The longest a legacy request can be: a 1.6 ping with the longest hostname.
+const MAX_REQUEST_LENGTH: usize = 3 + 2 + 2 * 11 + 2 + 1 + 2 + 2 * MAX_HOSTNAME_LENGTH + 4;
+
+/// How long to wait for more of a request. Old clients send their whole request at once, so this
+/// is only waited out by beta 1.8 to 1.5 pings, whose requests are a prefix of newer ones.
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// The characters that can follow `§` in formatting codes old clients understand: colours, then
+/// obfuscated, bold, strikethrough, underlined, italic and reset.
+const FORMATTING_CODES: &str = "0123456789abcdefklmnor";
+
+/// A request from a client older than 1.7.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Request {
+    /// Beta 1.8 to 1.3 send just `0xFE`.
+    Beta,
+    /// 1.4 and 1.5 send `0xFE 0x01`.
+    V1_4,
+    /// 1.6 sends `0xFE 0x01 0xFA`, followed by an `MC|PingHost` plugin message.
+    V1_6(PingHost),
+    /// Clients before 1.7 start logging in with `0x02`.
+    Login,
+}
+
+/// The contents of the `MC|PingHost` plugin message sent by 1.6 clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PingHost {
+    /// The client's protocol version.
+    pub protocol_version: u8,
+    /// The hostname the client is pinging.
+    pub hostname: String,
+    /// The port the client is pinging.
+    pub port: u16,
+}
+
+/// The result of parsing the start of a connection.
+#[derive(Debug, PartialEq, Eq)]
+enum Parsed {
+    /// A complete legacy request.
+    Request(Request),
+    /// More bytes are needed to tell.
+    Incomplete,
+    /// Not a legacy request, so the connection uses the modern protocol.
+    Modern,
+}
+
+impl Request {
+    /// The hostname the client connected with, only sent by 1.6 clients.
+    fn hostname(&self) -> Option<&str> {
+        match self {
+            Self::V1_6(host) => Some(host.hostname.trim_end_matches('.')),
+            _ => None,
+        }
+    }
+
+    /// Parse a legacy request from the bytes received so far. If `complete`, no more are coming,
+    /// so a prefix of a newer request is taken to come from an older client.
+    fn parse(buf: &[u8], complete: bool) -> Parsed {
+        match buf {
+            // a modern handshake is always longer than two bytes, so it never starts with 0x02
+            [0x02, ..] => Parsed::Request(Self::Login),
+            [0xFE] if complete => Parsed::Request(Self::Beta),
+            [0xFE, 0x01] if complete => Parsed::Request(Self::V1_4),
+            [] | [0xFE] | [0xFE, 0x01] => Parsed::Incomplete,
+            [0xFE, 0x01, 0xFA, data @ ..] => match PingHost::parse(data) {
+                Ok(host) => Parsed::Request(Self::V1_6(host)),
+                // the response is the same, just without the hostname
+                Err(ParseError::Incomplete) if complete => Parsed::Request(Self::V1_4),
+                Err(ParseError::Incomplete) => Parsed::Incomplete,
+                Err(ParseError::Invalid) => Parsed::Modern,
+            },
+            // a modern packet whose length starts with 0xFE
+            _ => Parsed::Modern,
+        }
+    }
+
+    /// The response to a ping, listing the server's status.
+    fn status(&self, motd: &str, online: u32, max_players: u32) -> Vec<u8> {
+        let message = match self {
+            Self::Beta => {
+                // `§` separates the fields, so no formatting codes can be kept
+                let suffix = format!("§{online}§{max_players}");
+                let motd = sanitize(
+                    motd,
+                    false,
+                    MAX_STRING_LENGTH - suffix.encode_utf16().count(),
+                );
+                format!("{motd}{suffix}")
+            }
+            _ => {
+                // `\0` separates the fields, and formatting codes are supported since 1.4
+                let prefix = format!("§1\0{PROTOCOL_VERSION}\0{LATEST_SUPPORTED_VERSION}\0");
+                let suffix = format!("\0{online}\0{max_players}");
+                let max_len = MAX_STRING_LENGTH
+                    - prefix.encode_utf16().count()
+                    - suffix.encode_utf16().count();
+                let motd = sanitize(motd, true, max_len);
+                format!("{prefix}{motd}{suffix}")
+            }
+        };
+        kick(&message)
+    }
+}
+
+/// Error that can occur while parsing a 1.6 ping.
+#[derive(Debug)]
+enum ParseError {
+    /// More bytes are needed.
+    Incomplete,
+    /// The bytes are not a valid `MC|PingHost` message.
+    Invalid,
+}
+
+impl PingHost {
+    /// Parse the `MC|PingHost` plugin message following `0xFE 0x01 0xFA`.
+    fn parse(mut buf: &[u8]) -> Result<Self, ParseError> {
+        if read_string(&mut buf, PING_HOST_CHANNEL.len())? != PING_HOST_CHANNEL {
+            return Err(ParseError::Invalid);
+        }
+        let len = u16::from_be_bytes(read_array(&mut buf)?);
+        let [protocol_version] = read_array(&mut buf)?;
+        let hostname = read_string(&mut buf, MAX_HOSTNAME_LENGTH)?;
+        let port = i32::from_be_bytes(read_array(&mut buf)?);
+
+        // the length covers the protocol version, hostname and port
+        if len as usize != 1 + 2 + 2 * hostname.encode_utf16().count() + 4 {
+            return Err(ParseError::Invalid);
+        }
+        Ok(Self {
+            protocol_version,
+            hostname,
+            port: port.try_into().map_err(|_| ParseError::Invalid)?,
+        })
+    }
+}
+
+/// Read the next `N` bytes.
+fn read_array<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], ParseError> {
+    let (bytes, rest) = buf.split_first_chunk().ok_or(ParseError::Incomplete)?;
+    *buf = rest;
+    Ok(*bytes)
+}
+
+/// Read a string, prefixed with its length in UTF-16 code units, of at most `max_len`.
+fn read_string(buf: &mut &[u8], max_len: usize) -> Result<String, ParseError> {
+    let len = u16::from_be_bytes(read_array(buf)?) as usize;
+    if len > max_len {
+        return Err(ParseError::Invalid);
+    }
+    if buf.len() < 2 * len {
+        return Err(ParseError::Incomplete);
+    }
+
+    let (bytes, rest) = buf.split_at(2 * len);
+    *buf = rest;
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<_, _>>()
+        .map_err(|_| ParseError::Invalid)
+}
+
+/// Remove anything from the MOTD that old clients cannot display, and cap it at `max_len` UTF-16
+/// code units. Formatting codes are dropped, or only unsupported ones if `keep_codes`.
+fn sanitize(motd: &str, keep_codes: bool, max_len: usize) -> String {
+    let mut out = String::with_capacity(motd.len());
+    let mut chars = motd.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '§' => match chars.next() {
+                // hex colours, `§x§r§r§g§g§b§b`, are dropped along with their digits
+                Some('x' | 'X') if is_hex_colour(chars.as_str()) => {
+                    chars.nth(11);
+                }
+                Some(code)
+                    if keep_codes && FORMATTING_CODES.contains(code.to_ascii_lowercase()) =>
+                {
+                    out.push('§');
+                    out.push(code);
+                }
+                _ => {}
+            },
+            // the server list only has room for one line
+            '\n' => out.push(' '),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+
+    let mut len = 0;
+    let end = out.char_indices().find_map(|(i, c)| {
+        len += c.len_utf16();
+        (len > max_len).then_some(i)
+    });
+    if let Some(end) = end {
+        out.truncate(end);
+        // do not leave half a formatting code
+        if out.ends_with('§') {
+            out.pop();
+        }
+    }
+    out
+}
+
+/// Whether a string starts with the six `§`-prefixed digits of a hex colour.
+fn is_hex_colour(s: &str) -> bool {
+    let mut chars = s.chars();
+    (0..6).all(|_| chars.next() == Some('§') && chars.next().is_some_and(|c| c.is_ascii_hexdigit()))
+}
+
+/// Encode a kick packet, which old clients are sent in response to both pings and logins.
+fn kick(message: &str) -> Vec<u8> {
+    let units: Vec<u16> = message.encode_utf16().take(MAX_STRING_LENGTH).collect();
+
+    let mut buf = BytesMut::with_capacity(3 + 2 * units.len());
     buf.put_u8(0xFF); // kick packet
-    buf.put_u16(len); // length
-    buf.extend_from_slice(&payload);
+    buf.put_u16(units.len() as u16); // length
+    for unit in units {
+        buf.put_u16(unit);
+    }
+    buf.to_vec()
+}
+
+/// Read a legacy request if the connection starts with one, without consuming anything if it uses
+/// the modern protocol.
+pub(crate) async fn detect<R: AsyncRead + Unpin>(reader: &mut PeekReader<R>) -> Option<Request> {
+    let first = reader.peek_exact(1).await.ok()?[0];
+    if !matches!(first, 0xFE | 0x02) {
+        return None;
+    }
+    read_request(reader).await
+}
+
+/// Read a legacy request from the start of a connection, without consuming anything if it turns out
+/// to use the modern protocol.
+async fn read_request<R: AsyncRead + Unpin>(reader: &mut PeekReader<R>) -> Option<Request> {
+    let mut len = 0;
+    loop {
+        // peek a byte at a time, so that the request is never read past
+        let complete = len == MAX_REQUEST_LENGTH
+            || !matches!(
+                timeout(READ_TIMEOUT, reader.peek_exact(len + 1)).await,
+                Ok(Ok(_))
+            );
+        if !complete {
+            len += 1;
+        }
+
+        match Request::parse(&reader.peeked()[..len], complete) {
+            Parsed::Request(request) => return Some(request),
+            Parsed::Modern => return None,
+            Parsed::Incomplete if complete => return None,
+            Parsed::Incomplete => {}
+        }
+    }
+}
+
+/// Respond to a legacy request. Logins are always refused, and pings are only answered if the
+/// status is enabled.
+pub(crate) async fn respond<W: AsyncWrite + Unpin>(
+    request: Request,
+    mut writer: W,
+    queue: &WorldQueue,
+) {
+    let response = match request {
+        Request::Login => kick(&format!(
+            "Outdated client! Please use {LATEST_SUPPORTED_VERSION}"
+        )),
+        _ => match queue
+            .run(move |world| status(request, world))
+            .await
+            .flatten()
+        {
+            Some(response) => response,
+            None => return,
+        },
+    };
+
+    let _ = writer.write_all(&response).await;
+}
+
+/// The response to a ping, or `None` if the status is disabled for the host being pinged.
+fn status(request: Request, world: &mut World) -> Option<Vec<u8>> {
+    if let Request::V1_6(host) = &request {
+        debug!(
+            protocol_version = host.protocol_version,
+            hostname = %host.hostname,
+            port = host.port,
+            "received legacy ping"
+        );
+    }
+
+    let config = world.resource::<Config>();
+    let host = config.host(request.hostname()).filter(|host| host.status)?;
+    let motd = host.motd.to_string();
+    let max_players = host.max_players;
+    let online = SystemState::<OnlinePlayers>::new(world).get(world).count();
+    Some(request.status(&motd, online, max_players))
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_data::{LATEST_SUPPORTED_VERSION, PROTOCOL_VERSION};
+
+    use super::{Parsed, PingHost, Request, kick, read_request, sanitize};
+    use crate::peek::PeekReader;
 
-    let _ = writer.write_all(&buf).await;
+    fn utf16(s: &str) -> Vec<u8> {
+        let mut buf = (s.encode_utf16().count() as u16).to_be_bytes().to_vec();
+        buf.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+        buf
+    }
+
+    fn ping_host(hostname: &str, port: i32) -> Vec<u8> {
+        let mut buf = vec![0xFE, 0x01, 0xFA];
+        buf.extend(utf16("MC|PingHost"));
+        buf.extend((7 + 2 * hostname.len() as u16).to_be_bytes());
+        buf.push(78);
+        buf.extend(utf16(hostname));
+        buf.extend(port.to_be_bytes());
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let read = |buf: Vec<u8>| async move {
+            let mut reader = PeekReader::new(&buf[..]);
+            read_request(&mut reader).await
+        };
+        assert_eq!(read(vec![0xFE]).await, Some(Request::Beta));
+        assert_eq!(read(vec![0xFE, 0x01]).await, Some(Request::V1_4));
+        assert_eq!(
+            read(ping_host("play.example.com", 25565)).await,
+            Some(Request::V1_6(PingHost {
+                protocol_version: 78,
+                hostname: "play.example.com".into(),
+                port: 25565,
+            }))
+        );
+        assert_eq!(
+            read(vec![0x02, 0x4E, 0x00, 0x05]).await,
+            Some(Request::Login)
+        );
+
+        // a modern handshake 254 bytes long
+        assert_eq!(read(vec![0xFE, 0x01, 0x00, 0xF6, 0x05]).await, None);
+        assert_eq!(read(vec![0xFE, 0x02, 0x00]).await, None);
+        assert_eq!(read(vec![0x10, 0x00]).await, None);
+    }
+
+    #[test]
+    fn test_parse_ping_host() {
+        let buf = ping_host("localhost", 25565);
+        for len in 3..buf.len() {
+            assert_eq!(Request::parse(&buf[..len], false), Parsed::Incomplete);
+            assert_eq!(
+                Request::parse(&buf[..len], true),
+                Parsed::Request(Request::V1_4)
+            );
+        }
+
+        let mut wrong_channel = buf.clone();
+        wrong_channel[6] = b'X';
+        assert_eq!(Request::parse(&wrong_channel, false), Parsed::Modern);
+
+        let mut wrong_length = buf.clone();
+        wrong_length[28] += 1;
+        assert_eq!(Request::parse(&wrong_length, false), Parsed::Modern);
+
+        let bad_port = ping_host("localhost", 65536);
+        assert_eq!(Request::parse(&bad_port, false), Parsed::Modern);
+    }
+
+    #[test]
+    fn test_status() {
+        let response = Request::Beta.status("§aA §lBeacon§r Server", 3, 20);
+        assert_eq!(response[..3], [0xFF, 0x00, 0x14]);
+        assert_eq!(response[3..], utf16("A Beacon Server§3§20")[2..]);
+
+        let response = Request::V1_4.status("§aA\nBeacon\0§x§1§2§3§4§5§6Server§", 3, 20);
+        let (protocol, version) = (
+            PROTOCOL_VERSION.to_string(),
+            LATEST_SUPPORTED_VERSION.to_string(),
+        );
+        let message = ["§1", &protocol, &version, "§aA BeaconServer", "3", "20"];
+        assert_eq!(response, kick(&message.join("\0")));
+    }
+
+    #[test]
+    fn test_length() {
+        let motd = "§a".repeat(200);
+        let response = Request::V1_4.status(&motd, 0, 20);
+        assert_eq!(u16::from_be_bytes([response[1], response[2]]) as usize, 256);
+        assert_eq!(response.len(), 3 + 2 * 256);
+
+        // formatting codes are not split
+        let motd = sanitize(&"§a".repeat(200), true, 255);
+        assert_eq!(motd.chars().count(), 254);
+
+        // neither are surrogate pairs
+        let motd = sanitize(&"🦀".repeat(200), false, 255);
+        assert_eq!(motd.encode_utf16().count(), 254);
+
+        assert_eq!(kick(&"a".repeat(1000)).len(), 3 + 2 * 256);
+    }
+
+    #[test]
+    fn test_login() {
+        assert_eq!(
+            Request::parse(&[0x02, 0x00, 0x05], false),
+            Parsed::Request(Request::Login)
+        );
+        let message = kick("Outdated client!");
+        assert_eq!(message[..3], [0xFF, 0x00, 0x10]);
+        assert_eq!(message[3..5], [0x00, b'O']);
+    }
 }
@@ -1,24 +1,73 @@
 //! # beacon-core
 
-use std::{net::SocketAddr, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use beacon_codec::{ProtocolState, decode::Decode, encode::Encode};
 use beacon_config::Config;
-use beacon_net::{conn::Connection, packet::RawPacket};
-use bevy_ecs::prelude::*;
+use beacon_net::text::Text;
+use bevy_ecs::{prelude::*, schedule::InternedSystemSet};
 use miette::{IntoDiagnostic, Result};
-use peekable::tokio::AsyncPeekable;
-use tokio::net::{TcpListener, TcpStream};
 use tokio_util::sync::CancellationToken;
 
+pub use crate::access::{
+    AccessList, Ban, BannedIps, BannedPlayers, GameProfile, IpBan, ListEntry, ListError, Operator,
+    Operators, PlayerBan, Whitelist, find_profile,
+};
+pub use crate::builder::BeaconServerBuilder;
+pub use crate::command::{
+    BroadcastToOps, CommandSender, CompleteCommand, RunCommand, complete_command, run_command,
+};
+pub use crate::limit::RateLimitMetrics;
+use crate::listener::Listener;
+use crate::management::ManagementServer;
+pub use crate::management::{ManagementMethods, ManagementNotifier, Params, RpcError};
+pub use crate::player_list::{PlayerList, PlayerLogin};
+pub use crate::plugin::{AccessPlugin, App, BeaconPlugin, ConfigPlugin, NetworkPlugin};
+use crate::query::QueryServer;
+pub use crate::queue::WorldQueue;
+use crate::rcon::RconServer;
+pub use crate::schedule::TickSet;
+pub use crate::tick::{DEFAULT_TICK_RATE, MAX_TICK_RATE, MIN_TICK_RATE, TickManager, TickMetrics};
+use crate::tick::{TickClock, TickStage, Watchdog};
+pub use crate::world::{DAY_LENGTH, Weather, WeatherKind, WorldTime};
+
+#[macro_use]
+extern crate derive_more;
 #[macro_use]
 extern crate tracing;
 
+mod access;
+mod builder;
+/// Commands, and the dispatcher that parses and runs them.
+pub mod command;
 mod legacy;
+mod limit;
+mod listener;
+mod management;
+mod peek;
+mod player_list;
+mod plugin;
+mod proxy;
+mod query;
+mod queue;
+mod rcon;
+mod schedule;
+mod tick;
+mod websocket;
+mod world;
+
+/// How long to wait for connections to be sent their last packets when the server stops.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 /// The Minecraft server you'll love.
 pub struct BeaconServer {
-    listener: TcpListener,
+    management: Option<ManagementServer>,
+    query: Option<QueryServer>,
+    rcon: Option<RconServer>,
     state: Arc<ServerState>,
 
     world: World,
@@ -26,31 +75,40 @@ pub struct BeaconServer {
 }
 
 /// Shared server state.
-#[derive(Default)]
 pub struct ServerState {
     cancel_token: CancellationToken,
 }
 
+/// A handle to the running server, which systems can use to stop it.
+#[derive(Resource, Clone, Default)]
+pub struct ServerHandle {
+    cancel_token: CancellationToken,
+}
+
+impl ServerHandle {
+    /// Stop the server at the end of the current tick.
+    pub fn shutdown(&self) {
+        self.cancel_token.cancel();
+    }
+}
+
 impl BeaconServer {
-    /// Create a new instance of beacon.
-    pub async fn new<P: AsRef<Path>>(config_path: P) -> Result<Self> {
-        // start ecs
-        let (mut world, mut schedule) = (World::new(), Schedule::default());
-        let config = beacon_config::ecs(&mut world, &mut schedule, config_path)?;
-        beacon_net::ecs(&mut schedule);
-
-        // bind the server
-        let addr: SocketAddr = (config.server.ip, config.server.port).into();
-        let listener = TcpListener::bind(addr).await.into_diagnostic()?;
-        info!("server listening on {}", addr);
-
-        Ok(Self {
-            listener,
-            state: Arc::new(ServerState::default()),
-
-            world,
-            schedule,
-        })
+    /// Create a new instance of beacon with the default plugins.
+    pub async fn new<P: Into<PathBuf>>(config_path: P) -> Result<Self> {
+        Self::builder()
+            .add_default_plugins(config_path)
+            .build()
+            .await
+    }
+
+    /// Create a builder to customise which plugins the server is made up of.
+    pub fn builder() -> BeaconServerBuilder {
+        BeaconServerBuilder::default()
+    }
+
+    /// A queue of tasks to run in the world, for code outside the ECS such as the console.
+    pub fn queue(&self) -> WorldQueue {
+        self.world.resource::<WorldQueue>().clone()
     }
 
     /// Start the server.
@@ -65,69 +123,95 @@ impl BeaconServer {
             }
         });
 
+        // answer queries, remote consoles and management clients alongside the game
+        if let Some(management) = self.management.take() {
+            tokio::spawn(management.run(self.state.cancel_token.clone()));
+        }
+        if let Some(query) = self.query.take() {
+            tokio::spawn(query.run(self.state.cancel_token.clone()));
+        }
+        if let Some(rcon) = self.rcon.take() {
+            tokio::spawn(rcon.run(self.state.cancel_token.clone()));
+        }
+
+        // start the watchdog
+        self.schedule
+            .initialize(&mut self.world)
+            .into_diagnostic()?;
+        // like vanilla, a max-tick-time of 0 or less disables the watchdog
+        let max_tick_time = self.world.resource::<Config>().server.max_tick_time;
+        let watchdog = u64::try_from(max_tick_time)
+            .ok()
+            .filter(|ms| *ms > 0)
+            .map(|ms| {
+                Watchdog::spawn(
+                    Duration::from_millis(ms),
+                    self.world.resource::<TickStage>().clone(),
+                    self.stage_systems(),
+                    self.state.cancel_token.clone(),
+                )
+            });
+
+        let mut clock = TickClock::new();
         loop {
             tokio::select! {
-                Ok((sock, addr)) = self.listener.accept() => spawn_connection(sock, addr, &mut self.world).await,
                 _ = self.state.cancel_token.cancelled() => break,
-                _ = tokio::task::yield_now() => self.schedule.run(&mut self.world),
+                _ = clock.wait() => {
+                    self.tick(watchdog.as_ref());
+                    clock.advance(self.world.resource::<TickManager>());
+                },
             }
         }
 
         // shutdown + cleanup
         info!("shutting down...");
+        let reason = Text::translate("multiplayer.disconnect.server_shutdown", [] as [Text; 0]);
+        beacon_net::close_all(&mut self.world, reason);
+        if let Some(listener) = self.world.get_resource::<Listener>() {
+            listener.drain(SHUTDOWN_GRACE).await;
+        }
 
         Ok(())
     }
-}
-
-async fn spawn_connection(sock: TcpStream, addr: SocketAddr, world: &mut World) {
-    // split socket
-    let (reader, mut writer) = sock.into_split();
-    let mut reader = AsyncPeekable::new(reader);
-
-    // check for legacy server list ping
-    let mut buf = [0; 2];
-    let _ = reader.peek(&mut buf).await;
-
-    // todo: detect beta 1.8-1.4 joins
-    if buf[0] == 0xFE {
-        let config = world.resource::<Config>();
-        if !config.server.status {
-            return;
-        }
-
-        let v2 = buf[1] == 0x01; // 1.4-1.6
-        let motd = config.server.motd.clone();
-        let max_players = config.server.max_players;
-        // todo: query for real, online players
-        let online = world.query::<&ProtocolState>().iter(world).count() as u32;
-        legacy::handle(v2, writer, &motd, online, max_players).await;
 
-        return;
+    /// The names of the systems in each stage of the tick, for the watchdog.
+    fn stage_systems(&self) -> Vec<(InternedSystemSet, Vec<String>)> {
+        let names: HashMap<_, _> = self
+            .schedule
+            .systems()
+            .into_iter()
+            .flatten()
+            .map(|(key, system)| (key, system.name().to_string()))
+            .collect();
+        schedule::stages()
+            .into_iter()
+            .map(|stage| {
+                let systems = self.schedule.graph().systems_in_set(stage);
+                let systems = systems.into_iter().flatten();
+                (
+                    stage,
+                    systems.filter_map(|key| names.get(key).cloned()).collect(),
+                )
+            })
+            .collect()
     }
 
-    // spawn in the ecs
-    let (tx, rx, despawn) = Connection::spawn(world);
+    /// Run a single tick of the game loop.
+    fn tick(&mut self, watchdog: Option<&Watchdog>) {
+        let start = Instant::now();
+        let tick = self.world.resource::<TickMetrics>().tick() + 1;
 
-    // spawn a task to read packets from the socket and send them to the connection
-    tokio::spawn(async move {
-        debug!(addr = %addr, "new connection established");
-        loop {
-            tokio::select! {
-                Ok(packet) = rx.recv_async() => {
-                    let _ = packet.encode(&mut writer).await;
-                },
-                res = RawPacket::decode(&mut reader) => {
-                    let Ok(packet) = res else { break; };
-                    let _ = tx.send_async(packet).await;
-                },
-                _ = despawn.cancelled() => break,
-            }
+        if let Some(watchdog) = watchdog {
+            watchdog.begin(tick);
+        }
+        self.schedule.run(&mut self.world);
+        if let Some(watchdog) = watchdog {
+            watchdog.end();
         }
 
-        debug!(addr = %addr, "connection closed");
-        if !despawn.is_cancelled() {
-            despawn.cancel()
-        };
-    });
+        self.world
+            .resource_mut::<TickMetrics>()
+            .record(start, start.elapsed());
+        self.world.resource_mut::<TickManager>().finish_tick();
+    }
 }
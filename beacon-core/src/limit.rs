@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use beacon_config::NetworkConfig;
+use beacon_net::packet::RawPacket;
+use bevy_ecs::prelude::*;
+
+/// How many IP addresses to track before forgetting the ones that are no longer throttled.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Counts how often each rate limit has been exceeded.
+#[derive(Resource, Clone, Default)]
+pub struct RateLimitMetrics(Arc<Counters>);
+
+#[derive(Default)]
+struct Counters {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+    connections: AtomicU64,
+    queries: AtomicU64,
+    rcon_logins: AtomicU64,
+}
+
+impl RateLimitMetrics {
+    /// How many connections have been kicked for sending too many packets.
+    pub fn packet_limited(&self) -> u64 {
+        self.0.packets.load(Ordering::Relaxed)
+    }
+
+    /// How many connections have been kicked for sending too many bytes.
+    pub fn byte_limited(&self) -> u64 {
+        self.0.bytes.load(Ordering::Relaxed)
+    }
+
+    /// How many connection attempts have been refused.
+    pub fn connections_throttled(&self) -> u64 {
+        self.0.connections.load(Ordering::Relaxed)
+    }
+
+    /// How many queries have been ignored.
+    pub fn queries_throttled(&self) -> u64 {
+        self.0.queries.load(Ordering::Relaxed)
+    }
+
+    /// How many RCON logins have been refused.
+    pub fn rcon_logins_throttled(&self) -> u64 {
+        self.0.rcon_logins.load(Ordering::Relaxed)
+    }
+
+    fn count(&self, limit: Limit) {
+        let counter = match limit {
+            Limit::Packets => &self.0.packets,
+            Limit::Bytes => &self.0.bytes,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A rate limit that was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub(crate) enum Limit {
+    #[display("packets per second")]
+    Packets,
+    #[display("bytes per second")]
+    Bytes,
+}
+
+/// Token bucket rate limiter.
+///
+/// Tokens refill continuously at `rate` per second, up to `capacity`.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket that allows `amount` tokens to be taken every `per`.
+    fn new(amount: f64, per: Duration, now: Instant) -> Self {
+        Self {
+            rate: amount / per.as_secs_f64(),
+            capacity: amount,
+            tokens: amount,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+
+    /// Take `n` tokens, returning false if there are not enough.
+    ///
+    /// A full bucket can always be taken from, going into debt if needed, so that a single cost
+    /// larger than the capacity does not exceed the limit forever.
+    fn try_take(&mut self, n: f64, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= n || self.tokens >= self.capacity {
+            self.tokens -= n;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+/// Limits the packets and bytes per second sent by a single connection.
+pub(crate) struct ConnectionLimiter {
+    packets: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    metrics: RateLimitMetrics,
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: &NetworkConfig, metrics: RateLimitMetrics) -> Self {
+        let now = Instant::now();
+        let bucket = |rate: u32| {
+            (rate > 0).then(|| TokenBucket::new(rate as f64, Duration::from_secs(1), now))
+        };
+        Self {
+            packets: bucket(config.rate_limit),
+            bytes: bucket(config.byte_rate_limit),
+            metrics,
+        }
+    }
+
+    /// Check a packet received from the connection against the limits.
+    pub fn check(&mut self, packet: &RawPacket) -> Result<(), Limit> {
+        let now = Instant::now();
+        if let Some(packets) = &mut self.packets
+            && !packets.try_take(1.0, now)
+        {
+            self.metrics.count(Limit::Packets);
+            return Err(Limit::Packets);
+        }
+        if let Some(bytes) = &mut self.bytes
+            && !bytes.try_take(packet.size() as f64, now)
+        {
+            self.metrics.count(Limit::Bytes);
+            return Err(Limit::Bytes);
+        }
+        Ok(())
+    }
+}
+
+/// A token bucket for each IP address.
+#[derive(Default)]
+struct IpBuckets {
+    buckets: HashMap<IpAddr, TokenBucket>,
+}
+
+impl IpBuckets {
+    /// Take a token from the bucket for `ip`, which allows `amount` tokens every `per`.
+    fn try_take(&mut self, ip: IpAddr, amount: u32, per: Duration) -> bool {
+        let now = Instant::now();
+        if self.buckets.len() > PRUNE_THRESHOLD {
+            self.buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        self.buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::new(amount as f64, per, now))
+            .try_take(1.0, now)
+    }
+
+    /// Whether the bucket for `ip` has a token left, without taking it.
+    fn has_token(&mut self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.buckets.get_mut(&ip).is_none_or(|bucket| {
+            bucket.refill(now);
+            bucket.tokens >= 1.0
+        })
+    }
+}
+
+/// Limits the connection attempts per minute from each IP address.
+#[derive(Default)]
+pub(crate) struct ConnectionThrottle {
+    buckets: IpBuckets,
+}
+
+impl ConnectionThrottle {
+    /// Record a connection attempt, returning false if it should be refused.
+    pub fn allow(
+        &mut self,
+        ip: IpAddr,
+        config: &NetworkConfig,
+        metrics: &RateLimitMetrics,
+    ) -> bool {
+        let limit = config.connection_rate_limit;
+        if limit == 0 {
+            return true;
+        }
+
+        let allowed = self.buckets.try_take(ip, limit, Duration::from_secs(60));
+        if !allowed {
+            metrics.0.connections.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+}
+
+/// Limits the queries per second from each IP address.
+#[derive(Default)]
+pub(crate) struct QueryThrottle {
+    buckets: IpBuckets,
+}
+
+impl QueryThrottle {
+    /// Record a query, returning false if it should be ignored.
+    pub fn allow(&mut self, ip: IpAddr, limit: u32, metrics: &RateLimitMetrics) -> bool {
+        if limit == 0 {
+            return true;
+        }
+
+        let allowed = self.buckets.try_take(ip, limit, Duration::from_secs(1));
+        if !allowed {
+            metrics.0.queries.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+}
+
+/// Limits the failed RCON logins per minute from each IP address.
+#[derive(Default)]
+pub(crate) struct LoginThrottle {
+    buckets: IpBuckets,
+}
+
+impl LoginThrottle {
+    /// Check whether a login may be attempted, returning false if it should be refused.
+    pub fn allow(&mut self, ip: IpAddr, limit: u32, metrics: &RateLimitMetrics) -> bool {
+        let allowed = limit == 0 || self.buckets.has_token(ip);
+        if !allowed {
+            metrics.0.rcon_logins.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    /// Record a failed login.
+    pub fn fail(&mut self, ip: IpAddr, limit: u32) {
+        if limit > 0 {
+            self.buckets.try_take(ip, limit, Duration::from_secs(60));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::TokenBucket;
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, Duration::from_secs(1), start);
+
+        // burst up to the capacity
+        for _ in 0..10 {
+            assert!(bucket.try_take(1.0, start));
+        }
+        assert!(!bucket.try_take(1.0, start));
+
+        // refills over time
+        let later = start + Duration::from_millis(500);
+        for _ in 0..5 {
+            assert!(bucket.try_take(1.0, later));
+        }
+        assert!(!bucket.try_take(1.0, later));
+
+        // a full bucket allows a single cost larger than the capacity
+        let much_later = later + Duration::from_secs(10);
+        assert!(bucket.try_take(25.0, much_later));
+        assert!(!bucket.try_take(1.0, much_later));
+    }
+}
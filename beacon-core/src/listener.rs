@@ -0,0 +1,289 @@
+//! Accepting Minecraft connections, and the tasks that move their packets between the socket and
+//! the ECS.
+
+use std::{
+    io::ErrorKind,
+    net::SocketAddr,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use beacon_codec::decode::{Decode, DecodeError};
+use beacon_config::{Config, ConfigSet, NetworkConfig};
+use beacon_net::{
+    conn::{CloseReason, Connection, IncomingSender, OutgoingReceiver, QueueError},
+    packet::RawPacket,
+    writer::PacketWriter,
+};
+use bevy_ecs::prelude::*;
+use miette::{IntoDiagnostic, Result};
+use tokio::{
+    net::{
+        TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    time::timeout,
+};
+use tokio_util::task::TaskTracker;
+
+use crate::{
+    App, RateLimitMetrics, TickSet, WorldQueue, legacy,
+    limit::{ConnectionLimiter, ConnectionThrottle},
+    peek::PeekReader,
+    proxy,
+};
+
+/// How long a new connection has to send its PROXY protocol header, or the start of its first
+/// packet, before it is closed.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The server's TCP listener. Connections are accepted at the start of every tick.
+#[derive(Resource)]
+pub(crate) struct Listener {
+    listener: std::net::TcpListener,
+    throttle: Arc<Mutex<ConnectionThrottle>>,
+    /// The tasks of connections that have been spawned in the ECS, which are waited on when the
+    /// server stops.
+    tasks: TaskTracker,
+}
+
+impl Listener {
+    /// Bind the server's address, and accept connections from it every tick.
+    pub(crate) fn configure(app: &mut App) -> Result<()> {
+        let config = app.world.get_resource::<Config>().ok_or_else(|| {
+            miette::miette!(
+                help = "add the ConfigPlugin before the NetworkPlugin",
+                "no configuration has been loaded"
+            )
+        })?;
+        let addr: SocketAddr = (config.server.ip, config.server.port).into();
+        let listener = std::net::TcpListener::bind(addr).into_diagnostic()?;
+        listener.set_nonblocking(true).into_diagnostic()?;
+        info!("server listening on {}", addr);
+
+        let listener = Self {
+            listener,
+            throttle: Arc::default(),
+            tasks: TaskTracker::new(),
+        };
+        app.insert_resource(listener)
+            .add_systems(TickSet::PreTick, Self::accept.after(ConfigSet));
+        Ok(())
+    }
+
+    /// Wait for the connections' tasks to finish writing their last packets, for at most `grace`.
+    pub(crate) async fn drain(&self, grace: Duration) {
+        self.tasks.close();
+        if timeout(grace, self.tasks.wait()).await.is_err() {
+            debug!(
+                remaining = self.tasks.len(),
+                "stopped waiting for connections to close"
+            );
+        }
+    }
+
+    /// Accept every connection that is waiting, handing each off to its own task.
+    fn accept(
+        listener: Res<Self>,
+        config: Res<Config>,
+        queue: Res<WorldQueue>,
+        metrics: Res<RateLimitMetrics>,
+    ) {
+        loop {
+            let (sock, peer) = match listener.listener.accept() {
+                Ok(accepted) => accepted,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    debug!(%err, "failed to accept connection");
+                    break;
+                }
+            };
+            // the peer is the client unless it is a proxy, so it can be throttled straight away
+            if !proxy::forwards(peer.ip(), &config.network)
+                && !allow(&listener.throttle, peer, &config.network, &metrics)
+            {
+                continue;
+            }
+            let sock = match sock
+                .set_nonblocking(true)
+                .and_then(|()| TcpStream::from_std(sock))
+            {
+                Ok(sock) => sock,
+                Err(err) => {
+                    debug!(peer = %peer, %err, "failed to accept connection");
+                    continue;
+                }
+            };
+
+            spawn_connection(
+                sock,
+                peer,
+                listener.throttle.clone(),
+                listener.tasks.clone(),
+                queue.clone(),
+                config.network.clone(),
+                metrics.clone(),
+            );
+        }
+    }
+}
+
+/// Spawn a task that handles a new connection, once it has been accepted.
+fn spawn_connection(
+    sock: TcpStream,
+    peer: SocketAddr,
+    throttle: Arc<Mutex<ConnectionThrottle>>,
+    tasks: TaskTracker,
+    queue: WorldQueue,
+    network: NetworkConfig,
+    metrics: RateLimitMetrics,
+) {
+    let (reader, writer) = sock.into_split();
+    let mut reader = PeekReader::new(reader);
+
+    // spawn a task to read packets from the socket and send them to the connection, and write the
+    // connection's packets to the socket
+    tokio::spawn(async move {
+        let opening = open(&mut reader, peer, &network, &throttle, &metrics);
+        let Ok(opened) = timeout(OPEN_TIMEOUT, opening).await else {
+            debug!(peer = %peer, "connection closed, timed out waiting for the client");
+            return;
+        };
+        let Some((addr, request)) = opened else {
+            return;
+        };
+        if let Some(request) = request {
+            legacy::respond(request, writer, &queue).await;
+            return;
+        }
+
+        // spawn in the ecs
+        let spawn = move |world: &mut World| Connection::spawn(world, addr);
+        let Some((tx, rx)) = queue.run(spawn).await else {
+            return;
+        };
+        let _task = tasks.token();
+        debug!(addr = %addr, "new connection established");
+
+        let limiter = ConnectionLimiter::new(&network, metrics);
+        let reading = read_packets(reader, tx, limiter);
+        let writing = write_packets(writer, rx);
+        tokio::pin!(reading, writing);
+
+        tokio::select! {
+            // the server closed the connection, or the socket could not be written to
+            res = &mut writing => {
+                if let Err(reason) = res {
+                    debug!(addr = %addr, %reason, "connection closed");
+                }
+            }
+            // the client closed the connection, or was kicked. wait for the connection to be
+            // despawned, so any packets it was sent are written first.
+            reason = &mut reading => {
+                match reason {
+                    CloseReason::Kicked(_) => warn!(addr = %addr, %reason, "connection closed"),
+                    _ => debug!(addr = %addr, %reason, "connection closed"),
+                }
+                let _ = writing.await;
+            }
+        }
+    });
+}
+
+/// Find out who a new connection is from and how it starts, returning its real address and the
+/// legacy request it sent, if any. Returns `None` if the connection is refused.
+async fn open(
+    reader: &mut PeekReader<OwnedReadHalf>,
+    peer: SocketAddr,
+    network: &NetworkConfig,
+    throttle: &Mutex<ConnectionThrottle>,
+    metrics: &RateLimitMetrics,
+) -> Option<(SocketAddr, Option<legacy::Request>)> {
+    // find the client's real address if behind a proxy
+    let addr = match proxy::resolve(reader, peer, network).await {
+        Ok(addr) => addr,
+        Err(err) => {
+            warn!(peer = %peer, %err, "refusing connection");
+            return None;
+        }
+    };
+
+    // connections from clients were throttled as they were accepted, but the real address of
+    // proxied connections is only known now
+    if proxy::forwards(peer.ip(), network) && !allow(throttle, addr, network, metrics) {
+        return None;
+    }
+
+    // check for legacy server list pings and logins
+    Some((addr, legacy::detect(reader).await))
+}
+
+/// Check that a client has not connected too often, logging a warning if it has.
+fn allow(
+    throttle: &Mutex<ConnectionThrottle>,
+    addr: SocketAddr,
+    network: &NetworkConfig,
+    metrics: &RateLimitMetrics,
+) -> bool {
+    let allowed = throttle
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .allow(addr.ip(), network, metrics);
+    if !allowed {
+        warn!(addr = %addr, "refusing connection, too many connection attempts");
+    }
+    allowed
+}
+
+/// Read packets from the socket until it's closed, or the client is kicked, then close the incoming
+/// side of the connection.
+async fn read_packets(
+    mut reader: PeekReader<OwnedReadHalf>,
+    tx: IncomingSender,
+    mut limiter: ConnectionLimiter,
+) -> CloseReason {
+    let reason = loop {
+        let packet = match RawPacket::decode(&mut reader).await {
+            Ok(packet) => packet,
+            Err(DecodeError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                break CloseReason::Disconnected;
+            }
+            Err(err) => break CloseReason::Error(err.to_string()),
+        };
+
+        if let Err(limit) = limiter.check(&packet) {
+            break CloseReason::Kicked(format!("exceeded the {limit} limit"));
+        }
+        match tx.send(packet).await {
+            Ok(()) => {}
+            Err(QueueError::Overflow) => {
+                break CloseReason::Kicked("sent too many packets".to_string());
+            }
+            // the server closed the connection, and the writer will finish up
+            Err(QueueError::Closed) => break CloseReason::Disconnected,
+        }
+    };
+
+    tx.close(reason.clone());
+    reason
+}
+
+/// Write packets to the socket until the connection is despawned, then shut the socket down. If
+/// writing fails, the outgoing side of the connection is closed instead.
+async fn write_packets(writer: OwnedWriteHalf, rx: OutgoingReceiver) -> Result<(), CloseReason> {
+    let mut writer = PacketWriter::new(writer);
+    let res = async {
+        // each batch holds every packet sent to the connection during a tick
+        while let Ok(packets) = rx.recv_async().await {
+            writer.write_batch(&packets).await?;
+        }
+        writer.shutdown().await
+    }
+    .await;
+
+    res.map_err(|err| {
+        let reason = CloseReason::Error(err.to_string());
+        rx.close(reason.clone());
+        reason
+    })
+}
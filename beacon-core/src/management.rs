@@ -0,0 +1,849 @@
+//! The server management protocol, which lets tools manage the server with JSON-RPC 2.0 over a
+//! WebSocket.
+//!
+//! See: <https://minecraft.wiki/w/Minecraft_Server_Management_Protocol>
+
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use beacon_codec::ProtocolState;
+use beacon_config::{Config, ManagementTlsConfig};
+use beacon_data::{LATEST_SUPPORTED_VERSION, PROTOCOL_VERSION};
+use beacon_net::{
+    conn::{ConnectionClosed, PacketSender},
+    player::PlayerIdentity,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+use miette::{IntoDiagnostic, Result};
+use p12_keystore::KeyStore;
+use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::broadcast,
+    time::timeout,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    },
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    App, ServerHandle, TickSet,
+    queue::WorldQueue,
+    rcon::constant_time_eq,
+    websocket::{self, Handshake, Message, MessageReader, ReadError},
+};
+
+mod lists;
+
+/// How long a client has to finish its TLS and WebSocket handshakes, each.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a generated secret is, like vanilla.
+const SECRET_LENGTH: usize = 40;
+
+/// How many notifications can be waiting to be sent to a client before it misses some.
+const NOTIFICATION_CAPACITY: usize = 256;
+
+/// An error returned from a JSON-RPC method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    /// The error code, as defined by JSON-RPC.
+    pub code: i32,
+    /// A short description of the error.
+    pub message: String,
+}
+
+impl RpcError {
+    /// The request was not valid JSON.
+    pub const PARSE_ERROR: i32 = -32700;
+    /// The request was not a valid JSON-RPC request.
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// The method does not exist.
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// The method's parameters were invalid.
+    pub const INVALID_PARAMS: i32 = -32602;
+    /// The method failed.
+    pub const INTERNAL_ERROR: i32 = -32603;
+
+    /// Create an error with the given code.
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The method's parameters were invalid.
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({ "code": self.code, "message": self.message })
+    }
+}
+
+/// The parameters of a request, which can be given by position or by name.
+#[derive(Debug)]
+pub struct Params {
+    value: Option<Value>,
+}
+
+impl Params {
+    /// Get the parameter at `index`, or named `name`, if it was given.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        index: usize,
+        name: &str,
+    ) -> Result<Option<T>, RpcError> {
+        let value = match &self.value {
+            Some(Value::Array(params)) => params.get(index),
+            Some(Value::Object(params)) => params.get(name),
+            _ => None,
+        };
+        value
+            .map(|value| T::deserialize(value))
+            .transpose()
+            .map_err(|err| RpcError::invalid_params(format!("invalid parameter {name}: {err}")))
+    }
+
+    /// Get a parameter that must be given.
+    pub fn require<T: DeserializeOwned>(&self, index: usize, name: &str) -> Result<T, RpcError> {
+        self.get(index, name)?
+            .ok_or_else(|| RpcError::invalid_params(format!("missing parameter {name}")))
+    }
+}
+
+/// A management method, which runs in the world.
+type Method = Box<dyn Fn(&mut World, Params) -> Result<Value, RpcError> + Send + Sync>;
+
+/// The methods management clients can call, which plugins can add to with
+/// [App::add_management_method].
+#[derive(Resource)]
+pub struct ManagementMethods {
+    methods: BTreeMap<String, (String, Method)>,
+}
+
+impl Default for ManagementMethods {
+    fn default() -> Self {
+        let mut methods = Self {
+            methods: BTreeMap::new(),
+        };
+        methods.add_builtin();
+        methods
+    }
+}
+
+impl ManagementMethods {
+    /// Add a method, replacing any existing method with the same name.
+    pub fn add<F>(&mut self, name: impl Into<String>, description: impl Into<String>, method: F)
+    where
+        F: Fn(&mut World, Params) -> Result<Value, RpcError> + Send + Sync + 'static,
+    {
+        self.methods
+            .insert(name.into(), (description.into(), Box::new(method)));
+    }
+
+    /// Call a method by name.
+    fn call(&self, world: &mut World, name: &str, params: Params) -> Result<Value, RpcError> {
+        if name == "rpc.discover" {
+            return Ok(self.discover());
+        }
+        let (_, method) = self
+            .methods
+            .get(name)
+            .ok_or_else(|| RpcError::new(RpcError::METHOD_NOT_FOUND, "Method not found"))?;
+        method(world, params)
+    }
+
+    /// Describe every method, for `rpc.discover`.
+    fn discover(&self) -> Value {
+        let methods: Vec<_> = self
+            .methods
+            .iter()
+            .map(|(name, (description, _))| json!({ "name": name, "description": description }))
+            .collect();
+        json!({
+            "openrpc": "1.3.2",
+            "info": { "title": "beacon", "version": env!("CARGO_PKG_VERSION") },
+            "methods": methods,
+        })
+    }
+
+    fn add_builtin(&mut self) {
+        self.add(
+            "minecraft:players",
+            "List the online players",
+            |world, _| Ok(Value::Array(online_players(world))),
+        );
+        self.add(
+            "minecraft:players/kick",
+            "Kick players, given their ids or names",
+            |world, params| {
+                let players: Vec<PlayerRef> = params.require(0, "players")?;
+                let message = params.get::<String>(1, "message")?.map_or_else(
+                    || Text::translate("multiplayer.disconnect.kicked", [] as [Text; 0]),
+                    Text::from,
+                );
+
+                let mut kicked = Vec::new();
+                let mut query = world.query::<(&PlayerIdentity, &ProtocolState, &PacketSender)>();
+                for (identity, state, sender) in query.iter(world) {
+                    if !sender.is_closed() && players.iter().any(|player| player.matches(identity))
+                    {
+                        sender.kick(*state, message.clone()).map_err(|err| {
+                            RpcError::new(RpcError::INTERNAL_ERROR, err.to_string())
+                        })?;
+                        kicked.push(player_json(identity));
+                    }
+                }
+                Ok(Value::Array(kicked))
+            },
+        );
+
+        self.add_lists();
+
+        self.add(
+            "minecraft:server/status",
+            "Get the server's status",
+            |world, _| Ok(server_status(world)),
+        );
+        self.add(
+            "minecraft:server/save",
+            "Save the server. There is nothing to save yet, so this always succeeds",
+            |_, _| Ok(Value::Bool(true)),
+        );
+        self.add("minecraft:server/stop", "Stop the server", |world, _| {
+            info!("stopping the server, as requested by a management client");
+            world.resource::<ServerHandle>().shutdown();
+            Ok(Value::Bool(true))
+        });
+
+        // settings are changed in memory, until the configuration file is next reloaded
+        self.add_setting("motd", |config| &mut config.server.motd);
+        self.add_setting("max_players", |config| &mut config.server.max_players);
+        self.add_setting("hide_online_players", |config| {
+            &mut config.server.hide_online_players
+        });
+        self.add_setting("status_replies", |config| &mut config.server.status);
+        self.add_setting("status_heartbeat_interval", |config| {
+            &mut config.management.status_heartbeat_interval
+        });
+    }
+
+    /// Add a pair of methods to get and set a server setting.
+    fn add_setting<T>(&mut self, name: &str, field: fn(&mut Config) -> &mut T)
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        let method = format!("minecraft:serversettings/{name}");
+        self.add(
+            &method,
+            format!("Get the {name} setting"),
+            move |world, _| {
+                // reading the setting should not look like a change to the configuration
+                let mut config = world.resource_mut::<Config>();
+                Ok(json!(field(config.bypass_change_detection())))
+            },
+        );
+        self.add(
+            format!("{method}/set"),
+            format!("Set the {name} setting"),
+            move |world, params| {
+                let value = params.require(0, "value")?;
+                let mut config = world.resource_mut::<Config>();
+                *field(&mut config) = value;
+                Ok(json!(field(&mut config)))
+            },
+        );
+    }
+}
+
+impl App {
+    /// Add a method that management clients can call, replacing any existing method with the
+    /// same name.
+    pub fn add_management_method<F>(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        method: F,
+    ) -> &mut Self
+    where
+        F: Fn(&mut World, Params) -> Result<Value, RpcError> + Send + Sync + 'static,
+    {
+        self.world
+            .resource_mut::<ManagementMethods>()
+            .add(name, description, method);
+        self
+    }
+}
+
+/// Sends notifications to every connected management client.
+#[derive(Resource, Clone)]
+pub struct ManagementNotifier {
+    tx: broadcast::Sender<Arc<str>>,
+}
+
+impl Default for ManagementNotifier {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        Self { tx }
+    }
+}
+
+impl ManagementNotifier {
+    /// Notify every connected client.
+    pub fn notify(&self, method: &str, params: Value) {
+        // nothing to do if no clients are connected
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+        let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        let _ = self.tx.send(notification.to_string().into());
+    }
+
+    /// Notify clients of players that have joined.
+    pub(crate) fn joined(
+        notifier: Res<Self>,
+        query: Query<(&PlayerIdentity, &ProtocolState), Changed<ProtocolState>>,
+    ) {
+        for (identity, _) in query
+            .iter()
+            .filter(|(_, state)| **state == ProtocolState::Play)
+        {
+            notifier.notify(
+                "minecraft:notification/players/joined",
+                json!([player_json(identity)]),
+            );
+        }
+    }
+
+    /// Notify clients of players that have left.
+    pub(crate) fn left(
+        event: On<ConnectionClosed>,
+        notifier: Res<Self>,
+        query: Query<(&PlayerIdentity, &ProtocolState)>,
+    ) {
+        if let Ok((identity, ProtocolState::Play)) = query.get(event.entity) {
+            notifier.notify(
+                "minecraft:notification/players/left",
+                json!([player_json(identity)]),
+            );
+        }
+    }
+
+    /// Notify clients of the server's status every `status-heartbeat-interval` seconds.
+    pub(crate) fn heartbeat(world: &mut World, mut last: Local<Option<Instant>>) {
+        let interval = world
+            .resource::<Config>()
+            .management
+            .status_heartbeat_interval;
+        if interval == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if last.is_some_and(|last| now - last < Duration::from_secs(interval)) {
+            return;
+        }
+        *last = Some(now);
+
+        let status = server_status(world);
+        world
+            .resource::<Self>()
+            .notify("minecraft:notification/server/status", json!([status]));
+    }
+}
+
+/// A player, identified by their id or name.
+#[derive(Debug, Deserialize)]
+struct PlayerRef {
+    id: Option<Uuid>,
+    name: Option<String>,
+}
+
+impl PlayerRef {
+    fn matches(&self, identity: &PlayerIdentity) -> bool {
+        self.id.is_some_and(|id| id.as_u128() == identity.uuid)
+            || self
+                .name
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(&identity.name))
+    }
+}
+
+fn player_json(identity: &PlayerIdentity) -> Value {
+    json!({
+        "id": Uuid::from_u128(identity.uuid).hyphenated().to_string(),
+        "name": identity.name,
+    })
+}
+
+fn online_players(world: &mut World) -> Vec<Value> {
+    let mut query = world.query::<(&PlayerIdentity, &ProtocolState)>();
+    query
+        .iter(world)
+        .filter(|(_, state)| **state == ProtocolState::Play)
+        .map(|(identity, _)| player_json(identity))
+        .collect()
+}
+
+fn server_status(world: &mut World) -> Value {
+    json!({
+        "started": true,
+        "players": online_players(world),
+        "version": {
+            "name": LATEST_SUPPORTED_VERSION.to_string(),
+            "protocol": PROTOCOL_VERSION,
+        },
+    })
+}
+
+/// Handle a JSON-RPC message, which is either a single request or a batch of them, returning the
+/// response to send, if any.
+fn handle_message(world: &mut World, message: &str) -> Option<String> {
+    let response = match serde_json::from_str::<Value>(message) {
+        Ok(Value::Array(requests)) if requests.is_empty() => Some(error_response(
+            Value::Null,
+            RpcError::new(RpcError::INVALID_REQUEST, "Invalid Request"),
+        )),
+        Ok(Value::Array(requests)) => {
+            let responses: Vec<_> = requests
+                .into_iter()
+                .filter_map(|request| handle_request(world, request))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(request) => handle_request(world, request),
+        Err(_) => Some(error_response(
+            Value::Null,
+            RpcError::new(RpcError::PARSE_ERROR, "Parse error"),
+        )),
+    };
+    response.map(|response| response.to_string())
+}
+
+/// Handle a single request, returning its response unless it is a notification.
+fn handle_request(world: &mut World, request: Value) -> Option<Value> {
+    let invalid = || RpcError::new(RpcError::INVALID_REQUEST, "Invalid Request");
+    let Value::Object(mut request) = request else {
+        return Some(error_response(Value::Null, invalid()));
+    };
+    let id = request.remove("id");
+    if let Some(id) = &id
+        && !matches!(id, Value::Null | Value::Number(_) | Value::String(_))
+    {
+        return Some(error_response(Value::Null, invalid()));
+    }
+    let method = match (request.remove("jsonrpc"), request.remove("method")) {
+        (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => method,
+        _ => return Some(error_response(id.unwrap_or_default(), invalid())),
+    };
+    let params = match request.remove("params") {
+        value @ (None | Some(Value::Array(_) | Value::Object(_))) => Params { value },
+        Some(_) => {
+            let err = RpcError::invalid_params("params must be an array or an object");
+            return Some(error_response(id.unwrap_or_default(), err));
+        }
+    };
+
+    let result = world.resource_scope(|world, methods: Mut<ManagementMethods>| {
+        methods.call(world, &method, params)
+    });
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => error_response(id, err),
+    })
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": err.to_json() })
+}
+
+/// State shared between every management connection.
+struct Shared {
+    secret: String,
+    queue: WorldQueue,
+    notifier: ManagementNotifier,
+}
+
+/// Accepts management connections, alongside the server's TCP listener.
+pub(crate) struct ManagementServer {
+    listener: TcpListener,
+    /// Set if connections must use TLS.
+    tls: Option<TlsAcceptor>,
+    shared: Arc<Shared>,
+}
+
+impl ManagementServer {
+    /// Bind the management listener if it is enabled, generating a secret if none is set.
+    pub(crate) async fn bind(app: &mut App) -> Result<Option<Self>> {
+        let config = &app.world.resource::<Config>().management;
+        if !config.enable {
+            return Ok(None);
+        }
+        let tls = match config.tls.enable {
+            true => Some(tls_acceptor(&config.tls)?),
+            false => None,
+        };
+        let secret = match config.secret.is_empty() {
+            true => {
+                let secret = Alphanumeric.sample_string(&mut rand::rng(), SECRET_LENGTH);
+                warn!(
+                    "no management secret is set, so clients must use {secret} until the server \
+                     restarts. set management.secret to keep one"
+                );
+                secret
+            }
+            false => config.secret.clone(),
+        };
+        let listener = TcpListener::bind((config.ip, config.port))
+            .await
+            .into_diagnostic()?;
+        info!(
+            "management listening on {}",
+            listener.local_addr().into_diagnostic()?
+        );
+
+        let shared = Shared {
+            secret,
+            queue: app.world.resource::<WorldQueue>().clone(),
+            notifier: app.world.resource::<ManagementNotifier>().clone(),
+        };
+        Ok(Some(Self {
+            listener,
+            tls,
+            shared: Arc::new(shared),
+        }))
+    }
+
+    /// Accept connections until the server shuts down.
+    pub(crate) async fn run(self, cancel: CancellationToken) {
+        loop {
+            let (stream, addr) = tokio::select! {
+                res = self.listener.accept() => match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        debug!(%err, "failed to accept management connection");
+                        continue;
+                    }
+                },
+                _ = cancel.cancelled() => break,
+            };
+
+            let (tls, shared) = (self.tls.clone(), self.shared.clone());
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                debug!(%addr, "management connection established");
+                let res = match tls {
+                    Some(tls) => match timeout(HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                        Ok(Ok(stream)) => handle(stream, addr, &shared, cancel).await,
+                        Ok(Err(err)) => Err(err),
+                        Err(_) => Err(handshake_timed_out()),
+                    },
+                    None => handle(stream, addr, &shared, cancel).await,
+                };
+                match res {
+                    Err(err) if err.kind() != ErrorKind::UnexpectedEof => {
+                        debug!(%addr, %err, "management connection closed");
+                    }
+                    _ => debug!(%addr, "management connection closed"),
+                }
+            });
+        }
+    }
+}
+
+/// Load the certificate chain and private key that connections are encrypted with from a PKCS#12
+/// keystore.
+fn tls_acceptor(config: &ManagementTlsConfig) -> Result<TlsAcceptor> {
+    if config.keystore.as_os_str().is_empty() {
+        return Err(miette::miette!(
+            help = "set management.tls.keystore to a PKCS#12 keystore, or disable management.tls",
+            "management requires TLS, but no keystore is configured"
+        ));
+    }
+
+    let help = "check management.tls.keystore and management.tls.password";
+    let path = config.keystore.display();
+    let keystore = std::fs::read(&config.keystore).map_err(|err| {
+        miette::miette!(help = help, "failed to read the keystore at {path}: {err}")
+    })?;
+    let keystore = KeyStore::from_pkcs12(&keystore, &config.password).map_err(|err| {
+        miette::miette!(help = help, "failed to load the keystore at {path}: {err}")
+    })?;
+    let (_, chain) = keystore
+        .private_key_chain()
+        .ok_or_else(|| miette::miette!(help = help, "the keystore at {path} has no private key"))?;
+
+    let certs = chain
+        .chain()
+        .iter()
+        .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+        .collect();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(chain.key().to_vec()));
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| miette::miette!(help = help, "invalid key in {path}: {err}"))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn handshake_timed_out() -> io::Error {
+    io::Error::new(
+        ErrorKind::TimedOut,
+        "the handshake was not finished in time",
+    )
+}
+
+/// Upgrade a connection to a WebSocket, then answer its requests and send it notifications until
+/// it disconnects.
+async fn handle<S: AsyncRead + AsyncWrite>(
+    stream: S,
+    addr: SocketAddr,
+    shared: &Shared,
+    cancel: CancellationToken,
+) -> io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    let handshake = timeout(HANDSHAKE_TIMEOUT, Handshake::read(&mut reader))
+        .await
+        .map_err(|_| handshake_timed_out())??;
+    let authorized = handshake
+        .bearer
+        .as_ref()
+        .is_some_and(|bearer| constant_time_eq(bearer, &shared.secret));
+    let response = match handshake.accept() {
+        Some(response) if authorized => response,
+        Some(_) => {
+            warn!(%addr, "refusing management connection, wrong secret");
+            writer
+                .write_all(websocket::refuse("401 Unauthorized").as_bytes())
+                .await?;
+            return Ok(());
+        }
+        None => {
+            writer
+                .write_all(websocket::refuse("400 Bad Request").as_bytes())
+                .await?;
+            return Ok(());
+        }
+    };
+    writer.write_all(response.as_bytes()).await?;
+    info!(%addr, "management client connected");
+
+    let mut notifications = shared.notifier.tx.subscribe();
+    let mut reader = MessageReader::new(reader);
+    let (tx, rx) = flume::unbounded::<Outgoing>();
+
+    // the reader is not cancel safe, so it runs alongside the writer rather than being selected
+    // on in a loop
+    let reading = async {
+        // dropping the sender when reading stops lets the writer finish
+        let tx = tx;
+        loop {
+            match reader.read().await {
+                Ok(Message::Text(text)) => {
+                    let run = move |world: &mut World| handle_message(world, &text);
+                    match shared.queue.run(run).await {
+                        Some(Some(response)) => {
+                            let _ = tx.send(Outgoing::Text(response.into()));
+                        }
+                        Some(None) => {}
+                        None => return Ok(()),
+                    }
+                }
+                Ok(Message::Ping(data)) => {
+                    let _ = tx.send(Outgoing::Pong(data));
+                }
+                Ok(Message::Close) => {
+                    let _ = tx.send(Outgoing::Close(websocket::CLOSE_NORMAL));
+                    return Ok(());
+                }
+                Err(ReadError::Protocol(status)) => {
+                    let _ = tx.send(Outgoing::Close(status));
+                    return Ok(());
+                }
+                Err(ReadError::Io(err)) => return Err(err),
+            }
+        }
+    };
+    let writing = async {
+        loop {
+            let outgoing = tokio::select! {
+                outgoing = rx.recv_async() => match outgoing {
+                    Ok(outgoing) => outgoing,
+                    Err(_) => return Ok(()),
+                },
+                notification = notifications.recv() => match notification {
+                    Ok(text) => Outgoing::Text(text),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        debug!(%addr, missed, "management client missed notifications");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                _ = cancel.cancelled() => Outgoing::Close(websocket::CLOSE_GOING_AWAY),
+            };
+            if outgoing.write(&mut writer).await? {
+                return writer.shutdown().await;
+            }
+        }
+    };
+    tokio::pin!(reading, writing);
+
+    // keep writing until the close frame has been sent, after the reader finishes
+    let res = tokio::select! {
+        res = &mut reading => res.and((&mut writing).await),
+        res = &mut writing => res,
+    };
+    info!(%addr, "management client disconnected");
+    res
+}
+
+/// A frame waiting to be sent to a client.
+enum Outgoing {
+    Text(Arc<str>),
+    Pong(Vec<u8>),
+    Close(u16),
+}
+
+impl Outgoing {
+    /// Write the frame, returning whether the connection is now closed.
+    async fn write<W: AsyncWrite + Unpin>(self, writer: &mut W) -> io::Result<bool> {
+        match self {
+            Self::Text(text) => websocket::write_text(writer, &text).await?,
+            Self::Pong(data) => websocket::write_pong(writer, &data).await?,
+            Self::Close(status) => {
+                websocket::write_close(writer, status).await?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Add the resources and systems that send notifications, whether or not the management server
+/// is started.
+pub(crate) fn configure(world: &mut World, schedule: &mut Schedule) {
+    world.init_resource::<ManagementMethods>();
+    world.init_resource::<ManagementNotifier>();
+    world.add_observer(ManagementNotifier::left);
+    schedule.add_systems(
+        (ManagementNotifier::joined, ManagementNotifier::heartbeat).in_set(TickSet::PostTick),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::ProtocolState;
+    use beacon_config::Config;
+    use beacon_net::{
+        client::connection::Disconnect,
+        conn::{Connection, PacketSender},
+        player::PlayerIdentity,
+        text::Text,
+    };
+    use bevy_ecs::prelude::*;
+    use serde_json::{Value, json};
+
+    use super::{ManagementMethods, handle_message};
+
+    #[test]
+    fn test_handle_message() {
+        let mut world = World::new();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<ManagementMethods>();
+        let mut call = |message: Value| {
+            let response = handle_message(&mut world, &message.to_string())?;
+            Some(serde_json::from_str::<Value>(&response).unwrap())
+        };
+
+        // by position and by name
+        let set = |params| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "minecraft:serversettings/max_players/set",
+                "params": params,
+            })
+        };
+        assert_eq!(call(set(json!([5]))).unwrap()["result"], 5);
+        assert_eq!(call(set(json!({ "value": 7 }))).unwrap()["result"], 7);
+        assert_eq!(call(set(json!(["x"]))).unwrap()["error"]["code"], -32602);
+
+        // notifications have no response
+        let mut notification = set(json!([9]));
+        notification.as_object_mut().unwrap().remove("id");
+        assert_eq!(call(notification), None);
+
+        // batches, with unknown methods and invalid requests
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": "a", "method": "minecraft:serversettings/max_players" },
+            { "jsonrpc": "2.0", "id": "b", "method": "nope" },
+            1,
+        ]);
+        let response = call(batch).unwrap();
+        assert_eq!(response[0]["result"], 9);
+        assert_eq!(response[1]["error"]["code"], -32601);
+        assert_eq!(response[2]["error"]["code"], -32600);
+
+        assert_eq!(
+            handle_message(&mut world, "{").unwrap(),
+            r#"{"error":{"code":-32700,"message":"Parse error"},"id":null,"jsonrpc":"2.0"}"#
+        );
+    }
+
+    #[test]
+    fn test_kick() {
+        let mut world = World::new();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<ManagementMethods>();
+        let mut schedule = Schedule::default();
+        beacon_net::ecs(&mut world, &mut schedule);
+
+        let (_incoming, outgoing) = Connection::spawn(&mut world, ([127, 0, 0, 1], 1).into());
+        let mut connections = world.query_filtered::<Entity, With<PacketSender>>();
+        let entity = connections.single(&world).unwrap();
+        world.entity_mut(entity).insert((
+            ProtocolState::Play,
+            PlayerIdentity {
+                name: "Steve".into(),
+                uuid: 1,
+                properties: Vec::new(),
+            },
+        ));
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "minecraft:players/kick",
+            "params": [[{ "name": "steve" }], "Bye"],
+        });
+        let response = handle_message(&mut world, &request.to_string()).unwrap();
+        let response = serde_json::from_str::<Value>(&response).unwrap();
+        assert_eq!(response["result"][0]["name"], "Steve");
+        schedule.run(&mut world);
+
+        // the player is shown the message before being disconnected
+        let disconnect = Disconnect::new(Text::from("Bye")).blocking_raw().unwrap();
+        let packets = outgoing.try_recv().unwrap();
+        assert_eq!(format!("{packets:?}"), format!("{:?}", [disconnect]));
+    }
+}
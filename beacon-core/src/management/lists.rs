@@ -0,0 +1,377 @@
+//! Methods to view and change the allowlist, bans, IP bans and operators.
+
+use std::{net::IpAddr, time::SystemTime};
+
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
+use beacon_net::{
+    conn::{PacketSender, RemoteAddr},
+    player::PlayerIdentity,
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::{ManagementMethods, PlayerRef, RpcError};
+use crate::access::{
+    AccessList, Ban, BannedIps, BannedPlayers, GameProfile, IpBan, ListEntry, Operator, Operators,
+    PlayerBan, Whitelist, date, find_profile,
+};
+
+/// Who made bans added by management clients, unless they say otherwise.
+const SOURCE: &str = "Management server";
+
+impl ManagementMethods {
+    /// Add the methods for each access list.
+    pub(super) fn add_lists(&mut self) {
+        self.add_list::<GameProfile>("allowlist", "the allowlist", "players");
+        self.add_list::<PlayerBan>("bans", "the banned players", "bans");
+        self.add_list::<IpBan>("ip_bans", "the banned IP addresses", "banlist");
+        self.add_list::<Operator>("operators", "the operators", "operators");
+    }
+
+    /// Add the methods to get, replace, add to, remove from and clear a list. Each returns the
+    /// list as it is afterwards.
+    fn add_list<T: ManagedEntry>(
+        &mut self,
+        name: &str,
+        description: &str,
+        set_param: &'static str,
+    ) {
+        let method = format!("minecraft:{name}");
+        self.add(&method, format!("Get {description}"), |world, _| {
+            Ok(list_json::<T>(world))
+        });
+        self.add(
+            format!("{method}/set"),
+            format!("Replace {description}"),
+            move |world, params| {
+                let entries = T::parse_all(world, params.require(0, set_param)?)?;
+                let mut list = world.resource_mut::<AccessList<T>>();
+                *list = AccessList::default();
+                for entry in entries {
+                    list.insert(entry);
+                }
+                T::enforce(world)?;
+                Ok(list_json::<T>(world))
+            },
+        );
+        self.add(
+            format!("{method}/add"),
+            format!("Add to {description}"),
+            |world, params| {
+                let entries = T::parse_all(world, params.require(0, "add")?)?;
+                let mut list = world.resource_mut::<AccessList<T>>();
+                for entry in entries {
+                    list.insert(entry);
+                }
+                T::enforce(world)?;
+                Ok(list_json::<T>(world))
+            },
+        );
+        self.add(
+            format!("{method}/remove"),
+            format!("Remove from {description}"),
+            |world, params| {
+                let values: Vec<Value> = params.require(0, "remove")?;
+                for value in values {
+                    if let Some(key) = T::parse_key(world, value)? {
+                        world.resource_mut::<AccessList<T>>().remove(&key);
+                    }
+                }
+                Ok(list_json::<T>(world))
+            },
+        );
+        self.add(
+            format!("{method}/clear"),
+            format!("Clear {description}"),
+            |world, _| {
+                *world.resource_mut::<AccessList<T>>() = AccessList::default();
+                Ok(list_json::<T>(world))
+            },
+        );
+    }
+}
+
+/// An entry of an access list that management clients can change.
+trait ManagedEntry: ListEntry + Sized {
+    /// The entry, as sent to clients.
+    fn to_json(&self) -> Value;
+
+    /// Parse an entry sent by a client, or `None` if the player it names is unknown.
+    fn parse(world: &mut World, value: Value) -> Result<Option<Self>, RpcError>;
+
+    /// Parse which entry a client is removing, or `None` if the player it names is unknown.
+    fn parse_key(world: &mut World, value: Value) -> Result<Option<Self::Key>, RpcError>;
+
+    /// Apply the list to the players online once entries have been added.
+    fn enforce(_world: &mut World) -> Result<(), RpcError> {
+        Ok(())
+    }
+
+    /// Parse every entry sent by a client, skipping players that are unknown.
+    fn parse_all(world: &mut World, values: Vec<Value>) -> Result<Vec<Self>, RpcError> {
+        let mut entries = Vec::new();
+        for value in values {
+            entries.extend(Self::parse(world, value)?);
+        }
+        Ok(entries)
+    }
+}
+
+impl ManagedEntry for GameProfile {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": Uuid::from_u128(self.uuid).hyphenated().to_string(),
+            "name": self.name,
+        })
+    }
+
+    fn parse(world: &mut World, value: Value) -> Result<Option<Self>, RpcError> {
+        Ok(resolve(world, &deserialize(value)?))
+    }
+
+    fn parse_key(world: &mut World, value: Value) -> Result<Option<u128>, RpcError> {
+        player_key(world, value)
+    }
+}
+
+/// A ban sent by a client, of a player or an IP address.
+#[derive(Debug, Deserialize)]
+struct IncomingBan {
+    player: Option<PlayerRef>,
+    ip: Option<IpAddr>,
+    reason: Option<String>,
+    source: Option<String>,
+    #[serde(default, with = "date::expiry")]
+    expires: Option<SystemTime>,
+}
+
+impl IncomingBan {
+    /// The ban's details, made now.
+    fn ban(&self) -> Ban {
+        let source = self.source.as_deref().unwrap_or(SOURCE);
+        Ban {
+            expires: self.expires,
+            ..Ban::new(source, self.reason.clone())
+        }
+    }
+}
+
+impl ManagedEntry for PlayerBan {
+    fn to_json(&self) -> Value {
+        let mut value = json!(self.ban);
+        value["player"] = self.profile.to_json();
+        value
+    }
+
+    fn parse(world: &mut World, value: Value) -> Result<Option<Self>, RpcError> {
+        let incoming: IncomingBan = deserialize(value)?;
+        let player = incoming
+            .player
+            .as_ref()
+            .ok_or_else(|| RpcError::invalid_params("missing player"))?;
+        Ok(resolve(world, player).map(|profile| Self {
+            profile,
+            ban: incoming.ban(),
+        }))
+    }
+
+    fn parse_key(world: &mut World, value: Value) -> Result<Option<u128>, RpcError> {
+        player_key(world, value)
+    }
+
+    fn enforce(world: &mut World) -> Result<(), RpcError> {
+        let bans = world.resource::<BannedPlayers>().clone();
+        kick(world, "multiplayer.disconnect.banned", |identity, _| {
+            bans.contains(&identity.uuid)
+        })
+    }
+}
+
+impl ManagedEntry for IpBan {
+    fn to_json(&self) -> Value {
+        json!(self)
+    }
+
+    /// Bans an address, or the address of a player who is online.
+    fn parse(world: &mut World, value: Value) -> Result<Option<Self>, RpcError> {
+        let incoming: IncomingBan = deserialize(value)?;
+        let ip = match (&incoming.ip, &incoming.player) {
+            (Some(ip), _) => Some(*ip),
+            (None, Some(player)) => {
+                let mut query = world.query::<(&PlayerIdentity, &RemoteAddr)>();
+                query
+                    .iter(world)
+                    .find(|(identity, _)| player.matches(identity))
+                    .map(|(_, addr)| addr.ip())
+            }
+            (None, None) => return Err(RpcError::invalid_params("missing ip or player")),
+        };
+        Ok(ip.map(|ip| Self {
+            ip,
+            ban: incoming.ban(),
+        }))
+    }
+
+    fn parse_key(_: &mut World, value: Value) -> Result<Option<IpAddr>, RpcError> {
+        deserialize(value).map(Some)
+    }
+
+    fn enforce(world: &mut World) -> Result<(), RpcError> {
+        let bans = world.resource::<BannedIps>().clone();
+        kick(world, "multiplayer.disconnect.ip_banned", |_, addr| {
+            bans.contains(&addr.ip())
+        })
+    }
+}
+
+/// An operator sent by a client.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IncomingOperator {
+    player: PlayerRef,
+    permission_level: Option<u8>,
+    bypasses_player_limit: Option<bool>,
+}
+
+impl ManagedEntry for Operator {
+    fn to_json(&self) -> Value {
+        json!({
+            "player": self.profile.to_json(),
+            "permissionLevel": self.level,
+            "bypassesPlayerLimit": self.bypasses_player_limit,
+        })
+    }
+
+    fn parse(world: &mut World, value: Value) -> Result<Option<Self>, RpcError> {
+        let incoming: IncomingOperator = deserialize(value)?;
+        let level = incoming
+            .permission_level
+            .unwrap_or(world.resource::<Config>().server.op_permission_level);
+        Ok(resolve(world, &incoming.player).map(|profile| Self {
+            profile,
+            level,
+            bypasses_player_limit: incoming.bypasses_player_limit.unwrap_or_default(),
+        }))
+    }
+
+    fn parse_key(world: &mut World, value: Value) -> Result<Option<u128>, RpcError> {
+        player_key(world, value)
+    }
+}
+
+/// A list, as sent to clients.
+fn list_json<T: ManagedEntry>(world: &World) -> Value {
+    let list = world.resource::<AccessList<T>>();
+    Value::Array(list.iter().map(T::to_json).collect())
+}
+
+/// Parse part of a request.
+fn deserialize<T: DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    serde_json::from_value(value).map_err(|err| RpcError::invalid_params(err.to_string()))
+}
+
+/// The profile of a player, given their id and name, or looked up by either among the players the
+/// server knows.
+fn resolve(world: &mut World, player: &PlayerRef) -> Option<GameProfile> {
+    match (player.id, &player.name) {
+        (Some(id), Some(name)) => Some(GameProfile {
+            uuid: id.as_u128(),
+            name: name.clone(),
+        }),
+        (Some(id), None) => {
+            let uuid = id.as_u128();
+            let mut query = world.query::<&PlayerIdentity>();
+            let online = query.iter(world).find(|identity| identity.uuid == uuid);
+            online
+                .map(GameProfile::from)
+                .or_else(|| {
+                    let op = world.resource::<Operators>().get(&uuid);
+                    op.map(|op| op.profile.clone())
+                })
+                .or_else(|| world.resource::<Whitelist>().get(&uuid).cloned())
+                .or_else(|| {
+                    let ban = world.resource::<BannedPlayers>().get(&uuid);
+                    ban.map(|ban| ban.profile.clone())
+                })
+        }
+        (None, Some(name)) => find_profile(world, name),
+        (None, None) => None,
+    }
+}
+
+/// The UUID of a player being removed from a list.
+fn player_key(world: &mut World, value: Value) -> Result<Option<u128>, RpcError> {
+    let player: PlayerRef = deserialize(value)?;
+    Ok(match player.id {
+        Some(id) => Some(id.as_u128()),
+        None => resolve(world, &player).map(|profile| profile.uuid),
+    })
+}
+
+/// Kick the players who are no longer allowed to play.
+fn kick(
+    world: &mut World,
+    reason: &str,
+    banned: impl Fn(&PlayerIdentity, &RemoteAddr) -> bool,
+) -> Result<(), RpcError> {
+    let mut query = world.query::<(&PlayerIdentity, &RemoteAddr, &ProtocolState, &PacketSender)>();
+    for (identity, addr, state, sender) in query.iter(world) {
+        if banned(identity, addr) && !sender.is_closed() {
+            sender
+                .kick(*state, Text::translate(reason, [] as [Text; 0]))
+                .map_err(|err| RpcError::new(RpcError::INTERNAL_ERROR, err.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_config::Config;
+    use bevy_ecs::world::World;
+    use serde_json::{Value, json};
+
+    use crate::{BannedIps, BannedPlayers, Operators, Whitelist, management::handle_message};
+
+    #[test]
+    fn test_lists() {
+        let mut world = World::new();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<super::ManagementMethods>();
+        world.init_resource::<Operators>();
+        world.init_resource::<Whitelist>();
+        world.init_resource::<BannedPlayers>();
+        world.init_resource::<BannedIps>();
+        let mut call = |method: &str, params: Value| {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+            let response = handle_message(&mut world, &request.to_string()).unwrap();
+            serde_json::from_str::<Value>(&response).unwrap()["result"].take()
+        };
+        let steve = json!({ "id": "00000000-0000-0000-0000-000000000001", "name": "Steve" });
+
+        let operators = call("minecraft:operators/add", json!([[{ "player": steve }]]));
+        assert_eq!(operators[0]["player"], steve);
+        assert_eq!(operators[0]["permissionLevel"], 4);
+
+        // players already known can be named without their id
+        let allowlist = call("minecraft:allowlist/add", json!([[{ "name": "steve" }]]));
+        assert_eq!(allowlist, json!([steve]));
+        let allowlist = call("minecraft:allowlist/remove", json!([[{ "name": "Steve" }]]));
+        assert_eq!(allowlist, json!([]));
+
+        let bans = call(
+            "minecraft:bans/set",
+            json!([[{ "player": steve, "reason": "griefing" }]]),
+        );
+        assert_eq!(bans[0]["reason"], "griefing");
+        assert_eq!(bans[0]["expires"], "forever");
+
+        let ip_bans = call("minecraft:ip_bans/add", json!([[{ "ip": "10.0.0.1" }]]));
+        assert_eq!(ip_bans[0]["ip"], "10.0.0.1");
+        assert_eq!(call("minecraft:ip_bans/clear", json!([])), json!([]));
+    }
+}
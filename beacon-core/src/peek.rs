@@ -0,0 +1,93 @@
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+/// A reader that can look ahead at what a connection starts with, such as a PROXY protocol header
+/// or a legacy ping, without consuming it.
+///
+/// Peeked bytes are kept until they are read, so the reader can be handed on as if nothing had been
+/// peeked.
+pub(crate) struct PeekReader<R> {
+    reader: R,
+    peeked: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> PeekReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            peeked: Vec::new(),
+        }
+    }
+
+    /// The bytes that have been peeked so far.
+    pub fn peeked(&self) -> &[u8] {
+        &self.peeked
+    }
+
+    /// Peek at the next `len` bytes, waiting until they have all been sent.
+    ///
+    /// This is cancel safe: bytes that arrive before the future is dropped are kept for the next
+    /// peek or read.
+    pub async fn peek_exact(&mut self, len: usize) -> io::Result<&[u8]> {
+        let mut chunk = [0; 64];
+        while self.peeked.len() < len {
+            let wanted = (len - self.peeked.len()).min(chunk.len());
+            let read = self.reader.read(&mut chunk[..wanted]).await?;
+            if read == 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            self.peeked.extend_from_slice(&chunk[..read]);
+        }
+        Ok(&self.peeked[..len])
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for PeekReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.peeked.is_empty() {
+            return Pin::new(&mut self.reader).poll_read(cx, buf);
+        }
+        let len = self.peeked.len().min(buf.remaining());
+        buf.put_slice(&self.peeked[..len]);
+        self.peeked.drain(..len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::PeekReader;
+
+    #[tokio::test]
+    async fn test_peek() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = PeekReader::new(server);
+
+        // bytes arriving separately are waited for
+        client.write_all(&[1, 2]).await.unwrap();
+        let peek = tokio::spawn(async move {
+            let peeked = reader.peek_exact(3).await.unwrap().to_vec();
+            (reader, peeked)
+        });
+        client.write_all(&[3, 4]).await.unwrap();
+        let (mut reader, peeked) = peek.await.unwrap();
+        assert_eq!(peeked, [1, 2, 3]);
+
+        // peeked bytes are read again
+        drop(client);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
+use beacon_net::{
+    client::tab_list::{PlayerInfo, PlayerInfoRemove, PlayerInfoUpdate, TabList},
+    conn::{ConnectionClosed, PacketSender, RemoteAddr},
+    player::{GameMode, PlayerIdentity},
+    text::Text,
+};
+use bevy_ecs::prelude::*;
+
+use crate::{App, Operators, TickSet};
+
+/// The players who have logged in, by UUID and name. Players are added once they have said who
+/// they are, and removed when they disconnect.
+#[derive(Resource, Debug, Default)]
+pub struct PlayerList {
+    players: HashMap<u128, (String, Entity)>,
+}
+
+impl PlayerList {
+    /// The connection of the player with the given UUID.
+    pub fn get(&self, uuid: u128) -> Option<Entity> {
+        self.players.get(&uuid).map(|(_, entity)| *entity)
+    }
+
+    /// The connection of the player with the given name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<Entity> {
+        self.players
+            .values()
+            .find(|(player, _)| player.eq_ignore_ascii_case(name))
+            .map(|(_, entity)| *entity)
+    }
+
+    /// Whether a player with the given UUID has logged in.
+    pub fn contains(&self, uuid: u128) -> bool {
+        self.players.contains_key(&uuid)
+    }
+
+    /// Iterate over the UUIDs of the players and their connections, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (u128, Entity)> {
+        self.players
+            .iter()
+            .map(|(uuid, (_, entity))| (*uuid, *entity))
+    }
+
+    /// How many players have logged in.
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    /// Whether no players have logged in.
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    /// Add a player who has logged in, returning the connection they were already logged in on.
+    fn insert(&mut self, identity: &PlayerIdentity, entity: Entity) -> Option<Entity> {
+        self.players
+            .insert(identity.uuid, (identity.name.clone(), entity))
+            .map(|(_, entity)| entity)
+            .filter(|old| *old != entity)
+    }
+
+    /// Remove a player, unless they have since logged in on another connection.
+    fn remove(&mut self, uuid: u128, entity: Entity) {
+        if self.get(uuid) == Some(entity) {
+            self.players.remove(&uuid);
+        }
+    }
+}
+
+/// Event triggered on a connection once the player has said who they are, before they are added to
+/// the [PlayerList]. Observers can refuse the player, e.g. because they are banned, by setting
+/// `refusal` to the reason shown to them.
+#[derive(EntityEvent, Debug)]
+pub struct PlayerLogin {
+    /// The player's connection.
+    pub entity: Entity,
+    /// Why the player may not join, if they may not.
+    pub refusal: Option<Text>,
+}
+
+/// Keep track of the players who have logged in, and keep each player's tab list up to date.
+pub(crate) fn configure_network(app: &mut App) {
+    app.add_observer(PlayerList::login)
+        .add_observer(PlayerList::leave)
+        .add_systems(
+            TickSet::NetworkOut,
+            (announce_joins, update_game_modes, update_header_and_footer),
+        );
+}
+
+/// A connection that is shown the tab list.
+type Viewer<'a> = (&'a ProtocolState, &'a PacketSender);
+
+impl PlayerList {
+    /// Check players once they have said who they are, before anything else sees them as logged in.
+    fn login(event: On<Insert, PlayerIdentity>, mut commands: Commands) {
+        let entity = event.entity;
+        commands.queue(move |world: &mut World| Self::join(world, entity));
+    }
+
+    /// Check a player who has said who they are with the [PlayerLogin] observers, then add them to
+    /// the list.
+    fn join(world: &mut World, entity: Entity) -> Result<()> {
+        let mut login = PlayerLogin {
+            entity,
+            refusal: None,
+        };
+        world.trigger_ref(&mut login);
+        world.run_system_cached_with(Self::admit, (entity, login.refusal))?
+    }
+
+    /// Add a player to the list, unless they were refused or the server is full. Players logging
+    /// in again disconnect their old connection.
+    fn admit(
+        In((entity, refusal)): In<(Entity, Option<Text>)>,
+        config: Res<Config>,
+        operators: Option<Res<Operators>>,
+        mut list: ResMut<Self>,
+        query: Query<(&PlayerIdentity, &RemoteAddr, &ProtocolState, &PacketSender)>,
+    ) -> Result<()> {
+        let Ok((identity, addr, state, sender)) = query.get(entity) else {
+            return Ok(());
+        };
+        // the connection may have closed in the meantime
+        if sender.is_closed() {
+            return Ok(());
+        }
+
+        let bypass = operators.is_some_and(|operators| {
+            operators
+                .get(&identity.uuid)
+                .is_some_and(|op| op.bypasses_player_limit)
+        });
+        let full = list.len() >= config.server.max_players as usize;
+        let refusal = refusal.or_else(|| {
+            (full && !bypass && !list.contains(identity.uuid))
+                .then(|| Text::translate("multiplayer.disconnect.server_full", [] as [Text; 0]))
+        });
+        if let Some(reason) = refusal {
+            info!("disconnecting {} ({}): {reason}", identity.name, addr.0);
+            sender.kick(*state, reason)?;
+            return Ok(());
+        }
+
+        if let Some(old) = list.insert(identity, entity)
+            && let Ok((_, _, state, sender)) = query.get(old)
+        {
+            let reason = Text::translate("multiplayer.disconnect.duplicate_login", [] as [Text; 0]);
+            sender.kick(*state, reason)?;
+        }
+        Ok(())
+    }
+
+    /// Remove players from the list when they disconnect, and from the tab list of everyone else.
+    fn leave(
+        event: On<ConnectionClosed>,
+        mut list: ResMut<Self>,
+        query: Query<(&PlayerIdentity, &ProtocolState)>,
+        viewers: Query<Viewer>,
+    ) -> Result<()> {
+        let Ok((identity, state)) = query.get(event.entity) else {
+            return Ok(());
+        };
+        list.remove(identity.uuid, event.entity);
+        if *state != ProtocolState::Play {
+            return Ok(());
+        }
+
+        let packet = PlayerInfoRemove {
+            players: vec![identity.uuid],
+        }
+        .blocking_raw()?;
+        for (state, sender) in viewers.iter_many(list.iter().map(|(_, entity)| entity)) {
+            if *state == ProtocolState::Play {
+                let _ = sender.send(packet.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A player in the tab list.
+type Listed<'a> = (
+    &'a PlayerIdentity,
+    Option<&'a GameMode>,
+    Ref<'a, ProtocolState>,
+    &'a PacketSender,
+);
+
+/// Add players who have joined to everyone's tab list, and show them everyone already playing.
+fn announce_joins(config: Res<Config>, query: Query<Listed>) -> Result<()> {
+    let joined = |state: &Ref<ProtocolState>| state.is_changed() && **state == ProtocolState::Play;
+    if !query.iter().any(|(_, _, state, _)| joined(&state)) {
+        return Ok(());
+    }
+
+    let entry = |identity: &PlayerIdentity, game_mode: Option<&GameMode>| PlayerInfo {
+        uuid: identity.uuid,
+        profile: Some((identity.name.clone(), identity.properties.clone())),
+        game_mode: Some(game_mode.copied().unwrap_or_default()),
+        listed: Some(true),
+        // latency is not measured yet
+        latency: Some(0),
+        display_name: Some(None),
+    };
+    let (mut joiners, mut everyone) = (Vec::new(), Vec::new());
+    for (identity, game_mode, state, _) in query.iter() {
+        if *state == ProtocolState::Play {
+            everyone.push(entry(identity, game_mode));
+        }
+        if joined(&state) {
+            joiners.push(entry(identity, game_mode));
+        }
+    }
+    let joined_packet = PlayerInfoUpdate::new(joiners).blocking_raw()?;
+    let everyone_packet = PlayerInfoUpdate::new(everyone).blocking_raw()?;
+    let header_and_footer = header_and_footer(&config)
+        .map(TabList::blocking_raw)
+        .transpose()?;
+
+    for (_, _, state, sender) in query.iter() {
+        if *state != ProtocolState::Play {
+            continue;
+        }
+        if !joined(&state) {
+            let _ = sender.send(joined_packet.clone());
+            continue;
+        }
+        let _ = sender.send(everyone_packet.clone());
+        if let Some(packet) = &header_and_footer {
+            let _ = sender.send(packet.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Show everyone the new game modes of players whose game mode has changed.
+fn update_game_modes(
+    changed: Query<(&PlayerIdentity, &GameMode, Ref<ProtocolState>), Changed<GameMode>>,
+    viewers: Query<Viewer>,
+) -> Result<()> {
+    // players who just joined were sent with their game mode
+    let players: Vec<PlayerInfo> = changed
+        .iter()
+        .filter(|(_, _, state)| **state == ProtocolState::Play && !state.is_changed())
+        .map(|(identity, game_mode, _)| PlayerInfo {
+            uuid: identity.uuid,
+            game_mode: Some(*game_mode),
+            ..Default::default()
+        })
+        .collect();
+    if players.is_empty() {
+        return Ok(());
+    }
+
+    let packet = PlayerInfoUpdate::new(players).blocking_raw()?;
+    for (state, sender) in viewers.iter() {
+        if *state == ProtocolState::Play {
+            let _ = sender.send(packet.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Show everyone the tab list's new header and footer when the configuration changes.
+fn update_header_and_footer(
+    config: Res<Config>,
+    mut last: Local<Option<(String, String)>>,
+    viewers: Query<Viewer>,
+) -> Result<()> {
+    if !config.is_changed() {
+        return Ok(());
+    }
+    let tab_list = &config.tab_list;
+    let current = (tab_list.header.clone(), tab_list.footer.clone());
+    if last
+        .replace(current.clone())
+        .is_none_or(|last| last == current)
+    {
+        return Ok(());
+    }
+
+    // an empty header and footer hide the ones shown before
+    let packet = TabList {
+        header: current.0.as_str().into(),
+        footer: current.1.as_str().into(),
+    }
+    .blocking_raw()?;
+    for (state, sender) in viewers.iter() {
+        if *state == ProtocolState::Play {
+            let _ = sender.send(packet.clone());
+        }
+    }
+    Ok(())
+}
+
+/// The tab list's header and footer, if either is set.
+fn header_and_footer(config: &Config) -> Option<TabList> {
+    let tab_list = &config.tab_list;
+    if tab_list.header.is_empty() && tab_list.footer.is_empty() {
+        return None;
+    }
+    Some(TabList {
+        header: tab_list.header.as_str().into(),
+        footer: tab_list.footer.as_str().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_net::player::PlayerIdentity;
+    use bevy_ecs::world::World;
+
+    use super::PlayerList;
+
+    #[test]
+    fn test_player_list() {
+        let mut world = World::new();
+        let (first, second) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let identity = PlayerIdentity {
+            name: "Steve".to_string(),
+            uuid: 1,
+            properties: Vec::new(),
+        };
+
+        let mut list = PlayerList::default();
+        assert_eq!(list.insert(&identity, first), None);
+        assert_eq!(list.find("steve"), Some(first));
+        // logging in again replaces the old connection
+        assert_eq!(list.insert(&identity, second), Some(first));
+        list.remove(1, first);
+        assert_eq!(list.get(1), Some(second));
+        list.remove(1, second);
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use beacon_codec::decode::Decode;
+use beacon_config::{Config, ConfigSet};
+use beacon_net::{
+    packet::{PacketData, PacketEvent},
+    registry::PacketRegistry,
+};
+use bevy_ecs::{
+    prelude::*,
+    system::{IntoObserverSystem, ScheduleSystem},
+};
+use miette::{Report, Result};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    BannedIps, BannedPlayers, Operators, PlayerList, RateLimitMetrics, ServerHandle, TickManager,
+    TickMetrics, TickSet, Whitelist, WorldQueue, access,
+    command::{self, CommandDispatcher, SelectorPredicates},
+    listener::Listener,
+    management, player_list, schedule,
+    tick::TickStage,
+    world,
+};
+
+/// A piece of functionality that can be added to a [BeaconServer](crate::BeaconServer).
+///
+/// Plugins are built in the order they were added to the
+/// [BeaconServerBuilder](crate::BeaconServerBuilder), so a plugin can rely on the resources added
+/// by the plugins before it.
+pub trait BeaconPlugin: Send + Sync + 'static {
+    /// Add this plugin's systems, resources and observers to the server.
+    fn build(&self, app: &mut App) -> Result<()>;
+
+    /// The name of this plugin. Each plugin can only be added to a server once.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The ECS of a server that is being built, which plugins add to.
+pub struct App {
+    pub(crate) world: World,
+    pub(crate) schedule: Schedule,
+}
+
+impl App {
+    pub(crate) fn new() -> Self {
+        let mut world = World::new();
+        world.init_resource::<TickMetrics>();
+        world.init_resource::<TickManager>();
+        world.init_resource::<TickStage>();
+        world.init_resource::<RateLimitMetrics>();
+        world.init_resource::<WorldQueue>();
+        world.init_resource::<ServerHandle>();
+        world.init_resource::<CommandDispatcher>();
+        world.init_resource::<SelectorPredicates>();
+        world.init_resource::<Operators>();
+        world.init_resource::<Whitelist>();
+        world.init_resource::<BannedPlayers>();
+        world.init_resource::<BannedIps>();
+        world.init_resource::<PlayerList>();
+
+        let mut schedule = Schedule::default();
+        schedule::configure(&mut schedule);
+        management::configure(&mut world, &mut schedule);
+        schedule.add_systems(
+            WorldQueue::run_queued
+                .in_set(TickSet::PreTick)
+                .after(ConfigSet),
+        );
+
+        let mut app = Self { world, schedule };
+        world::configure(&mut app);
+        app
+    }
+
+    /// The server's world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// The server's world, mutably.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// The schedule that is run every tick.
+    pub fn schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    /// Add systems to a stage of the tick.
+    pub fn add_systems<M>(
+        &mut self,
+        set: TickSet,
+        systems: impl IntoScheduleConfigs<ScheduleSystem, M>,
+    ) -> &mut Self {
+        self.schedule.add_systems(systems.in_set(set));
+        self
+    }
+
+    /// Insert a resource, replacing any existing resource of the same type.
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    /// Initialize a resource with its default value, if it does not already exist.
+    pub fn init_resource<R: Resource + FromWorld>(&mut self) -> &mut Self {
+        self.world.init_resource::<R>();
+        self
+    }
+
+    /// Add a global observer.
+    pub fn add_observer<E: Event, B: Bundle, M>(
+        &mut self,
+        observer: impl IntoObserverSystem<E, B, M>,
+    ) -> &mut Self {
+        self.world.add_observer(observer);
+        self
+    }
+
+    /// Add a handler for a serverbound packet, registering the packet if needed. Handlers run
+    /// alongside any other handlers for the same packet.
+    pub fn add_packet_handler<P, B: Bundle, M>(
+        &mut self,
+        handler: impl IntoObserverSystem<PacketEvent<P>, B, M>,
+    ) -> &mut Self
+    where
+        P: PacketData + Decode + Send + Sync + 'static,
+    {
+        PacketRegistry::add_handler(&mut self.world, handler);
+        self
+    }
+
+    /// Replace every existing handler for a serverbound packet, including the built-in ones.
+    pub fn replace_packet_handlers<P, B: Bundle, M>(
+        &mut self,
+        handler: impl IntoObserverSystem<PacketEvent<P>, B, M>,
+    ) -> &mut Self
+    where
+        P: PacketData + Decode + Send + Sync + 'static,
+    {
+        PacketRegistry::replace_handlers(&mut self.world, handler);
+        self
+    }
+
+    /// Load a section of the configuration file as a resource, falling back to its default value
+    /// if the section is missing. The resource is reloaded whenever the configuration is.
+    ///
+    /// Requires the [Config] resource, which is added by [ConfigPlugin].
+    pub fn add_config_section<T>(&mut self, key: &'static str) -> Result<&mut Self>
+    where
+        T: Resource + DeserializeOwned + Default,
+    {
+        let config = self.world.get_resource::<Config>().ok_or_else(|| {
+            miette::miette!(
+                help = "add the ConfigPlugin before any plugin that uses configuration",
+                "no configuration has been loaded"
+            )
+        })?;
+        let section: T = config.section(key)?.unwrap_or_default();
+        self.world.insert_resource(section);
+
+        let reload = move |config: Res<Config>, mut section: ResMut<T>| {
+            if !config.is_changed() || config.is_added() {
+                return;
+            }
+            match config.section(key) {
+                Ok(new) => *section = new.unwrap_or_default(),
+                Err(e) => error!("{:?}", Report::new(e)),
+            }
+        };
+        self.add_systems(TickSet::PreTick, reload.after(ConfigSet));
+
+        Ok(self)
+    }
+}
+
+/// Loads the configuration file into the [Config] resource, and reloads it when the file changes.
+pub struct ConfigPlugin {
+    path: PathBuf,
+}
+
+impl ConfigPlugin {
+    /// Load the configuration from the given path.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl BeaconPlugin for ConfigPlugin {
+    fn build(&self, app: &mut App) -> Result<()> {
+        beacon_config::ecs(&mut app.world, &mut app.schedule, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Keeps the operator list, whitelist and ban lists in sync with vanilla's `ops.json`,
+/// `whitelist.json`, `banned-players.json` and `banned-ips.json`, and refuses players who are not
+/// allowed to join.
+///
+/// Requires the [Config] resource, which is added by [ConfigPlugin].
+pub struct AccessPlugin {
+    dir: PathBuf,
+}
+
+impl AccessPlugin {
+    /// Keep the lists in the given directory, creating any that are missing.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl BeaconPlugin for AccessPlugin {
+    fn build(&self, app: &mut App) -> Result<()> {
+        access::configure(app, &self.dir)?;
+        Ok(())
+    }
+}
+
+/// Accepts connections on the server's address and handles the packets sent by them.
+///
+/// Requires the [Config] resource, which is added by [ConfigPlugin].
+pub struct NetworkPlugin;
+
+impl BeaconPlugin for NetworkPlugin {
+    fn build(&self, app: &mut App) -> Result<()> {
+        Listener::configure(app)?;
+        beacon_net::ecs(&mut app.world, &mut app.schedule);
+        app.add_systems(TickSet::NetworkOut, TickManager::sync);
+        command::configure_network(app);
+        world::configure_network(app);
+        player_list::configure_network(app);
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+//! See: <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use beacon_config::{NetworkConfig, ProxyProtocol};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::peek::PeekReader;
+
+const V1_SIGNATURE: &[u8] = b"PROXY ";
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The longest a v1 header can be, including the trailing CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// Error that can occur while reading a PROXY protocol header.
+#[derive(Debug, Error)]
+pub(crate) enum ProxyError {
+    /// An I/O error occurred while reading the header.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The header was malformed.
+    #[error("invalid PROXY protocol header")]
+    Invalid,
+
+    /// The header was required, but not sent.
+    #[error("missing PROXY protocol header")]
+    Missing,
+
+    /// The connection did not come from a trusted proxy.
+    #[error("connection is not from a trusted proxy")]
+    Untrusted,
+}
+
+/// Find the real address of a client, reading the PROXY protocol header sent by a load balancer
+/// if there is one.
+pub(crate) async fn resolve<R: AsyncRead + Unpin>(
+    reader: &mut PeekReader<R>,
+    peer: SocketAddr,
+    config: &NetworkConfig,
+) -> Result<SocketAddr, ProxyError> {
+    let required = config.proxy_protocol == ProxyProtocol::Required;
+    if !forwards(peer.ip(), config) {
+        return if required {
+            Err(ProxyError::Untrusted)
+        } else {
+            Ok(peer)
+        };
+    }
+
+    match read_header(reader).await? {
+        Some(addr) => Ok(addr.unwrap_or(peer)),
+        None if required => Err(ProxyError::Missing),
+        None => Ok(peer),
+    }
+}
+
+/// Whether a connection from `peer` may send a PROXY protocol header with the client's real
+/// address. If not, the peer's address is the client's.
+pub(crate) fn forwards(peer: IpAddr, config: &NetworkConfig) -> bool {
+    config.proxy_protocol != ProxyProtocol::Disabled
+        && config.trusted_proxies.iter().any(|net| net.contains(&peer))
+}
+
+/// Read a PROXY protocol header, if the connection starts with one.
+///
+/// Returns `None` if there is no header, or `Some(None)` if there is a header but it does not
+/// contain the client's address, e.g. for health checks.
+async fn read_header<R: AsyncRead + Unpin>(
+    reader: &mut PeekReader<R>,
+) -> Result<Option<Option<SocketAddr>>, ProxyError> {
+    if starts_with(reader, V2_SIGNATURE).await? {
+        read_v2(reader).await.map(Some)
+    } else if starts_with(reader, V1_SIGNATURE).await? {
+        read_v1(reader).await.map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check if the connection starts with `signature`, without consuming anything.
+///
+/// Bytes are peeked one at a time, so that clients that send fewer bytes than the signature (such
+/// as the legacy server list ping) are not waited on.
+async fn starts_with<R: AsyncRead + Unpin>(
+    reader: &mut PeekReader<R>,
+    signature: &[u8],
+) -> io::Result<bool> {
+    for len in 1..=signature.len() {
+        if reader.peek_exact(len).await?[len - 1] != signature[len - 1] {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Read a human-readable (v1) header, e.g. `PROXY TCP4 192.0.2.1 192.0.2.2 56324 25565\r\n`.
+async fn read_v1<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<SocketAddr>, ProxyError> {
+    let mut line = Vec::with_capacity(V1_MAX_LEN);
+    while !line.ends_with(b"\r\n") {
+        if line.len() == V1_MAX_LEN {
+            return Err(ProxyError::Invalid);
+        }
+        line.push(reader.read_u8().await?);
+    }
+
+    let line = std::str::from_utf8(&line[..line.len() - 2]).map_err(|_| ProxyError::Invalid)?;
+    let mut parts = line.split(' ').skip(1);
+    match parts.next() {
+        Some("TCP4" | "TCP6") => {}
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(ProxyError::Invalid),
+    }
+
+    let mut next = || parts.next().ok_or(ProxyError::Invalid);
+    let ip: IpAddr = next()?.parse().map_err(|_| ProxyError::Invalid)?;
+    let _destination = next()?;
+    let port: u16 = next()?.parse().map_err(|_| ProxyError::Invalid)?;
+    Ok(Some((ip, port).into()))
+}
+
+/// Read a binary (v2) header.
+async fn read_v2<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<SocketAddr>, ProxyError> {
+    let mut signature = [0; V2_SIGNATURE.len()];
+    reader.read_exact(&mut signature).await?;
+    let version_command = reader.read_u8().await?;
+    let family = reader.read_u8().await?;
+    let len = reader.read_u16().await? as usize;
+
+    // read the rest of the header, including any TLVs we don't use
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await?;
+
+    if version_command >> 4 != 2 {
+        return Err(ProxyError::Invalid);
+    }
+    match version_command & 0x0F {
+        // LOCAL: the proxy's own connection, e.g. a health check
+        0x0 => return Ok(None),
+        // PROXY
+        0x1 => {}
+        _ => return Err(ProxyError::Invalid),
+    }
+
+    let mut body = body.as_slice();
+    let ip = match family >> 4 {
+        // AF_INET
+        0x1 if body.len() >= 12 => IpAddr::V4(Ipv4Addr::from(body.read_u32().await?)),
+        // AF_INET6
+        0x2 if body.len() >= 36 => IpAddr::V6(Ipv6Addr::from(body.read_u128().await?)),
+        // AF_UNSPEC or AF_UNIX
+        0x0 | 0x3 => return Ok(None),
+        _ => return Err(ProxyError::Invalid),
+    };
+    // skip the destination address
+    let destination_len = if ip.is_ipv4() { 4 } else { 16 };
+    body = &body[destination_len..];
+    let port = body.read_u16().await?;
+
+    Ok(Some((ip, port).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use beacon_config::{Config, NetworkConfig, ProxyProtocol};
+
+    use super::{ProxyError, read_v1, read_v2, resolve};
+    use crate::peek::PeekReader;
+
+    const HEADER: &[u8] = b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 25565\r\n\x10";
+
+    async fn resolve_from(
+        peer: &str,
+        data: &[u8],
+        network: &NetworkConfig,
+    ) -> Result<SocketAddr, ProxyError> {
+        let mut reader = PeekReader::new(data);
+        resolve(&mut reader, peer.parse().unwrap(), network).await
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let mut network = Config::load("missing.toml").unwrap().network;
+        network.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+        let (proxy, client) = ("10.0.0.1:40000", "192.0.2.9:40000");
+
+        // untrusted peers cannot connect if a header is required, and their header is otherwise
+        // left unread
+        network.proxy_protocol = ProxyProtocol::Required;
+        let res = resolve_from(client, HEADER, &network).await;
+        assert!(matches!(res, Err(ProxyError::Untrusted)));
+        network.proxy_protocol = ProxyProtocol::Optional;
+        let res = resolve_from(client, HEADER, &network).await;
+        assert_eq!(res.unwrap(), client.parse().unwrap());
+
+        // trusted peers give the client's address in their header, if they send one
+        let res = resolve_from(proxy, HEADER, &network).await;
+        assert_eq!(res.unwrap(), "192.0.2.1:56324".parse().unwrap());
+        let res = resolve_from(proxy, b"\x10\x00", &network).await;
+        assert_eq!(res.unwrap(), proxy.parse().unwrap());
+        network.proxy_protocol = ProxyProtocol::Required;
+        let res = resolve_from(proxy, b"\x10\x00", &network).await;
+        assert!(matches!(res, Err(ProxyError::Missing)));
+    }
+
+    #[tokio::test]
+    async fn test_v1() {
+        let data = b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 25565\r\n\x10";
+        let mut reader = &data[..];
+        let addr = read_v1(&mut reader).await.unwrap();
+        assert_eq!(addr, Some("192.0.2.1:56324".parse::<SocketAddr>().unwrap()));
+        // the rest of the stream is left unread
+        assert_eq!(reader, b"\x10");
+
+        let data = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 25565\r\n";
+        let addr = read_v1(&mut &data[..]).await.unwrap();
+        assert_eq!(addr, Some("[2001:db8::1]:56324".parse().unwrap()));
+
+        let data = b"PROXY UNKNOWN\r\n";
+        assert_eq!(read_v1(&mut &data[..]).await.unwrap(), None);
+
+        let data = b"PROXY TCP4 not.an.ip 192.0.2.2 56324 25565\r\n";
+        assert!(read_v1(&mut &data[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_v2() {
+        #[rustfmt::skip]
+        let data = [
+            // signature
+            0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+            // version 2, PROXY
+            0x21,
+            // TCP over IPv4
+            0x11,
+            // length
+            0x00, 0x0C,
+            // source address, destination address
+            192, 0, 2, 1, 192, 0, 2, 2,
+            // source port, destination port
+            0xDC, 0x04, 0x63, 0xDD,
+            // start of the handshake
+            0x10,
+        ];
+        let mut reader = &data[..];
+        let addr = read_v2(&mut reader).await.unwrap();
+        assert_eq!(addr, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(reader, [0x10]);
+
+        // LOCAL command
+        let mut local = data;
+        local[12] = 0x20;
+        assert_eq!(read_v2(&mut &local[..]).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,403 @@
+//! The GameSpy4 query protocol, which server lists use to find the server's status and online
+//! players over UDP.
+//!
+//! See: <https://minecraft.wiki/w/Query>
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    mem,
+    net::SocketAddr,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
+use beacon_data::LATEST_SUPPORTED_VERSION;
+use beacon_net::player::{OnlinePlayers, PlayerIdentity};
+use bevy_ecs::prelude::*;
+use miette::{IntoDiagnostic, Result};
+use tokio::net::UdpSocket;
+use tokio_util::sync::CancellationToken;
+
+use crate::{App, RateLimitMetrics, TickSet, limit::QueryThrottle};
+
+/// The bytes every request starts with.
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+
+/// Requests a challenge token, which must be sent with every stat request.
+const HANDSHAKE: u8 = 9;
+
+/// Requests a basic stat, or a full stat if the token is followed by four bytes of padding.
+const STAT: u8 = 0;
+
+/// The padding before the key-value section of a full stat.
+const KEY_VALUE_PADDING: &[u8] = b"splitnum\0\x80\0";
+
+/// The padding before the player section of a full stat.
+const PLAYER_PADDING: &[u8] = b"\x01player_\0\0";
+
+/// How long a challenge token is issued for. Tokens are accepted for up to twice as long, so that
+/// one issued just before a rotation is still valid.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+
+/// The size of the receive buffer, larger than any valid request.
+const BUFFER_SIZE: usize = 64;
+
+/// The game type reported to queries, like vanilla.
+const GAME_TYPE: &str = "SMP";
+
+/// The game ID reported to queries, like vanilla.
+const GAME_ID: &str = "MINECRAFT";
+
+/// The name of the main world.
+const MAP: &str = "world";
+
+/// Answers GameSpy4 queries over UDP, alongside the server's TCP listener.
+pub(crate) struct QueryServer {
+    socket: UdpSocket,
+    stats: QueryStats,
+    challenges: Challenges,
+    throttle: QueryThrottle,
+    metrics: RateLimitMetrics,
+}
+
+impl QueryServer {
+    /// Bind the query listener if it is enabled, and keep its responses up to date every tick.
+    pub(crate) async fn bind(app: &mut App, plugins: &[&str]) -> Result<Option<Self>> {
+        let config = app.world.resource::<Config>();
+        if !config.query.enable {
+            return Ok(None);
+        }
+        let addr: SocketAddr = (config.server.ip, config.query.port).into();
+        let socket = UdpSocket::bind(addr).await.into_diagnostic()?;
+        info!("query listening on {}", addr);
+
+        let stats = QueryStats::new(plugins);
+        app.insert_resource(stats.clone());
+        app.add_systems(TickSet::PostTick, QueryStats::update);
+
+        Ok(Some(Self::new(
+            socket,
+            stats,
+            app.world.resource::<RateLimitMetrics>().clone(),
+        )))
+    }
+
+    fn new(socket: UdpSocket, stats: QueryStats, metrics: RateLimitMetrics) -> Self {
+        Self {
+            socket,
+            stats,
+            challenges: Challenges::new(Instant::now()),
+            throttle: QueryThrottle::default(),
+            metrics,
+        }
+    }
+
+    /// Answer queries until the server shuts down.
+    pub(crate) async fn run(mut self, cancel: CancellationToken) {
+        let mut buf = [0; BUFFER_SIZE];
+        loop {
+            let (len, addr) = tokio::select! {
+                res = self.socket.recv_from(&mut buf) => match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        debug!(%err, "failed to receive query");
+                        continue;
+                    }
+                },
+                _ = cancel.cancelled() => break,
+            };
+
+            if let Some(response) = self.respond(&buf[..len], addr)
+                && let Err(err) = self.socket.send_to(&response, addr).await
+            {
+                debug!(%addr, %err, "failed to answer query");
+            }
+        }
+    }
+
+    /// The response to a request, or `None` if it should be ignored.
+    fn respond(&mut self, request: &[u8], addr: SocketAddr) -> Option<Vec<u8>> {
+        let ([magic @ .., kind, s0, s1, s2, s3], payload) = request.split_first_chunk::<7>()?;
+        if *magic != MAGIC {
+            return None;
+        }
+        let rate_limit = self.stats.read(|stats| stats.rate_limit);
+        if !self.throttle.allow(addr.ip(), rate_limit, &self.metrics) {
+            return None;
+        }
+
+        // the response echoes the type and session ID
+        let mut response = vec![*kind, *s0, *s1, *s2, *s3];
+        let now = Instant::now();
+        match *kind {
+            HANDSHAKE if payload.is_empty() => {
+                let token = self.challenges.issue(addr, now);
+                put_str(&mut response, &token.to_string());
+            }
+            STAT => {
+                let (token, padding) = payload.split_first_chunk()?;
+                let full = match padding.len() {
+                    0 => false,
+                    4 => true,
+                    _ => return None,
+                };
+                if !self
+                    .challenges
+                    .verify(addr, i32::from_be_bytes(*token), now)
+                {
+                    return None;
+                }
+
+                self.stats.read(|stats| match full {
+                    false => response.extend(&stats.basic),
+                    true => response.extend(&stats.full),
+                });
+            }
+            _ => return None,
+        }
+        Some(response)
+    }
+}
+
+/// The encoded stat responses, which the query listener reads from outside the ECS. They are
+/// rebuilt whenever the configuration or the online players change.
+#[derive(Resource, Clone)]
+pub(crate) struct QueryStats {
+    stats: Arc<RwLock<Stats>>,
+    plugins: String,
+}
+
+#[derive(Default)]
+struct Stats {
+    basic: Vec<u8>,
+    full: Vec<u8>,
+    rate_limit: u32,
+}
+
+impl QueryStats {
+    fn new(plugins: &[&str]) -> Self {
+        Self {
+            stats: Arc::default(),
+            plugins: format!(
+                "Beacon {}: {}",
+                env!("CARGO_PKG_VERSION"),
+                plugins.join("; ")
+            ),
+        }
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Stats) -> T) -> T {
+        f(&self.stats.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Rebuild the stat responses if anything in them has changed.
+    fn update(
+        stats: Res<Self>,
+        config: Res<Config>,
+        players: OnlinePlayers,
+        // players are counted once they reach the play state
+        joined: Query<(), (With<PlayerIdentity>, Changed<ProtocolState>)>,
+        mut left: RemovedComponents<PlayerIdentity>,
+    ) {
+        let left = left.read().count() > 0;
+        if !config.is_changed() && joined.is_empty() && !left {
+            return;
+        }
+
+        let server = &config.server;
+        let names: Vec<_> = players.iter().map(|player| player.name.as_str()).collect();
+        let addr = (server.ip, server.port).into();
+        let (basic, full) = stats.encode(&server.motd, server.max_players, addr, &names);
+        *stats.stats.write().unwrap_or_else(PoisonError::into_inner) = Stats {
+            basic,
+            full,
+            rate_limit: config.query.rate_limit,
+        };
+    }
+
+    /// Encode the basic and full stat responses.
+    fn encode(
+        &self,
+        motd: &str,
+        max_players: u32,
+        addr: SocketAddr,
+        players: &[&str],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let (ip, port) = (addr.ip().to_string(), addr.port());
+        let online = players.len().to_string();
+        let max_players = max_players.to_string();
+
+        let mut basic = Vec::new();
+        for value in [motd, GAME_TYPE, MAP, &online, &max_players] {
+            put_str(&mut basic, value);
+        }
+        basic.extend(port.to_le_bytes());
+        put_str(&mut basic, &ip);
+
+        let mut full = KEY_VALUE_PADDING.to_vec();
+        let version = LATEST_SUPPORTED_VERSION.to_string();
+        for (key, value) in [
+            ("hostname", motd),
+            ("gametype", GAME_TYPE),
+            ("game_id", GAME_ID),
+            ("version", &version),
+            ("plugins", &self.plugins),
+            ("map", MAP),
+            ("numplayers", &online),
+            ("maxplayers", &max_players),
+            ("hostport", &port.to_string()),
+            ("hostip", &ip),
+        ] {
+            put_str(&mut full, key);
+            put_str(&mut full, value);
+        }
+        full.push(0);
+        full.extend(PLAYER_PADDING);
+        for player in players {
+            put_str(&mut full, player);
+        }
+        full.push(0);
+
+        (basic, full)
+    }
+}
+
+/// Append a null-terminated string, dropping any null bytes in it.
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend(s.bytes().filter(|&b| b != 0));
+    buf.push(0);
+}
+
+/// Issues and verifies challenge tokens without storing them, by hashing the client's address with
+/// keys that are rotated regularly.
+struct Challenges {
+    current: RandomState,
+    previous: RandomState,
+    rotated: Instant,
+}
+
+impl Challenges {
+    fn new(now: Instant) -> Self {
+        Self {
+            current: RandomState::new(),
+            previous: RandomState::new(),
+            rotated: now,
+        }
+    }
+
+    /// Issue a challenge token to a client.
+    fn issue(&mut self, addr: SocketAddr, now: Instant) -> i32 {
+        self.rotate(now);
+        Self::token(&self.current, addr)
+    }
+
+    /// Check a challenge token sent by a client.
+    fn verify(&mut self, addr: SocketAddr, token: i32, now: Instant) -> bool {
+        self.rotate(now);
+        token == Self::token(&self.current, addr) || token == Self::token(&self.previous, addr)
+    }
+
+    /// Replace the keys once they have been issued for long enough.
+    fn rotate(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.rotated);
+        if elapsed < CHALLENGE_LIFETIME {
+            return;
+        }
+
+        let expired = mem::replace(&mut self.current, RandomState::new());
+        self.previous = match elapsed < 2 * CHALLENGE_LIFETIME {
+            true => expired,
+            false => RandomState::new(),
+        };
+        self.rotated = now;
+    }
+
+    /// The token for an address, which is kept below 2^24 like vanilla's.
+    fn token(keys: &RandomState, addr: SocketAddr) -> i32 {
+        (keys.hash_one(addr) & 0xFF_FFFF) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tokio::{net::UdpSocket, time::timeout};
+    use tokio_util::sync::CancellationToken;
+
+    use super::{CHALLENGE_LIFETIME, Challenges, QueryServer, QueryStats, Stats};
+    use crate::RateLimitMetrics;
+
+    #[test]
+    fn test_challenges() {
+        let start = Instant::now();
+        let mut challenges = Challenges::new(start);
+        let addr = "203.0.113.7:25565".parse().unwrap();
+        let token = challenges.issue(addr, start);
+        assert!(challenges.verify(addr, token, start));
+        assert!(!challenges.verify("203.0.113.8:25565".parse().unwrap(), token, start));
+
+        // tokens survive one rotation, but not two
+        assert!(challenges.verify(addr, token, start + CHALLENGE_LIFETIME));
+        let later = start + 2 * CHALLENGE_LIFETIME + Duration::from_secs(1);
+        assert!(!challenges.verify(addr, token, later));
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let stats = QueryStats::new(&["Example"]);
+        let (basic, full) = stats.encode("A Beacon Server", 20, addr, &["Notch", "jeb_"]);
+        *stats.stats.write().unwrap() = Stats {
+            basic,
+            full,
+            rate_limit: 0,
+        };
+        let cancel = CancellationToken::new();
+        let server = QueryServer::new(socket, stats, RateLimitMetrics::default());
+        tokio::spawn(server.run(cancel.clone()));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.connect(addr).await.unwrap();
+        let request = async |request: &[u8]| {
+            client.send(request).await.unwrap();
+            let mut buf = [0; 1024];
+            let len = timeout(Duration::from_millis(100), client.recv(&mut buf))
+                .await
+                .ok()?
+                .unwrap();
+            Some(buf[..len].to_vec())
+        };
+
+        let response = request(&[0xFE, 0xFD, 9, 0, 0, 0, 1]).await.unwrap();
+        assert_eq!(response[..5], [9, 0, 0, 0, 1]);
+        let token: i32 = std::str::from_utf8(&response[5..response.len() - 1])
+            .unwrap()
+            .parse()
+            .unwrap();
+        let token = token.to_be_bytes();
+
+        let mut stat = vec![0xFE, 0xFD, 0, 0, 0, 0, 2];
+        stat.extend(token);
+        let mut expected = vec![0, 0, 0, 0, 2];
+        expected.extend(b"A Beacon Server\0SMP\0world\x002\x0020\0");
+        expected.extend(addr.port().to_le_bytes());
+        expected.extend(b"127.0.0.1\0");
+        assert_eq!(request(&stat).await.unwrap(), expected);
+
+        stat.extend([0; 4]);
+        let response = request(&stat).await.unwrap();
+        let text = String::from_utf8_lossy(&response);
+        assert!(text.contains("\0plugins\0Beacon 0.0.0: Example\0"));
+        assert!(text.ends_with("\0\0\u{1}player_\0\0Notch\0jeb_\0\0"));
+
+        // stats are only sent to clients with a valid token
+        stat[7] ^= 0xFF;
+        assert_eq!(request(&stat).await, None);
+
+        cancel.cancel();
+    }
+}
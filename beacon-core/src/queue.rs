@@ -0,0 +1,43 @@
+use bevy_ecs::prelude::*;
+
+/// A task sent from outside the ECS, waiting to be run.
+type Task = Box<dyn FnOnce(&mut World) + Send>;
+
+/// Tasks sent from outside the ECS, such as commands sent over RCON, which are run in the world at
+/// the start of the next tick.
+#[derive(Resource, Clone)]
+pub struct WorldQueue {
+    tx: flume::Sender<Task>,
+    rx: flume::Receiver<Task>,
+}
+
+impl Default for WorldQueue {
+    fn default() -> Self {
+        let (tx, rx) = flume::unbounded();
+        Self { tx, rx }
+    }
+}
+
+impl WorldQueue {
+    /// Queue a task, and wait for its result. Returns `None` if the server shut down first.
+    pub async fn run<T, F>(&self, task: F) -> Option<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut World) -> T + Send + 'static,
+    {
+        let (tx, rx) = flume::bounded(1);
+        let task: Task = Box::new(move |world| {
+            let _ = tx.send(task(world));
+        });
+        self.tx.send_async(task).await.ok()?;
+        rx.recv_async().await.ok()
+    }
+
+    /// Run every queued task.
+    pub(crate) fn run_queued(world: &mut World) {
+        let rx = world.resource::<Self>().rx.clone();
+        for task in rx.try_iter() {
+            task(world);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! The Source RCON protocol, which remote consoles use to run commands over TCP.
+//!
+//! See: <https://minecraft.wiki/w/RCON>
+
+use std::{
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use beacon_config::{Config, RconConfig};
+use bevy_ecs::world::World;
+use miette::{IntoDiagnostic, Result};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    App, RateLimitMetrics,
+    command::{self, CommandSender},
+    limit::LoginThrottle,
+    queue::WorldQueue,
+};
+
+/// Sent by the server with the output of a command.
+const RESPONSE_VALUE: i32 = 0;
+
+/// Sent by the client to run a command, and by the server in response to a login.
+const EXEC_COMMAND: i32 = 2;
+
+/// Sent by the server in response to a login.
+const AUTH_RESPONSE: i32 = 2;
+
+/// Sent by the client to log in with the password.
+const AUTH: i32 = 3;
+
+/// The request ID sent in response to a failed login.
+const AUTH_FAILED: i32 = -1;
+
+/// The longest packet a client may send, like vanilla.
+const MAX_REQUEST_LENGTH: i32 = 1460;
+
+/// The longest body of a response packet. Longer output is split over several packets.
+const MAX_RESPONSE_BODY: usize = 4096;
+
+/// A packet sent between an RCON client and the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: String,
+}
+
+impl Packet {
+    /// Read a packet, which is prefixed with its length and ends with two null bytes.
+    async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+        let len = reader.read_i32_le().await?;
+        if !(10..=MAX_REQUEST_LENGTH).contains(&len) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "invalid packet length",
+            ));
+        }
+        let id = reader.read_i32_le().await?;
+        let kind = reader.read_i32_le().await?;
+
+        let mut body = vec![0; len as usize - 8];
+        reader.read_exact(&mut body).await?;
+        if !body.ends_with(&[0, 0]) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "packet is not terminated",
+            ));
+        }
+        body.truncate(body.len() - 2);
+        let body = String::from_utf8_lossy(&body).into_owned();
+        Ok(Self { id, kind, body })
+    }
+
+    /// Encode the packet, splitting a long body over several packets with the same ID.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut body = self.body.as_str();
+        loop {
+            let mut end = body.len().min(MAX_RESPONSE_BODY);
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (chunk, rest) = body.split_at(end);
+
+            buf.extend((chunk.len() as i32 + 10).to_le_bytes());
+            buf.extend(self.id.to_le_bytes());
+            buf.extend(self.kind.to_le_bytes());
+            buf.extend(chunk.as_bytes());
+            buf.extend([0, 0]);
+
+            body = rest;
+            if body.is_empty() {
+                return buf;
+            }
+        }
+    }
+}
+
+/// State shared between every RCON connection.
+struct Shared {
+    config: RconConfig,
+    queue: WorldQueue,
+    throttle: Mutex<LoginThrottle>,
+    metrics: RateLimitMetrics,
+}
+
+/// Accepts RCON connections, alongside the server's TCP listener.
+pub(crate) struct RconServer {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+impl RconServer {
+    /// Bind the RCON listener if it is enabled and a password is set.
+    pub(crate) async fn bind(app: &mut App) -> Result<Option<Self>> {
+        let config = app.world.resource::<Config>();
+        if !config.rcon.enable {
+            return Ok(None);
+        }
+        if config.rcon.password.is_empty() {
+            warn!("rcon is enabled, but will not be started until a password is set");
+            return Ok(None);
+        }
+        let addr: SocketAddr = (config.server.ip, config.rcon.port).into();
+        let listener = TcpListener::bind(addr).await.into_diagnostic()?;
+        info!("rcon listening on {}", addr);
+
+        let shared = Shared {
+            config: config.rcon.clone(),
+            queue: app.world.resource::<WorldQueue>().clone(),
+            throttle: Mutex::default(),
+            metrics: app.world.resource::<RateLimitMetrics>().clone(),
+        };
+        Ok(Some(Self {
+            listener,
+            shared: Arc::new(shared),
+        }))
+    }
+
+    /// Accept connections until the server shuts down.
+    pub(crate) async fn run(self, cancel: CancellationToken) {
+        loop {
+            let (stream, addr) = tokio::select! {
+                res = self.listener.accept() => match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        debug!(%err, "failed to accept rcon connection");
+                        continue;
+                    }
+                },
+                _ = cancel.cancelled() => break,
+            };
+
+            let shared = self.shared.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                debug!(%addr, "rcon connection established");
+                let res = tokio::select! {
+                    res = handle(stream, addr, &shared) => res,
+                    _ = cancel.cancelled() => Ok(()),
+                };
+                match res {
+                    Err(err) if err.kind() != ErrorKind::UnexpectedEof => {
+                        debug!(%addr, %err, "rcon connection closed");
+                    }
+                    _ => debug!(%addr, "rcon connection closed"),
+                }
+            });
+        }
+    }
+}
+
+/// Answer a client's packets until it disconnects, or is refused.
+async fn handle(mut stream: TcpStream, addr: SocketAddr, shared: &Shared) -> io::Result<()> {
+    let mut authenticated = false;
+    loop {
+        let packet = Packet::read(&mut stream).await?;
+        let response = match packet.kind {
+            AUTH => {
+                let limit = shared.config.login_rate_limit;
+                let mut throttle = shared
+                    .throttle
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if !throttle.allow(addr.ip(), limit, &shared.metrics) {
+                    warn!(%addr, "refusing rcon login, too many failed attempts");
+                    return Ok(());
+                }
+
+                authenticated = constant_time_eq(&packet.body, &shared.config.password);
+                if authenticated {
+                    info!(%addr, "rcon client logged in");
+                    Packet {
+                        kind: AUTH_RESPONSE,
+                        body: String::new(),
+                        ..packet
+                    }
+                } else {
+                    warn!(%addr, "rcon login failed, wrong password");
+                    throttle.fail(addr.ip(), limit);
+                    Packet {
+                        id: AUTH_FAILED,
+                        kind: AUTH_RESPONSE,
+                        body: String::new(),
+                    }
+                }
+            }
+            _ if !authenticated => Packet {
+                id: AUTH_FAILED,
+                kind: AUTH_RESPONSE,
+                body: String::new(),
+            },
+            EXEC_COMMAND => {
+                info!(%addr, command = packet.body, "rcon client ran a command");
+                let sender = CommandSender::Rcon(addr);
+                let command = packet.body;
+                let run = move |world: &mut World| command::run_command(world, sender, &command);
+                let Some(output) = shared.queue.run(run).await else {
+                    return Ok(());
+                };
+                Packet {
+                    id: packet.id,
+                    kind: RESPONSE_VALUE,
+                    body: output
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }
+            // clients send an empty response after a command, to find the end of its output
+            RESPONSE_VALUE => Packet {
+                body: String::new(),
+                ..packet
+            },
+            kind => Packet {
+                id: packet.id,
+                kind: RESPONSE_VALUE,
+                body: format!("Unknown request {kind:x}"),
+            },
+        };
+        stream.write_all(&response.encode()).await?;
+    }
+}
+
+/// Compare two strings in time that only depends on their lengths, so the password cannot be
+/// guessed from how long a login takes.
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AUTH, MAX_RESPONSE_BODY, Packet, RESPONSE_VALUE};
+
+    #[tokio::test]
+    async fn test_packet() {
+        let packet = Packet {
+            id: 7,
+            kind: AUTH,
+            body: "password".into(),
+        };
+        let buf = packet.encode();
+        assert_eq!(buf[..4], 18i32.to_le_bytes());
+        assert_eq!(buf[4..8], 7i32.to_le_bytes());
+        assert_eq!(buf[8..12], 3i32.to_le_bytes());
+        assert_eq!(&buf[12..], b"password\0\0");
+        assert_eq!(Packet::read(&mut &buf[..]).await.unwrap(), packet);
+
+        // unterminated
+        let mut bad = buf.clone();
+        bad[21] = b'!';
+        assert!(Packet::read(&mut &bad[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_split() {
+        let packet = Packet {
+            id: 1,
+            kind: RESPONSE_VALUE,
+            body: "é".repeat(MAX_RESPONSE_BODY),
+        };
+        let buf = packet.encode();
+
+        // split into packets of at most 4096 bytes, without splitting characters
+        let mut reader = &buf[..];
+        let mut body = String::new();
+        let mut lengths = Vec::new();
+        while !reader.is_empty() {
+            let len = i32::from_le_bytes(reader[..4].try_into().unwrap()) as usize;
+            let chunk = &reader[12..4 + len - 2];
+            lengths.push(chunk.len());
+            body.push_str(std::str::from_utf8(chunk).unwrap());
+            reader = &reader[4 + len..];
+        }
+        assert_eq!(lengths, [4096, 4096]);
+        assert_eq!(body, packet.body);
+    }
+}
@@ -0,0 +1,76 @@
+use beacon_config::ConfigSet;
+use beacon_net::NetworkSet;
+use bevy_ecs::{prelude::*, schedule::InternedSystemSet};
+
+use crate::{TickManager, tick::TickStage};
+
+/// The stages of a server tick.
+///
+/// Every tick runs these sets in order:
+/// 1. [PreTick](TickSet::PreTick) - housekeeping, such as reloading the configuration.
+/// 2. [NetworkIn](TickSet::NetworkIn) - incoming packets are read and their handlers are run.
+///    Any commands queued by packet handlers are applied before [GameLogic](TickSet::GameLogic).
+/// 3. [GameLogic](TickSet::GameLogic) - advances the game. Skipped while the game is frozen by the
+///    [TickManager], unless it is stepping.
+/// 4. [NetworkOut](TickSet::NetworkOut) - systems that queue packets for clients.
+/// 5. [PostTick](TickSet::PostTick) - cleanup after the tick.
+///
+/// Once every set has run, all packets queued during the tick are flushed to their connections
+/// together, so a client sees each tick's packets at once.
+///
+/// Plugins should place their systems in one of these sets, and order them relative to each other
+/// within it if needed.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TickSet {
+    /// Runs first in every tick.
+    PreTick,
+    /// Reads incoming packets.
+    NetworkIn,
+    /// Advances the game.
+    GameLogic,
+    /// Queues outgoing packets.
+    NetworkOut,
+    /// Runs last in every tick, before packets are flushed.
+    PostTick,
+}
+
+/// Configure the ordering of [TickSet] and the sets from other beacon crates.
+pub(crate) fn configure(schedule: &mut Schedule) {
+    schedule.configure_sets(
+        (
+            TickSet::PreTick,
+            TickSet::NetworkIn,
+            TickSet::GameLogic.run_if(TickManager::is_running),
+            TickSet::NetworkOut,
+            TickSet::PostTick,
+        )
+            .chain(),
+    );
+    schedule.configure_sets((
+        ConfigSet.in_set(TickSet::PreTick),
+        NetworkSet::Receive.in_set(TickSet::NetworkIn),
+        NetworkSet::Flush.after(TickSet::PostTick),
+    ));
+
+    // record each stage as it starts, so the watchdog can tell where a tick hung
+    let stages = stages();
+    for (i, stage) in stages.into_iter().enumerate() {
+        let marker = TickStage::enter(stage).before(stage);
+        match i.checked_sub(1) {
+            Some(previous) => schedule.add_systems(marker.after(stages[previous])),
+            None => schedule.add_systems(marker),
+        };
+    }
+}
+
+/// The stages of a tick, in the order they run.
+pub(crate) fn stages() -> [InternedSystemSet; 6] {
+    [
+        TickSet::PreTick.intern(),
+        TickSet::NetworkIn.intern(),
+        TickSet::GameLogic.intern(),
+        TickSet::NetworkOut.intern(),
+        TickSet::PostTick.intern(),
+        NetworkSet::Flush.intern(),
+    ]
+}
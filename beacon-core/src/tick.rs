@@ -0,0 +1,576 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use beacon_codec::{ProtocolState, types::VarInt};
+use beacon_net::{
+    client::tick::{TickingState, TickingStep},
+    conn::PacketSender,
+};
+use bevy_ecs::{prelude::*, schedule::InternedSystemSet};
+use tokio_util::sync::CancellationToken;
+
+/// The number of ticks the server aims to run every second.
+pub const DEFAULT_TICK_RATE: f32 = 20.0;
+
+/// The lowest tick rate the server can be set to.
+pub const MIN_TICK_RATE: f32 = 1.0;
+
+/// The highest tick rate the server can be set to.
+pub const MAX_TICK_RATE: f32 = 10000.0;
+
+/// How many ticks of history are kept in [TickMetrics].
+const HISTORY_LEN: usize = 100;
+
+/// How far behind schedule the server may fall before it gives up catching up and skips ticks.
+const MAX_CATCH_UP: Duration = Duration::from_secs(2);
+
+/// Minimum time between two "can't keep up" warnings.
+const WARN_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Timing information about recent ticks.
+#[derive(Resource, Debug, Default)]
+pub struct TickMetrics {
+    tick: u64,
+    last_start: Option<Instant>,
+    /// How long each recent tick took to run.
+    mspt: VecDeque<Duration>,
+    /// How long passed between the start of each recent tick and the one before it.
+    intervals: VecDeque<Duration>,
+}
+
+impl TickMetrics {
+    /// The number of ticks that have run since the server started.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The average time, in milliseconds, that recent ticks took to run.
+    pub fn mspt(&self) -> f64 {
+        average_ms(&self.mspt)
+    }
+
+    /// How long each recent tick took to run, oldest first.
+    pub fn mspt_history(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.mspt.iter().copied()
+    }
+
+    /// The average number of ticks per second over recent ticks.
+    pub fn tps(&self) -> f64 {
+        match average_ms(&self.intervals) {
+            0.0 => 0.0,
+            ms => 1000.0 / ms,
+        }
+    }
+
+    /// The instantaneous ticks per second of each recent tick, oldest first.
+    pub fn tps_history(&self) -> impl Iterator<Item = f64> + '_ {
+        self.intervals
+            .iter()
+            .map(|interval| 1.0 / interval.as_secs_f64().max(f64::EPSILON))
+    }
+
+    /// Record a tick that started at `start` and took `duration` to run.
+    pub(crate) fn record(&mut self, start: Instant, duration: Duration) {
+        self.tick += 1;
+        if let Some(last) = self.last_start.replace(start) {
+            push_bounded(&mut self.intervals, start.duration_since(last));
+        }
+        push_bounded(&mut self.mspt, duration);
+    }
+}
+
+fn push_bounded<T>(history: &mut VecDeque<T>, value: T) {
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+fn average_ms(durations: &VecDeque<Duration>) -> f64 {
+    if durations.is_empty() {
+        return 0.0;
+    }
+    let total: Duration = durations.iter().sum();
+    total.as_secs_f64() * 1000.0 / durations.len() as f64
+}
+
+/// Controls the speed of the game loop: freezing, stepping, sprinting and the target tick rate.
+///
+/// Systems that advance the game should only run while [TickManager::is_running] is true, so that
+/// freezing the game does not also stop the server from handling connections.
+#[derive(Resource, Debug)]
+pub struct TickManager {
+    rate: f32,
+    frozen: bool,
+    steps: u32,
+    sprint: Option<Sprint>,
+
+    state_changed: bool,
+    step_changed: bool,
+}
+
+#[derive(Debug)]
+struct Sprint {
+    remaining: u64,
+    ticks: u64,
+    started: Instant,
+}
+
+impl Default for TickManager {
+    fn default() -> Self {
+        Self {
+            rate: DEFAULT_TICK_RATE,
+            frozen: false,
+            steps: 0,
+            sprint: None,
+
+            state_changed: false,
+            step_changed: false,
+        }
+    }
+}
+
+impl TickManager {
+    /// The number of ticks the server aims to run every second.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Set the target tick rate, clamped between [MIN_TICK_RATE] and [MAX_TICK_RATE].
+    /// Returns false, leaving the rate unchanged, if `rate` is not a finite number.
+    pub fn set_rate(&mut self, rate: f32) -> bool {
+        if !rate.is_finite() {
+            return false;
+        }
+        self.rate = rate.clamp(MIN_TICK_RATE, MAX_TICK_RATE);
+        self.state_changed = true;
+        true
+    }
+
+    /// Whether the game is frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Freeze the game. Connections are still handled while frozen.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+        self.state_changed = true;
+    }
+
+    /// Unfreeze the game, cancelling any steps that are still to run.
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+        self.state_changed = true;
+        self.stop_stepping();
+    }
+
+    /// The number of ticks left to step while frozen.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Run `ticks` ticks while the game is frozen. Returns false if the game is not frozen.
+    pub fn step(&mut self, ticks: u32) -> bool {
+        if !self.frozen {
+            return false;
+        }
+        self.steps = ticks;
+        self.step_changed = true;
+        true
+    }
+
+    /// Stop stepping. Returns false if the game was not stepping.
+    pub fn stop_stepping(&mut self) -> bool {
+        if self.steps == 0 {
+            return false;
+        }
+        self.steps = 0;
+        self.step_changed = true;
+        true
+    }
+
+    /// Whether the game is sprinting.
+    pub fn is_sprinting(&self) -> bool {
+        self.sprint.is_some()
+    }
+
+    /// Run the next `ticks` ticks as fast as possible, ignoring the target tick rate.
+    /// Returns false if a sprint was already in progress, in which case it is replaced.
+    pub fn sprint(&mut self, ticks: u64) -> bool {
+        let replaced = self.sprint.is_some();
+        self.sprint = Some(Sprint {
+            remaining: ticks,
+            ticks: 0,
+            started: Instant::now(),
+        });
+        !replaced
+    }
+
+    /// Stop sprinting. Returns false if the game was not sprinting.
+    pub fn stop_sprinting(&mut self) -> bool {
+        match self.sprint.take() {
+            Some(sprint) => {
+                sprint.report();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run condition for systems that advance the game: true unless the game is frozen and not
+    /// stepping.
+    pub fn is_running(manager: Res<Self>) -> bool {
+        !manager.frozen || manager.steps > 0
+    }
+
+    /// The time to wait between the starts of two ticks.
+    fn interval(&self) -> Duration {
+        match self.sprint {
+            Some(_) => Duration::ZERO,
+            None => Duration::from_secs_f32(1.0 / self.rate),
+        }
+    }
+
+    /// Update stepping and sprinting after a tick has run.
+    pub(crate) fn finish_tick(&mut self) {
+        if self.frozen && self.steps > 0 {
+            self.steps -= 1;
+        }
+
+        if let Some(sprint) = &mut self.sprint {
+            sprint.ticks += 1;
+            sprint.remaining = sprint.remaining.saturating_sub(1);
+            if sprint.remaining == 0 {
+                self.stop_sprinting();
+            }
+        }
+    }
+
+    /// Keep clients in the `Play` state in sync with the tick manager.
+    pub(crate) fn sync(
+        mut manager: ResMut<Self>,
+        query: Query<(Ref<ProtocolState>, &PacketSender)>,
+    ) -> Result<()> {
+        for (state, sender) in query.iter() {
+            if *state != ProtocolState::Play {
+                continue;
+            }
+
+            // players who just entered the game need the full state
+            let joined = state.is_changed();
+            if manager.state_changed || joined {
+                let packet = TickingState {
+                    tick_rate: manager.rate,
+                    is_frozen: manager.frozen,
+                };
+                let _ = sender.send(packet.blocking_raw()?);
+            }
+            if manager.step_changed || (joined && manager.steps > 0) {
+                let packet = TickingStep {
+                    tick_steps: VarInt(manager.steps as i32),
+                };
+                let _ = sender.send(packet.blocking_raw()?);
+            }
+        }
+
+        // avoid triggering change detection every tick
+        if manager.state_changed || manager.step_changed {
+            manager.state_changed = false;
+            manager.step_changed = false;
+        }
+
+        Ok(())
+    }
+}
+
+impl Sprint {
+    fn report(&self) {
+        let elapsed = self.started.elapsed();
+        let mspt = elapsed.as_secs_f64() * 1000.0 / self.ticks.max(1) as f64;
+        info!(
+            "sprint completed with {} ticks per second, or {:.2} ms per tick",
+            (self.ticks as f64 / elapsed.as_secs_f64().max(f64::EPSILON)) as u64,
+            mspt
+        );
+    }
+}
+
+/// Fixed-timestep clock that decides when the next tick should run.
+pub(crate) struct TickClock {
+    next: Instant,
+    last_warning: Option<Instant>,
+}
+
+impl TickClock {
+    /// Create a clock whose first tick is due now.
+    pub fn new() -> Self {
+        Self {
+            next: Instant::now(),
+            last_warning: None,
+        }
+    }
+
+    /// Wait until the next tick is due.
+    ///
+    /// If the server is behind schedule, this returns immediately so that missed ticks are caught up.
+    pub async fn wait(&self) {
+        tokio::time::sleep_until(self.next.into()).await;
+    }
+
+    /// Schedule the next tick, skipping ticks if the server has fallen too far behind.
+    pub fn advance(&mut self, manager: &TickManager) {
+        let now = Instant::now();
+
+        // sprinting runs ticks back to back, so there is nothing to catch up on
+        if manager.is_sprinting() {
+            self.next = now;
+            return;
+        }
+
+        let interval = manager.interval();
+        self.next += interval;
+        let behind = now.saturating_duration_since(self.next);
+        if behind > MAX_CATCH_UP {
+            if self
+                .last_warning
+                .is_none_or(|last| now.duration_since(last) > WARN_INTERVAL)
+            {
+                let skipped = behind.as_nanos() / interval.as_nanos();
+                warn!(
+                    "can't keep up! is the server overloaded? running {}ms or {} ticks behind",
+                    behind.as_millis(),
+                    skipped
+                );
+                self.last_warning = Some(now);
+            }
+            self.next = now;
+        }
+    }
+}
+
+/// The stage of the tick that is running, so the watchdog can tell where a tick hung.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct TickStage(Arc<Mutex<Option<InternedSystemSet>>>);
+
+impl TickStage {
+    /// A system that records that the tick has reached the given set.
+    pub fn enter(set: InternedSystemSet) -> impl FnMut(Res<TickStage>) {
+        move |stage: Res<TickStage>| {
+            if let Ok(mut current) = stage.0.lock() {
+                *current = Some(set);
+            }
+        }
+    }
+
+    /// The set the tick last reached.
+    fn current(&self) -> Option<InternedSystemSet> {
+        self.0.lock().ok().and_then(|current| *current)
+    }
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    tick: u64,
+    started: Option<Instant>,
+    reported: bool,
+}
+
+/// Background thread that reports ticks which run for longer than `max-tick-time`.
+pub(crate) struct Watchdog {
+    state: Arc<Mutex<WatchdogState>>,
+}
+
+impl Watchdog {
+    /// Spawn the watchdog thread.
+    ///
+    /// `systems` are the names of the systems in each stage of the tick. When a tick hangs, the
+    /// stage it is stuck in and that stage's systems are logged, to help track down the culprit.
+    pub fn spawn(
+        max_tick_time: Duration,
+        stage: TickStage,
+        systems: Vec<(InternedSystemSet, Vec<String>)>,
+        cancel: CancellationToken,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(WatchdogState::default()));
+        let poll = (max_tick_time / 4).clamp(Duration::from_millis(1), Duration::from_secs(1));
+
+        thread::Builder::new()
+            .name("beacon-watchdog".into())
+            .spawn({
+                let state = state.clone();
+                move || {
+                    while !cancel.is_cancelled() {
+                        thread::sleep(poll);
+
+                        let Ok(mut state) = state.lock() else { break };
+                        let Some(started) = state.started else {
+                            continue;
+                        };
+                        let elapsed = started.elapsed();
+                        if elapsed > max_tick_time && !state.reported {
+                            state.reported = true;
+                            error!(
+                                tick = state.tick,
+                                "a single server tick has taken {}ms (should be max {}ms)",
+                                elapsed.as_millis(),
+                                max_tick_time.as_millis()
+                            );
+                            report_stage(stage.current(), &systems);
+                        }
+                    }
+                }
+            })
+            .expect("failed to spawn watchdog thread");
+
+        Self { state }
+    }
+
+    /// Mark the start of a tick.
+    pub fn begin(&self, tick: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = WatchdogState {
+                tick,
+                started: Some(Instant::now()),
+                reported: false,
+            };
+        }
+    }
+
+    /// Mark the end of the current tick.
+    pub fn end(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.reported
+                && let Some(started) = state.started
+            {
+                warn!(
+                    tick = state.tick,
+                    "hung tick finished after {}ms",
+                    started.elapsed().as_millis()
+                );
+            }
+            state.started = None;
+        }
+    }
+}
+
+/// Log the stage a hung tick is stuck in, and the systems that run in it.
+fn report_stage(stage: Option<InternedSystemSet>, systems: &[(InternedSystemSet, Vec<String>)]) {
+    let Some(stage) = stage else {
+        error!("the hung tick has not started running any systems");
+        return;
+    };
+    let names = systems
+        .iter()
+        .find(|(set, _)| *set == stage)
+        .map_or_else(Vec::new, |(_, names)| names.clone());
+    error!(
+        "the hung tick is stuck in {stage:?}, which runs:\n\t{}",
+        names.join("\n\t")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bevy_ecs::schedule::SystemSet;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{
+        DEFAULT_TICK_RATE, HISTORY_LEN, MAX_CATCH_UP, MAX_TICK_RATE, TickClock, TickManager,
+        TickMetrics, TickStage, Watchdog,
+    };
+    use crate::TickSet;
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = TickMetrics::default();
+        let start = Instant::now();
+        for i in 0..HISTORY_LEN as u32 + 10 {
+            metrics.record(
+                start + Duration::from_millis(50) * i,
+                Duration::from_millis(10),
+            );
+        }
+
+        assert_eq!(metrics.tick(), HISTORY_LEN as u64 + 10);
+        assert_eq!(metrics.mspt_history().count(), HISTORY_LEN);
+        assert!((metrics.mspt() - 10.0).abs() < 1e-9);
+        assert!((metrics.tps() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_manager() {
+        let mut manager = TickManager::default();
+        assert!(!manager.set_rate(f32::NAN));
+        assert!(!manager.set_rate(f32::INFINITY));
+        assert_eq!(manager.rate(), DEFAULT_TICK_RATE);
+        assert!(manager.set_rate(100_000.0));
+        assert_eq!(manager.rate(), MAX_TICK_RATE);
+
+        // stepping only works while frozen, and counts down as ticks run
+        assert!(!manager.step(2));
+        manager.freeze();
+        assert!(manager.step(2));
+        manager.finish_tick();
+        assert_eq!(manager.steps(), 1);
+        manager.unfreeze();
+        assert_eq!(manager.steps(), 0);
+
+        assert!(manager.sprint(2));
+        assert!(!manager.sprint(2));
+        assert_eq!(manager.interval(), Duration::ZERO);
+        manager.finish_tick();
+        manager.finish_tick();
+        assert!(!manager.is_sprinting());
+    }
+
+    #[test]
+    fn test_clock() {
+        let manager = TickManager::default();
+        let mut clock = TickClock::new();
+        let start = clock.next;
+        clock.advance(&manager);
+        assert_eq!(clock.next - start, manager.interval());
+
+        // ticks are skipped once the server falls too far behind
+        clock.next = Instant::now() - MAX_CATCH_UP * 2;
+        clock.advance(&manager);
+        assert!(clock.next + Duration::from_secs(1) > Instant::now());
+        assert!(clock.last_warning.is_some());
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let cancel = CancellationToken::new();
+        let stage = TickStage::default();
+        let systems = vec![(TickSet::GameLogic.intern(), vec!["slow".to_string()])];
+        let watchdog = Watchdog::spawn(
+            Duration::from_millis(10),
+            stage.clone(),
+            systems,
+            cancel.clone(),
+        );
+
+        watchdog.begin(1);
+        *stage.0.lock().unwrap() = Some(TickSet::GameLogic.intern());
+        thread::sleep(Duration::from_millis(100));
+        assert!(watchdog.state.lock().unwrap().reported);
+        watchdog.end();
+        assert!(watchdog.state.lock().unwrap().started.is_none());
+
+        // quick ticks are not reported
+        watchdog.begin(2);
+        watchdog.end();
+        assert!(!watchdog.state.lock().unwrap().reported);
+        cancel.cancel();
+    }
+}
@@ -0,0 +1,306 @@
+//! A minimal WebSocket server implementation, enough for the management protocol.
+//!
+//! See: <https://datatracker.ietf.org/doc/html/rfc6455>
+
+use std::io::{self, ErrorKind};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use sha1::{Digest, Sha1};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+
+/// Appended to the client's key before hashing it, to prove the server understood the handshake.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The longest a handshake request can be.
+const MAX_HANDSHAKE_LENGTH: usize = 8192;
+
+/// The largest message a client may send, after joining its fragments.
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+/// Close status: the connection is closing normally.
+pub(crate) const CLOSE_NORMAL: u16 = 1000;
+
+/// Close status: the client broke the protocol.
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+/// Close status: the client sent something other than text.
+const CLOSE_UNSUPPORTED: u16 = 1003;
+
+/// Close status: a text message was not valid UTF-8.
+const CLOSE_INVALID_DATA: u16 = 1007;
+
+/// Close status: the message was too big.
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Close status: the server is shutting down.
+pub(crate) const CLOSE_GOING_AWAY: u16 = 1001;
+
+/// The opening handshake sent by a client.
+#[derive(Debug, Default)]
+pub(crate) struct Handshake {
+    /// The value of the `Sec-WebSocket-Key` header.
+    key: Option<String>,
+    /// Whether the client asked to upgrade to a WebSocket.
+    upgrade: bool,
+    /// The bearer token from the `Authorization` header.
+    pub bearer: Option<String>,
+}
+
+impl Handshake {
+    /// Read the client's HTTP upgrade request, reading no more than [MAX_HANDSHAKE_LENGTH] bytes.
+    pub(crate) async fn read<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+        let mut reader = reader.take(MAX_HANDSHAKE_LENGTH as u64);
+        let mut handshake = Self::default();
+        let mut line = String::new();
+        let mut request_line = true;
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await?;
+            // lines are cut short when the client stops sending or the limit is reached
+            if !line.ends_with('\n') {
+                return Err(match reader.limit() {
+                    0 => invalid("handshake is too long"),
+                    _ => ErrorKind::UnexpectedEof.into(),
+                });
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                return Ok(handshake);
+            }
+            if std::mem::take(&mut request_line) {
+                if !line.starts_with("GET ") {
+                    return Err(invalid("handshake is not a GET request"));
+                }
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(invalid("malformed handshake header"));
+            };
+            let value = value.trim();
+            match name.to_ascii_lowercase().as_str() {
+                "upgrade" => handshake.upgrade = value.eq_ignore_ascii_case("websocket"),
+                "sec-websocket-key" => handshake.key = Some(value.to_string()),
+                "authorization" => {
+                    handshake.bearer = value
+                        .strip_prefix("Bearer ")
+                        .map(|token| token.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The response accepting the handshake, or `None` if it is not a valid WebSocket upgrade.
+    pub(crate) fn accept(&self) -> Option<String> {
+        let key = self.key.as_ref().filter(|_| self.upgrade)?;
+        let accept = BASE64_STANDARD.encode(Sha1::digest(format!("{key}{ACCEPT_GUID}")));
+        Some(format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {accept}\r\n\r\n"
+        ))
+    }
+}
+
+/// A response refusing the handshake.
+pub(crate) fn refuse(status: &str) -> String {
+    format!("HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n")
+}
+
+/// A message received from the client.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Message {
+    /// A text message.
+    Text(String),
+    /// A ping, which must be answered with a pong containing the same data.
+    Ping(Vec<u8>),
+    /// The client is closing the connection.
+    Close,
+}
+
+/// Error that can occur while reading a message.
+#[derive(Debug)]
+pub(crate) enum ReadError {
+    /// The socket could not be read from.
+    Io(io::Error),
+    /// The client broke the protocol, and the connection should be closed with this status.
+    Protocol(u16),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Reads messages from a client, joining fragmented messages and skipping pongs.
+pub(crate) struct MessageReader<R> {
+    reader: R,
+    /// The fragments of a message received so far, kept across control frames.
+    partial: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> MessageReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Read the next message.
+    pub(crate) async fn read(&mut self) -> Result<Message, ReadError> {
+        loop {
+            let mut head = [0; 2];
+            self.reader.read_exact(&mut head).await?;
+            let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0F);
+            if head[1] & 0x80 == 0 {
+                // clients must mask their frames
+                return Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR));
+            }
+            let len = match head[1] & 0x7F {
+                126 => self.reader.read_u16().await? as u64,
+                127 => self.reader.read_u64().await?,
+                len => len as u64,
+            };
+            if self.partial.len() as u64 + len > MAX_MESSAGE_SIZE as u64 {
+                return Err(ReadError::Protocol(CLOSE_TOO_BIG));
+            }
+
+            let mut mask = [0; 4];
+            self.reader.read_exact(&mut mask).await?;
+            let mut payload = vec![0; len as usize];
+            self.reader.read_exact(&mut payload).await?;
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+
+            match opcode {
+                TEXT | CONTINUATION => {
+                    self.partial.extend(payload);
+                    if fin {
+                        return String::from_utf8(std::mem::take(&mut self.partial))
+                            .map(Message::Text)
+                            .map_err(|_| ReadError::Protocol(CLOSE_INVALID_DATA));
+                    }
+                }
+                BINARY => return Err(ReadError::Protocol(CLOSE_UNSUPPORTED)),
+                CLOSE => return Ok(Message::Close),
+                PING => return Ok(Message::Ping(payload)),
+                PONG => {}
+                _ => return Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR)),
+            }
+        }
+    }
+}
+
+/// Encode an unmasked frame, as sent by the server.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![0x80 | opcode];
+    match payload.len() {
+        len @ ..126 => buf.push(len as u8),
+        len @ ..=0xFFFF => {
+            buf.push(126);
+            buf.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            buf.push(127);
+            buf.extend((len as u64).to_be_bytes());
+        }
+    }
+    buf.extend(payload);
+    buf
+}
+
+/// Write a text message.
+pub(crate) async fn write_text<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    text: &str,
+) -> io::Result<()> {
+    writer.write_all(&frame(TEXT, text.as_bytes())).await
+}
+
+/// Answer a ping.
+pub(crate) async fn write_pong<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    data: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&frame(PONG, data)).await
+}
+
+/// Close the connection with a status code.
+pub(crate) async fn write_close<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: u16,
+) -> io::Result<()> {
+    writer.write_all(&frame(CLOSE, &status.to_be_bytes())).await
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Handshake, MAX_HANDSHAKE_LENGTH, Message, MessageReader, ReadError};
+
+    #[tokio::test]
+    async fn test_handshake() {
+        // the example from the rfc
+        let request = "GET /chat HTTP/1.1\r\n\
+                       Host: server.example.com\r\n\
+                       Upgrade: websocket\r\n\
+                       Connection: Upgrade\r\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                       Authorization: Bearer secret\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n";
+        let handshake = Handshake::read(&mut request.as_bytes()).await.unwrap();
+        assert_eq!(handshake.bearer.as_deref(), Some("secret"));
+        assert!(
+            handshake
+                .accept()
+                .unwrap()
+                .contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n")
+        );
+
+        // the request is read no further than the limit
+        let long = format!(
+            "GET / HTTP/1.1\r\nHost: {}\r\n\r\n",
+            "a".repeat(MAX_HANDSHAKE_LENGTH)
+        );
+        let err = Handshake::read(&mut long.as_bytes()).await.unwrap_err();
+        assert_eq!(err.to_string(), "handshake is too long");
+    }
+
+    #[tokio::test]
+    async fn test_read_message() {
+        // a fragmented, masked "Hello" from the rfc, with a ping in between
+        let frames: &[u8] = &[
+            0x01, 0x83, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, // "Hel"
+            0x89, 0x80, 0x00, 0x00, 0x00, 0x00, // empty ping
+            0x80, 0x82, 0x37, 0xfa, 0x21, 0x3d, 0x5b, 0x95, // "lo"
+        ];
+        let mut reader = MessageReader::new(frames);
+        assert_eq!(reader.read().await.unwrap(), Message::Ping(Vec::new()));
+        assert_eq!(reader.read().await.unwrap(), Message::Text("Hello".into()));
+
+        // unmasked
+        let mut reader = MessageReader::new(&[0x81, 0x01, b'a'][..]);
+        assert!(matches!(
+            reader.read().await,
+            Err(ReadError::Protocol(1002))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use beacon_codec::ProtocolState;
+use beacon_net::{
+    client::world::{GameEvent, SetTime},
+    conn::PacketSender,
+};
+use bevy_ecs::prelude::*;
+
+use crate::{App, TickSet};
+
+/// How long a day lasts, in ticks.
+pub const DAY_LENGTH: i64 = 24000;
+
+/// How often clients are sent the time, in ticks, so their clocks do not drift.
+const TIME_SYNC_INTERVAL: i64 = 20;
+
+/// The time in the world, which advances by one every tick.
+#[derive(Resource, Debug, Default)]
+pub struct WorldTime {
+    game_time: i64,
+    day_time: i64,
+    changed: bool,
+}
+
+impl WorldTime {
+    /// How many ticks the world has existed for.
+    pub fn game_time(&self) -> i64 {
+        self.game_time
+    }
+
+    /// The time of day in ticks, counting up since the world was created. 0 is sunrise, 6000 is
+    /// noon, 12000 is sunset and 18000 is midnight.
+    pub fn day_time(&self) -> i64 {
+        self.day_time
+    }
+
+    /// How many days have passed.
+    pub fn day(&self) -> i64 {
+        self.day_time / DAY_LENGTH
+    }
+
+    /// Set the time of day, e.g. with `/time set`.
+    pub fn set_day_time(&mut self, day_time: i64) {
+        self.day_time = day_time;
+        self.changed = true;
+    }
+
+    /// Advance the time by a tick.
+    fn advance(mut time: ResMut<Self>) {
+        time.game_time += 1;
+        time.day_time += 1;
+    }
+
+    /// Keep clients in the `Play` state in sync with the time.
+    fn sync(
+        mut time: ResMut<Self>,
+        query: Query<(Ref<ProtocolState>, &PacketSender)>,
+    ) -> Result<()> {
+        let periodic = time.game_time % TIME_SYNC_INTERVAL == 0;
+        for (state, sender) in query.iter() {
+            if *state != ProtocolState::Play || !(time.changed || periodic || state.is_changed()) {
+                continue;
+            }
+            let packet = SetTime {
+                world_age: time.game_time,
+                time_of_day: time.day_time,
+                time_of_day_increasing: true,
+            };
+            let _ = sender.send(packet.blocking_raw()?);
+        }
+
+        if time.changed {
+            time.changed = false;
+        }
+        Ok(())
+    }
+}
+
+/// What the weather is like.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeatherKind {
+    /// No rain.
+    #[default]
+    Clear,
+    /// Rain, or snow in cold biomes.
+    Rain,
+    /// Rain with thunder and lightning.
+    Thunder,
+}
+
+impl WeatherKind {
+    /// The weather's name, as used in commands.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Clear => "clear",
+            Self::Rain => "rain",
+            Self::Thunder => "thunder",
+        }
+    }
+}
+
+/// The weather in the world. Once the weather's duration runs out, the sky clears up again.
+#[derive(Resource, Debug, Default)]
+pub struct Weather {
+    kind: WeatherKind,
+    remaining: Option<u32>,
+    changed: bool,
+}
+
+impl Weather {
+    /// What the weather is like.
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    /// How many more ticks the weather lasts for, or `None` if it lasts until it is changed.
+    pub fn remaining(&self) -> Option<u32> {
+        self.remaining
+    }
+
+    /// Change the weather, for the given number of ticks or until it is changed again.
+    pub fn set(&mut self, kind: WeatherKind, duration: Option<u32>) {
+        self.changed |= self.kind != kind;
+        self.kind = kind;
+        self.remaining = duration;
+    }
+
+    /// Count down the weather's duration.
+    fn advance(mut weather: ResMut<Self>) {
+        let Some(remaining) = weather.remaining else {
+            return;
+        };
+        match remaining.checked_sub(1) {
+            Some(0) | None => weather.set(WeatherKind::Clear, None),
+            remaining => weather.remaining = remaining,
+        }
+    }
+
+    /// Keep clients in the `Play` state in sync with the weather.
+    fn sync(
+        mut weather: ResMut<Self>,
+        query: Query<(Ref<ProtocolState>, &PacketSender)>,
+    ) -> Result<()> {
+        for (state, sender) in query.iter() {
+            let joined = state.is_changed();
+            if *state != ProtocolState::Play || !(weather.changed || joined) {
+                continue;
+            }
+            // players who just joined only need to hear about bad weather
+            if joined && weather.kind == WeatherKind::Clear {
+                continue;
+            }
+
+            let (event, rain, thunder) = match weather.kind {
+                WeatherKind::Clear => (GameEvent::END_RAINING, 0.0, 0.0),
+                WeatherKind::Rain => (GameEvent::BEGIN_RAINING, 1.0, 0.0),
+                WeatherKind::Thunder => (GameEvent::BEGIN_RAINING, 1.0, 1.0),
+            };
+            let events = [
+                (event, 0.0),
+                (GameEvent::RAIN_LEVEL_CHANGE, rain),
+                (GameEvent::THUNDER_LEVEL_CHANGE, thunder),
+            ];
+            for (event, value) in events {
+                let _ = sender.send(GameEvent { event, value }.blocking_raw()?);
+            }
+        }
+
+        if weather.changed {
+            weather.changed = false;
+        }
+        Ok(())
+    }
+}
+
+/// Advance the time and weather each tick.
+pub(crate) fn configure(app: &mut App) {
+    app.init_resource::<WorldTime>()
+        .init_resource::<Weather>()
+        .add_systems(TickSet::GameLogic, (WorldTime::advance, Weather::advance));
+}
+
+/// Keep clients in sync with the time and weather.
+pub(crate) fn configure_network(app: &mut App) {
+    app.add_systems(TickSet::NetworkOut, (WorldTime::sync, Weather::sync));
+}
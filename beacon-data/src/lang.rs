@@ -0,0 +1,75 @@
+/// The English message for a translation key, for the messages beacon sends. Clients translate
+/// messages themselves, so this is only used to show them in the console and logs.
+///
+/// See: <https://minecraft.wiki/w/Resource_pack#Language>
+pub fn english(key: &str) -> Option<&'static str> {
+    let message = match key {
+        "argument.entity.notfound.entity" => "No entity was found",
+        "argument.entity.notfound.player" => "No player was found",
+        "argument.entity.selector.not_allowed" => "Selector not allowed",
+        "argument.player.unknown" => "That player does not exist",
+        "chat.type.admin" => "[%s: %s]",
+        "chat.type.announcement" => "[%s] %s",
+        "commands.ban.failed" => "Nothing changed. The player is already banned",
+        "commands.ban.success" => "Banned %s: %s",
+        "commands.banip.failed" => "Nothing changed. That IP is already banned",
+        "commands.banip.info" => "This ban affects %s player(s): %s",
+        "commands.banip.invalid" => "Invalid IP address or unknown player",
+        "commands.banip.success" => "Banned IP %s: %s",
+        "commands.deop.failed" => "Nothing changed. The player is not an operator",
+        "commands.deop.success" => "Made %s no longer a server operator",
+        "commands.gamemode.success.other" => "Set %s's game mode to %s",
+        "commands.gamemode.success.self" => "Set own game mode to %s",
+        "commands.give.success.multiple" => "Gave %s [%s] to %s players",
+        "commands.give.success.single" => "Gave %s [%s] to %s",
+        "commands.kick.success" => "Kicked %s: %s",
+        "commands.list.players" => "There are %s of a max of %s players online: %s",
+        "commands.op.failed" => "Nothing changed. The player already is an operator",
+        "commands.op.success" => "Made %s a server operator",
+        "commands.pardon.failed" => "Nothing changed. The player isn't banned",
+        "commands.pardon.success" => "Unbanned %s",
+        "commands.pardonip.failed" => "Nothing changed. That IP isn't banned",
+        "commands.pardonip.invalid" => "Invalid IP address",
+        "commands.pardonip.success" => "Unbanned IP %s",
+        "commands.stop.stopping" => "Stopping the server",
+        "commands.teleport.success.entity.multiple" => "Teleported %s entities to %s",
+        "commands.teleport.success.entity.single" => "Teleported %s to %s",
+        "commands.teleport.success.location.multiple" => "Teleported %s entities to %s, %s, %s",
+        "commands.teleport.success.location.single" => "Teleported %s to %s, %s, %s",
+        "commands.time.query" => "The time is %s",
+        "commands.time.set" => "Set the time to %s",
+        "commands.weather.set.clear" => "Set the weather to clear",
+        "commands.weather.set.rain" => "Set the weather to rain",
+        "commands.weather.set.thunder" => "Set the weather to rain & thunder",
+        "commands.whitelist.add.failed" => "Player is already whitelisted",
+        "commands.whitelist.add.success" => "Added %s to the whitelist",
+        "commands.whitelist.alreadyOff" => "Whitelist is already turned off",
+        "commands.whitelist.alreadyOn" => "Whitelist is already turned on",
+        "commands.whitelist.disabled" => "Whitelist is now turned off",
+        "commands.whitelist.enabled" => "Whitelist is now turned on",
+        "commands.whitelist.list" => "There are %s whitelisted player(s): %s",
+        "commands.whitelist.none" => "There are no whitelisted players",
+        "commands.whitelist.remove.failed" => "Player is not whitelisted",
+        "commands.whitelist.remove.success" => "Removed %s from the whitelist",
+        "gameMode.adventure" => "Adventure Mode",
+        "gameMode.changed" => "Your game mode has been updated to %s",
+        "gameMode.creative" => "Creative Mode",
+        "gameMode.spectator" => "Spectator Mode",
+        "gameMode.survival" => "Survival Mode",
+        "multiplayer.disconnect.banned" => "You are banned from this server",
+        "multiplayer.disconnect.banned.reason" => "You are banned from this server.\nReason: %s",
+        "multiplayer.disconnect.banned_ip.reason" => {
+            "Your IP address is banned from this server.\nReason: %s"
+        }
+        "multiplayer.disconnect.duplicate_login" => "You logged in from another location",
+        "multiplayer.disconnect.ip_banned" => "You have been IP banned from this server",
+        "multiplayer.disconnect.kicked" => "Kicked by an operator",
+        "multiplayer.disconnect.not_whitelisted" => "You are not white-listed on this server!",
+        "multiplayer.disconnect.server_full" => "The server is full!",
+        "multiplayer.disconnect.server_shutdown" => "Server closed",
+        "permissions.requires.entity" => "An entity is required to run this command here",
+        "permissions.requires.player" => "A player is required to run this command here",
+        _ => return None,
+    };
+    Some(message)
+}
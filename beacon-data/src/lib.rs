@@ -5,6 +5,9 @@
 #[macro_use]
 extern crate derive_more;
 
+/// Translations of the messages beacon sends.
+pub mod lang;
+
 /// A Minecraft version number.
 #[derive(Clone, Copy, Display)]
 #[display("1.{}.{}", self.0, self.1)]
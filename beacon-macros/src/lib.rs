@@ -150,13 +150,11 @@ pub fn client(args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn server(args: TokenStream, input: TokenStream) -> TokenStream {
     // alias
     let decode = quote! { beacon_codec::decode };
-    let entity = quote! { bevy_ecs::entity::Entity };
 
     let mut item = syn::parse_macro_input!(input as ItemStruct);
     let packet = packet(false, args, &mut item);
 
     let name = &item.ident;
-    let event = Ident::new(&format!("{name}Event"), Span::call_site().into());
 
     // impl Decode
     let decode_fields = item.fields.iter().map(
@@ -184,20 +182,6 @@ pub fn server(args: TokenStream, input: TokenStream) -> TokenStream {
                 })
             }
         }
-
-        #[doc = concat!("Event fired when a [[", stringify!(#name), "]] packet is received.") ]
-        #[derive(bevy_ecs::event::EntityEvent)]
-        pub(crate) struct #event {
-            pub entity: #entity,
-            pub packet: #name,
-        }
-
-        impl #name {
-            #[doc = concat!("Convert this packet into a [[", stringify!(#event), "]] for the given entity.") ]
-            pub fn event(self, entity: #entity) -> #event {
-                #event { entity, packet: self }
-            }
-        }
     }.into()
 }
 
@@ -208,7 +192,6 @@ pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
     let mut item = syn::parse_macro_input!(input as syn::ItemFn);
     let packet: Ident = syn::parse(args).unwrap_or_abort();
     let call_site = Span::call_site().into();
-    let event = Ident::new(&format!("{}Event", packet), call_site);
 
     // rename to handle
     item.sig.ident = Ident::new("handle", call_site);
@@ -216,7 +199,7 @@ pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
     // add event parameter to function signature
     item.sig.inputs.insert(
         0,
-        syn::parse_quote! { event: bevy_ecs::observer::On<#event> },
+        syn::parse_quote! { event: bevy_ecs::observer::On<crate::packet::PacketEvent<#packet>> },
     );
 
     // make it public to the crate
@@ -0,0 +1,152 @@
+//! Compares writing each packet to a socket on its own with [PacketWriter], which writes a tick's
+//! batch at once.
+//!
+//! Run with `cargo bench -p beacon-net`. Before benchmarking, the number of writes each approach
+//! makes per batch is printed.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use beacon_codec::{encode::Encode, types::VarInt};
+use beacon_net::{packet::RawPacket, writer::PacketWriter};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+};
+
+/// Sizes of the batches, in packets.
+const BATCH_SIZES: [usize; 3] = [1, 16, 256];
+
+/// A typical small packet, such as an entity movement update.
+fn batch(size: usize) -> Vec<RawPacket> {
+    (0..size)
+        .map(|i| RawPacket::new(VarInt(i as i32 % 128), vec![0xAB; 24].into()))
+        .collect()
+}
+
+/// A writer that counts how many writes are made to it.
+#[derive(Default)]
+struct Counter(usize);
+
+impl AsyncWrite for Counter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.0 += 1;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.0 += 1;
+        Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Print how many writes, and so syscalls on a socket, each approach makes per batch.
+fn print_writes(runtime: &Runtime) {
+    runtime.block_on(async {
+        for size in BATCH_SIZES {
+            let packets = batch(size);
+
+            let mut counter = Counter::default();
+            for packet in &packets {
+                packet.encode(&mut counter).await.unwrap();
+            }
+            let per_packet = counter.0;
+
+            let mut writer = PacketWriter::new(Counter::default());
+            writer.write_batch(&packets).await.unwrap();
+            let batched = writer.into_inner().0;
+
+            println!("{size} packets: {per_packet} writes per packet, {batched} writes batched");
+        }
+    });
+}
+
+/// Open a loopback connection, with a task discarding everything sent to it.
+async fn connect() -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut peer, _) = listener.accept().await.unwrap();
+    tokio::spawn(async move {
+        let mut buf = vec![0; 64 * 1024];
+        while peer.read(&mut buf).await.is_ok_and(|n| n > 0) {}
+    });
+    stream
+}
+
+fn bench_writer(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    print_writes(&runtime);
+
+    let mut group = c.benchmark_group("write_batch");
+    for size in BATCH_SIZES {
+        let packets = batch(size);
+        group.throughput(Throughput::Elements(size as u64));
+
+        let mut stream = runtime.block_on(connect());
+        group.bench_with_input(
+            BenchmarkId::new("per_packet", size),
+            &packets,
+            |b, packets| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            for packet in packets {
+                                packet.encode(&mut stream).await.unwrap();
+                            }
+                        }
+                        start.elapsed()
+                    })
+                })
+            },
+        );
+
+        let mut writer = PacketWriter::new(runtime.block_on(connect()));
+        group.bench_with_input(BenchmarkId::new("batched", size), &packets, |b, packets| {
+            b.iter_custom(|iters| {
+                runtime.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        writer.write_batch(packets).await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_writer);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+use crate::prelude::*;
+
+/// Marks the start and end of a bundle. The client processes every packet in a bundle in the same
+/// tick.
+///
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Bundle_Delimiter>
+#[client(resource = "bundle_delimiter", state = Play)]
+pub struct BundleDelimiter;
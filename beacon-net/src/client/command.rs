@@ -0,0 +1,149 @@
+use beacon_codec::encode::{Encode, EncodeError};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{prelude::*, text::Text};
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Commands>
+#[client(resource = "commands", state = Play)]
+pub struct DeclareCommands {
+    nodes: Vec<CommandNode>,
+    root_index: VarInt,
+}
+
+/// A node of the command graph sent to clients, so they can parse and suggest commands.
+///
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Command_data>
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandNode {
+    /// Whether this node is a literal, argument, or the root.
+    pub kind: CommandNodeKind,
+    /// Whether the command can be run if it ends at this node.
+    pub executable: bool,
+    /// Whether running the command from a chat click event needs confirmation.
+    pub restricted: bool,
+    /// The indices of the node's children.
+    pub children: Vec<VarInt>,
+    /// The index of the node that parsing continues from after this one, if any.
+    pub redirect: Option<VarInt>,
+}
+
+/// The kind of a [CommandNode].
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandNodeKind {
+    /// The root of the graph.
+    Root,
+    /// A word that must be typed exactly.
+    Literal {
+        /// The word.
+        name: String,
+    },
+    /// A value parsed by the client.
+    Argument {
+        /// The argument's name.
+        name: String,
+        /// The ID of the parser in the `command_argument_type` registry.
+        parser: VarInt,
+        /// The parser's properties, already encoded.
+        properties: Vec<u8>,
+        /// Where the client should get suggestions from, e.g. `minecraft:ask_server`.
+        suggestions: Option<String>,
+    },
+}
+
+impl Encode for CommandNode {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        let mut flags: u8 = match self.kind {
+            CommandNodeKind::Root => 0,
+            CommandNodeKind::Literal { .. } => 1,
+            CommandNodeKind::Argument { .. } => 2,
+        };
+        if self.executable {
+            flags |= 0x04;
+        }
+        if self.redirect.is_some() {
+            flags |= 0x08;
+        }
+        if let CommandNodeKind::Argument {
+            suggestions: Some(_),
+            ..
+        } = self.kind
+        {
+            flags |= 0x10;
+        }
+        if self.restricted {
+            flags |= 0x20;
+        }
+
+        flags.encode(write).await?;
+        self.children.encode(write).await?;
+        if let Some(redirect) = self.redirect {
+            redirect.encode(write).await?;
+        }
+        match &self.kind {
+            CommandNodeKind::Root => {}
+            CommandNodeKind::Literal { name } => name.encode(write).await?,
+            CommandNodeKind::Argument {
+                name,
+                parser,
+                properties,
+                suggestions,
+            } => {
+                name.encode(write).await?;
+                parser.encode(write).await?;
+                write.write_all(properties).await?;
+                if let Some(suggestions) = suggestions {
+                    suggestions.encode(write).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Command_Suggestions_Response>
+#[client(resource = "command_suggestions", state = Play)]
+pub struct CommandSuggestions {
+    id: VarInt,
+    start: VarInt,
+    length: VarInt,
+    matches: Vec<SuggestionMatch>,
+}
+
+/// A suggestion to replace part of the command with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestionMatch {
+    /// The text to insert.
+    pub text: String,
+    /// Shown when hovering over the suggestion.
+    pub tooltip: Option<Text>,
+}
+
+impl Encode for SuggestionMatch {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        self.text.encode(write).await?;
+        match &self.tooltip {
+            Some(tooltip) => {
+                true.encode(write).await?;
+                tooltip.encode(write).await
+            }
+            None => false.encode(write).await,
+        }
+    }
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#System_Chat_Message>
+#[client(resource = "system_chat", state = Play)]
+pub struct SystemChat {
+    content: Text,
+    overlay: bool,
+}
+
+impl SystemChat {
+    /// Create a chat message, shown in the chat box rather than above the hotbar.
+    pub fn new(content: impl Into<Text>) -> Self {
+        Self {
+            content: content.into(),
+            overlay: false,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::{prelude::*, text::Text};
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Disconnect_(play)>
+#[client(resource = "disconnect", state = Play)]
+pub struct Disconnect {
+    reason: Text,
+}
+
+impl Disconnect {
+    /// Create a disconnect packet, shown to the player on the disconnection screen.
+    pub fn new(reason: impl Into<Text>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{prelude::*, text::Text};
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Disconnect_(login)>
+#[client(resource = "login_disconnect", state = Login)]
+pub struct LoginDisconnect {
+    reason: Json<Text>,
+}
+
+impl LoginDisconnect {
+    /// Create a disconnect packet with a plain text reason.
+    pub fn new(reason: impl Into<Text>) -> Self {
+        Self {
+            reason: Json(reason.into()),
+        }
+    }
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Login_Plugin_Request>
+#[client(resource = "custom_query", state = Login)]
+pub struct LoginPluginRequest {
+    message_id: VarInt,
+    channel: String,
+    data: Remaining,
+}
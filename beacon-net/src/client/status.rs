@@ -1,6 +1,7 @@
 use serde::Serialize;
+use uuid::Uuid;
 
-use crate::{prelude::*, server::PingRequest};
+use crate::{player::PlayerIdentity, prelude::*, server::PingRequest};
 
 /// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Status_Response>
 #[client(resource = "status_response", state = Status)]
@@ -31,7 +32,7 @@ impl From<PingRequest> for PongResponse {
 }
 
 /// The version of Minecraft the server supports.
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Version {
     /// The name of the version, e.g. "1.21.1"
     pub name: String,
@@ -40,7 +41,7 @@ pub struct Version {
 }
 
 /// Information about the players on the server.
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Players {
     /// How many players can the server support at once.
     pub max: u32,
@@ -52,7 +53,7 @@ pub struct Players {
 }
 
 /// A player who is currently online on the server.  
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct SamplePlayer {
     /// The player's username.
     pub name: String,
@@ -60,19 +61,38 @@ pub struct SamplePlayer {
     pub id: String,
 }
 
+impl SamplePlayer {
+    /// Create an entry that is only a line of text, rather than a real player, for custom hover
+    /// text.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            name: text.into(),
+            id: Uuid::nil().hyphenated().to_string(),
+        }
+    }
+}
+
+impl From<&PlayerIdentity> for SamplePlayer {
+    fn from(player: &PlayerIdentity) -> Self {
+        Self {
+            name: player.name.clone(),
+            id: Uuid::from_u128(player.uuid).hyphenated().to_string(),
+        }
+    }
+}
+
 // todo: replace with text component
 // see: https://minecraft.wiki/w/Text_component_format
 
 /// A text component containing the server's MOTD.
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Description {
     /// The text of the MOTD.
     pub text: String,
 }
 
-// todo: cache response
 /// See: https://minecraft.wiki/w/Java_Edition_protocol/Server_List_Ping#Status_Response
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct StatusResponsePayload {
     /// The version of Minecraft the server supports.
     pub version: Version,
@@ -0,0 +1,120 @@
+use beacon_codec::encode::{Encode, EncodeError};
+use tokio::io::AsyncWrite;
+
+use crate::{
+    player::{GameMode, ProfileProperty},
+    prelude::*,
+    text::Text,
+};
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Player_Info_Update>
+#[client(resource = "player_info_update", state = Play)]
+pub struct PlayerInfoUpdate {
+    actions: u8,
+    players: Vec<PlayerInfo>,
+}
+
+impl PlayerInfoUpdate {
+    /// Add the players to the client's list of players.
+    pub const ADD_PLAYER: u8 = 0x01;
+    /// Change the players' game modes.
+    pub const UPDATE_GAME_MODE: u8 = 0x04;
+    /// Show or hide the players in the tab list.
+    pub const UPDATE_LISTED: u8 = 0x08;
+    /// Change the players' latency.
+    pub const UPDATE_LATENCY: u8 = 0x10;
+    /// Change the names the players are shown with in the tab list.
+    pub const UPDATE_DISPLAY_NAME: u8 = 0x20;
+
+    /// Create an update for the given players, with an action for each of the fields they have.
+    /// Every player must have the same fields set.
+    pub fn new(players: Vec<PlayerInfo>) -> Self {
+        let actions = players.first().map_or(0, PlayerInfo::actions);
+        Self { actions, players }
+    }
+}
+
+/// What a [PlayerInfoUpdate] changes about a player. Only the fields that are set are sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerInfo {
+    /// The player's UUID.
+    pub uuid: u128,
+    /// The player's name and profile properties, to add them to the list.
+    pub profile: Option<(String, Vec<ProfileProperty>)>,
+    /// The player's game mode.
+    pub game_mode: Option<GameMode>,
+    /// Whether the player is shown in the tab list.
+    pub listed: Option<bool>,
+    /// The player's latency, in milliseconds.
+    pub latency: Option<i32>,
+    /// The name to show the player with in the tab list, instead of their username.
+    pub display_name: Option<Option<Text>>,
+}
+
+impl PlayerInfo {
+    /// The actions needed to send the fields that are set.
+    fn actions(&self) -> u8 {
+        let mut actions = 0;
+        if self.profile.is_some() {
+            actions |= PlayerInfoUpdate::ADD_PLAYER;
+        }
+        if self.game_mode.is_some() {
+            actions |= PlayerInfoUpdate::UPDATE_GAME_MODE;
+        }
+        if self.listed.is_some() {
+            actions |= PlayerInfoUpdate::UPDATE_LISTED;
+        }
+        if self.latency.is_some() {
+            actions |= PlayerInfoUpdate::UPDATE_LATENCY;
+        }
+        if self.display_name.is_some() {
+            actions |= PlayerInfoUpdate::UPDATE_DISPLAY_NAME;
+        }
+        actions
+    }
+}
+
+impl Encode for PlayerInfo {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        self.uuid.encode(write).await?;
+        // each action's data is written in the order of the actions' bits
+        if let Some((name, properties)) = &self.profile {
+            name.encode(write).await?;
+            properties.encode(write).await?;
+        }
+        if let Some(game_mode) = self.game_mode {
+            VarInt(game_mode.id().into()).encode(write).await?;
+        }
+        if let Some(listed) = self.listed {
+            listed.encode(write).await?;
+        }
+        if let Some(latency) = self.latency {
+            VarInt(latency).encode(write).await?;
+        }
+        if let Some(display_name) = &self.display_name {
+            display_name.encode(write).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for ProfileProperty {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        self.name.encode(write).await?;
+        self.value.encode(write).await?;
+        self.signature.encode(write).await
+    }
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Player_Info_Remove>
+#[client(resource = "player_info_remove", state = Play)]
+pub struct PlayerInfoRemove {
+    players: Vec<u128>,
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Set_Tab_List_Header_And_Footer>
+#[client(resource = "tab_list", state = Play)]
+pub struct TabList {
+    header: Text,
+    footer: Text,
+}
@@ -0,0 +1,14 @@
+use crate::prelude::*;
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Set_Ticking_State>
+#[client(resource = "ticking_state", state = Play)]
+pub struct TickingState {
+    tick_rate: f32,
+    is_frozen: bool,
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Step_Tick>
+#[client(resource = "ticking_step", state = Play)]
+pub struct TickingStep {
+    tick_steps: VarInt,
+}
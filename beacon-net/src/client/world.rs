@@ -0,0 +1,29 @@
+use crate::prelude::*;
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Update_Time>
+#[client(resource = "set_time", state = Play)]
+pub struct SetTime {
+    world_age: i64,
+    time_of_day: i64,
+    time_of_day_increasing: bool,
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Game_Event>
+#[client(resource = "game_event", state = Play)]
+pub struct GameEvent {
+    event: u8,
+    value: f32,
+}
+
+impl GameEvent {
+    /// Rain starts falling.
+    pub const BEGIN_RAINING: u8 = 1;
+    /// Rain stops falling.
+    pub const END_RAINING: u8 = 2;
+    /// The player's game mode changes to the given ID.
+    pub const CHANGE_GAME_MODE: u8 = 3;
+    /// How heavily it is raining, from 0 to 1.
+    pub const RAIN_LEVEL_CHANGE: u8 = 7;
+    /// How heavily it is thundering, from 0 to 1.
+    pub const THUNDER_LEVEL_CHANGE: u8 = 8;
+}
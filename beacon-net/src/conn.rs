@@ -1,23 +1,254 @@
-use beacon_codec::ProtocolState;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
+
+use beacon_codec::{ProtocolState, encode::EncodeError};
+use beacon_config::{Config, OverflowPolicy};
 use bevy_ecs::prelude::*;
-use flume::{Receiver, Sender};
-use tokio_util::sync::CancellationToken;
+use flume::{Receiver, SendError, Sender, TrySendError};
+use thiserror::Error;
+
+use crate::{
+    client::{bundle::BundleDelimiter, connection::Disconnect, login::LoginDisconnect},
+    packet::{PacketData, RawPacket},
+    text::Text,
+};
+
+/// The most packets the client accepts in one bundle.
+const MAX_BUNDLE_SIZE: usize = 4096;
+
+/// Why a connection was closed.
+#[derive(Debug, Clone, Display, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The client closed the connection.
+    #[display("disconnected")]
+    Disconnected,
+    /// The server closed the connection.
+    #[display("kicked: {_0}")]
+    Kicked(String),
+    /// Reading from or writing to the socket failed.
+    #[display("error: {_0}")]
+    Error(String),
+}
 
-use crate::{observe_packets, packet::RawPacket};
+/// Event triggered on a connection entity just before it is despawned, because either the client
+/// or the server closed the connection.
+#[derive(EntityEvent, Debug)]
+pub struct ConnectionClosed {
+    /// The connection that was closed.
+    pub entity: Entity,
+    /// Why the connection was closed.
+    pub reason: CloseReason,
+}
+
+/// Where the connection task records why it closed the connection, before dropping its end of a
+/// channel.
+type ReasonSlot = Arc<OnceLock<CloseReason>>;
 
 /// Receiver for incoming packets.
 #[derive(Component, Deref)]
-pub struct PacketReceiver(Receiver<RawPacket>);
+pub struct PacketReceiver {
+    #[deref]
+    rx: Receiver<RawPacket>,
+    reason: ReasonSlot,
+}
+
+impl PacketReceiver {
+    /// Why the connection task closed the connection, if it has.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.reason.get()
+    }
+}
+
+/// Error that can occur when queueing an incoming packet.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QueueError {
+    /// The connection has been despawned.
+    #[error("connection closed")]
+    Closed,
+
+    /// The connection's queue is full, and the overflow policy is to kick.
+    #[error("too many packets waiting to be processed")]
+    Overflow,
+}
+
+/// Sender for incoming packets, used by the task reading from the socket.
+pub struct IncomingSender {
+    tx: Sender<RawPacket>,
+    policy: OverflowPolicy,
+    reason: ReasonSlot,
+}
+
+impl IncomingSender {
+    /// Queue a packet to be processed by the ECS, following the overflow policy if the queue is
+    /// full.
+    pub async fn send(&self, packet: RawPacket) -> Result<(), QueueError> {
+        match self.policy {
+            OverflowPolicy::Backpressure => self
+                .tx
+                .send_async(packet)
+                .await
+                .map_err(|_| QueueError::Closed),
+            policy => match self.tx.try_send(packet) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Disconnected(_)) => Err(QueueError::Closed),
+                Err(TrySendError::Full(packet)) if policy == OverflowPolicy::Drop => {
+                    debug!(id = %packet.id, "dropping packet, queue is full");
+                    Ok(())
+                }
+                Err(TrySendError::Full(_)) => Err(QueueError::Overflow),
+            },
+        }
+    }
+
+    /// Stop sending packets, so that the connection is despawned. The reason is only recorded if
+    /// the outgoing side has not already closed the connection.
+    pub fn close(self, reason: CloseReason) {
+        let _ = self.reason.set(reason);
+    }
+}
+
+/// Receiver for outgoing packets, batched per tick, used by the task writing to the socket.
+///
+/// Once the connection is despawned, every batch sent before then can still be received, after
+/// which receiving fails.
+#[derive(Deref)]
+pub struct OutgoingReceiver {
+    #[deref]
+    rx: Receiver<Vec<RawPacket>>,
+    reason: ReasonSlot,
+}
+
+impl OutgoingReceiver {
+    /// Stop receiving packets, so that the connection is despawned. The reason is only recorded if
+    /// the incoming side has not already closed the connection.
+    pub fn close(self, reason: CloseReason) {
+        let _ = self.reason.set(reason);
+    }
+}
+
+/// Create a bundle delimiter packet.
+fn delimiter() -> RawPacket {
+    RawPacket {
+        id: <BundleDelimiter as PacketData>::ID,
+        data: Default::default(),
+    }
+}
 
-// todo: stop using cancellation tokens and despawn when channel closes.
-// todo: make sure we don't write to a closed channel mid system.
 /// Sender for outgoing packets.
-#[derive(Component, Deref)]
-pub struct PacketSender(Sender<RawPacket>);
+///
+/// Packets are queued, and sent to the connection in one batch at the end of the tick. Closing the
+/// connection also waits until the end of the tick, so the queued packets are sent first.
+#[derive(Component)]
+pub struct PacketSender {
+    queue: Mutex<Vec<RawPacket>>,
+    tx: Sender<Vec<RawPacket>>,
+    closing: OnceLock<CloseReason>,
+}
 
-/// A cancellation token used to despawn a connection when it's closed.
-#[derive(Component, Deref)]
-pub struct Despawn(CancellationToken);
+impl PacketSender {
+    /// Queue a packet to be sent at the end of the tick.
+    pub fn send(&self, packet: RawPacket) -> Result<(), SendError<RawPacket>> {
+        if self.is_closed() {
+            return Err(SendError(packet));
+        }
+
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(packet);
+        Ok(())
+    }
+
+    /// Queue packets to be sent at the end of the tick as a bundle, so the client processes all of
+    /// them in the same tick. Should only be used in the Play state.
+    ///
+    /// Bundles of more than 4096 packets are split up, as the client would otherwise disconnect.
+    pub fn send_bundle(
+        &self,
+        packets: impl IntoIterator<Item = RawPacket>,
+    ) -> Result<(), SendError<Vec<RawPacket>>> {
+        let packets: Vec<_> = packets.into_iter().collect();
+        if self.is_closed() {
+            return Err(SendError(packets));
+        }
+
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut packets = packets.into_iter().peekable();
+        while packets.peek().is_some() {
+            queue.push(delimiter());
+            queue.extend(packets.by_ref().take(MAX_BUNDLE_SIZE));
+            queue.push(delimiter());
+        }
+        Ok(())
+    }
+
+    /// Send every queued packet to the connection.
+    pub(crate) fn flush(&mut self) -> Result<(), TrySendError<Vec<RawPacket>>> {
+        let queue = self.queue.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if queue.is_empty() {
+            return Ok(());
+        }
+
+        self.tx.try_send(std::mem::take(queue))
+    }
+
+    /// Close the connection at the end of the tick, after sending the packets queued so far. Only
+    /// the first reason given is kept.
+    pub fn close(&self, reason: CloseReason) {
+        let _ = self.closing.set(reason);
+    }
+
+    /// Show the client why it is being disconnected, if it is logging in or playing, then
+    /// [close](Self::close) the connection.
+    pub fn kick(&self, state: ProtocolState, reason: impl Into<Text>) -> Result<(), EncodeError> {
+        let reason = reason.into();
+        let packet = match state {
+            ProtocolState::Login => Some(LoginDisconnect::new(reason.clone()).blocking_raw()?),
+            ProtocolState::Play => Some(Disconnect::new(reason.clone()).blocking_raw()?),
+            _ => None,
+        };
+        if let Some(packet) = packet {
+            let _ = self.send(packet);
+        }
+        self.close(CloseReason::Kicked(reason.to_string()));
+        Ok(())
+    }
+
+    /// Whether the connection has been closed, or is closing at the end of the tick. Packets can no
+    /// longer be sent once it is.
+    pub fn is_closed(&self) -> bool {
+        self.closing.get().is_some() || self.tx.is_disconnected()
+    }
+
+    /// Whether the connection task has stopped receiving packets.
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.tx.is_disconnected()
+    }
+
+    /// Why the connection is closing at the end of the tick, if [close](Self::close) was called.
+    pub(crate) fn closing(&self) -> Option<&CloseReason> {
+        self.closing.get()
+    }
+}
+
+/// The address of the client, which is the real client address if the connection came through a
+/// proxy.
+#[derive(Component, Clone, Copy, Debug, Deref)]
+pub struct RemoteAddr(pub SocketAddr);
+
+/// What the client sent in its handshake.
+#[derive(Component, Clone, Debug)]
+pub struct HandshakeInfo {
+    /// The protocol version the client is using.
+    pub protocol_version: i32,
+    /// The hostname the client used to connect, without anything appended by proxies or mod
+    /// loaders.
+    pub hostname: String,
+    /// The port the client used to connect.
+    pub port: u16,
+}
 
 /// A connection to the server.
 #[derive(Bundle)]
@@ -26,29 +257,159 @@ pub struct Connection {
     receiver: PacketReceiver,
     sender: PacketSender,
     state: ProtocolState,
-    despawn: Despawn,
+    addr: RemoteAddr,
 }
 
 impl Connection {
     /// Spawn a new connection and add it to the world. Returns:
     /// - a sender for incoming packets
-    /// - a receiver for outgoing packets
-    /// - a cancellation token to despawn the connection when it's closed
-    pub fn spawn(world: &mut World) -> (Sender<RawPacket>, Receiver<RawPacket>, CancellationToken) {
+    /// - a receiver for outgoing packets, batched per tick
+    ///
+    /// The connection is despawned once either of them is closed or dropped, and dropping the
+    /// entity closes both of them.
+    pub fn spawn(world: &mut World, addr: SocketAddr) -> (IncomingSender, OutgoingReceiver) {
+        let network = &world.resource::<Config>().network;
+        let policy = network.overflow;
+
         // open channels
-        let (in_tx, in_rx) = flume::bounded(1024);
+        let (in_tx, in_rx) = flume::bounded(network.queue_size);
         let (out_tx, out_rx) = flume::bounded(1024);
-        let token = CancellationToken::new();
+        let reason = ReasonSlot::default();
 
         // spawn entity
-        let mut entity = world.spawn(Self {
-            receiver: PacketReceiver(in_rx),
-            sender: PacketSender(out_tx),
+        world.spawn(Self {
+            receiver: PacketReceiver {
+                rx: in_rx,
+                reason: reason.clone(),
+            },
+            sender: PacketSender {
+                queue: Mutex::default(),
+                tx: out_tx,
+                closing: OnceLock::new(),
+            },
             state: ProtocolState::default(),
-            despawn: Despawn(token.clone()),
+            addr: RemoteAddr(addr),
+        });
+
+        let incoming = IncomingSender {
+            tx: in_tx,
+            policy,
+            reason: reason.clone(),
+        };
+        let outgoing = OutgoingReceiver { rx: out_rx, reason };
+        (incoming, outgoing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::types::VarInt;
+    use beacon_config::OverflowPolicy;
+
+    use super::{
+        CloseReason, IncomingSender, MAX_BUNDLE_SIZE, OutgoingReceiver, PacketReceiver,
+        PacketSender, QueueError, ReasonSlot,
+    };
+    use crate::packet::RawPacket;
+
+    fn packet() -> RawPacket {
+        RawPacket {
+            id: VarInt(0),
+            data: Default::default(),
+        }
+    }
+
+    fn sender(tx: flume::Sender<RawPacket>, policy: OverflowPolicy) -> IncomingSender {
+        IncomingSender {
+            tx,
+            policy,
+            reason: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_kick() {
+        let (tx, _rx) = flume::bounded(1);
+        let sender = sender(tx, OverflowPolicy::Kick);
+        assert_eq!(sender.send(packet()).await, Ok(()));
+        assert_eq!(sender.send(packet()).await, Err(QueueError::Overflow));
+    }
+
+    #[tokio::test]
+    async fn test_overflow_drop() {
+        let (tx, rx) = flume::bounded(1);
+        let sender = sender(tx, OverflowPolicy::Drop);
+        assert_eq!(sender.send(packet()).await, Ok(()));
+        assert_eq!(sender.send(packet()).await, Ok(()));
+        assert_eq!(rx.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_overflow_backpressure() {
+        let (tx, rx) = flume::bounded(1);
+        let sender = sender(tx, OverflowPolicy::Backpressure);
+        assert_eq!(sender.send(packet()).await, Ok(()));
+
+        // the second send waits until the first packet has been processed
+        let (sent, _) = tokio::join!(sender.send(packet()), async {
+            tokio::task::yield_now().await;
+            rx.recv_async().await.ok()
+        });
+        assert_eq!(sent, Ok(()));
+        assert_eq!(rx.len(), 1);
+
+        drop(rx);
+        assert_eq!(sender.send(packet()).await, Err(QueueError::Closed));
+    }
+
+    #[test]
+    fn test_close_reason() {
+        let reason = ReasonSlot::default();
+        let (in_tx, in_rx) = flume::bounded(1);
+        let (_out_tx, out_rx) = flume::bounded::<Vec<RawPacket>>(1);
+        let incoming = IncomingSender {
+            tx: in_tx,
+            policy: OverflowPolicy::Kick,
+            reason: reason.clone(),
+        };
+        let outgoing = OutgoingReceiver {
+            rx: out_rx,
+            reason: reason.clone(),
+        };
+        let receiver = PacketReceiver { rx: in_rx, reason };
+
+        // the first side to close decides the reason
+        incoming.close(CloseReason::Disconnected);
+        outgoing.close(CloseReason::Error("broken pipe".into()));
+        assert!(receiver.is_disconnected());
+        assert_eq!(receiver.close_reason(), Some(&CloseReason::Disconnected));
+    }
+
+    #[test]
+    fn test_send_bundle() {
+        let (tx, rx) = flume::bounded(1);
+        let mut sender = PacketSender {
+            queue: Default::default(),
+            tx,
+            closing: Default::default(),
+        };
+        let packets = (0..MAX_BUNDLE_SIZE + 1).map(|_| RawPacket {
+            id: VarInt(1),
+            data: Default::default(),
         });
-        observe_packets(&mut entity);
+        sender.send_bundle(packets).unwrap();
+        sender.flush().unwrap();
 
-        (in_tx, out_rx, token)
+        // too many packets for one bundle, so they are split in two
+        let delimiters: Vec<_> = rx
+            .try_recv()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, packet)| packet.id == VarInt(0))
+            .map(|(i, _)| i)
+            .collect();
+        let end = MAX_BUNDLE_SIZE + 1;
+        assert_eq!(delimiters, [0, end, end + 1, end + 3]);
     }
 }
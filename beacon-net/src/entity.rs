@@ -0,0 +1,55 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::prelude::*;
+
+/// The type of an entity that is not a player, e.g. `minecraft:pig`.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Deref)]
+pub struct EntityType(pub String);
+
+/// An entity's position in the world.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    /// East (+) and west (-).
+    pub x: f64,
+    /// Up (+) and down (-).
+    pub y: f64,
+    /// South (+) and north (-).
+    pub z: f64,
+}
+
+impl Position {
+    /// Create a position from its coordinates.
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The straight line distance to another position, squared.
+    pub fn distance_squared(&self, other: &Self) -> f64 {
+        (self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2)
+    }
+}
+
+/// Where an entity is looking, in degrees.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rotation {
+    /// The horizontal angle, where 0 is south and 90 is west.
+    pub yaw: f32,
+    /// The vertical angle, where -90 is straight up and 90 is straight down.
+    pub pitch: f32,
+}
+
+/// A name given to an entity, e.g. with a name tag.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Deref)]
+pub struct CustomName(pub String);
+
+/// The scoreboard tags added to an entity with `/tag`.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Deref, DerefMut)]
+pub struct Tags(pub HashSet<String>);
+
+/// The scoreboard team an entity is on.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Deref)]
+pub struct Team(pub String);
+
+/// An entity's score for each scoreboard objective it has one in.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Deref, DerefMut)]
+pub struct Scores(pub HashMap<String, i32>);
@@ -0,0 +1,230 @@
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use beacon_codec::{
+    decode::{Decode, DecodeError},
+    types::VarInt,
+};
+use bevy_ecs::prelude::*;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::player::ProfileProperty;
+
+/// The login plugin channel Velocity forwards player information on.
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+
+/// The highest version of Velocity's forwarding format we understand, `MODERN_DEFAULT`.
+pub const VELOCITY_VERSION: u8 = 1;
+
+/// The length of the HMAC-SHA256 signature at the start of Velocity's response.
+const SIGNATURE_LENGTH: usize = 32;
+
+static NEXT_MESSAGE_ID: AtomicI32 = AtomicI32::new(0);
+
+/// Error that can occur when reading player information forwarded by a proxy.
+#[derive(Debug, Error)]
+pub enum ForwardingError {
+    /// The proxy did not forward any player information.
+    #[error("no player information was forwarded")]
+    Missing,
+
+    /// The forwarded player information could not be parsed.
+    #[error("invalid forwarded player information: {0}")]
+    Invalid(&'static str),
+
+    /// The signature did not match, so the proxy does not share our secret.
+    #[error("forwarded player information has an invalid signature")]
+    Signature,
+
+    /// The proxy used a version of modern forwarding we did not ask for.
+    #[error("unsupported forwarding version: {0}")]
+    Version(VarInt),
+
+    /// The forwarded player information could not be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Player information forwarded by a proxy.
+#[derive(Component, Debug, Clone)]
+pub struct Forwarded {
+    /// The player's real IP address.
+    pub ip: IpAddr,
+    /// The player's UUID.
+    pub uuid: u128,
+    /// The player's username, only forwarded by modern forwarding.
+    pub name: Option<String>,
+    /// The player's profile properties, such as their skin.
+    pub properties: Vec<ProfileProperty>,
+}
+
+/// Marks a connection that has been sent a `velocity:player_info` request, and is waiting for the
+/// response.
+#[derive(Component, Debug)]
+pub struct PendingForwarding {
+    /// The message ID of the request.
+    pub message_id: VarInt,
+}
+
+impl PendingForwarding {
+    /// Create a pending request with a new message ID.
+    pub fn new() -> Self {
+        Self {
+            message_id: VarInt(NEXT_MESSAGE_ID.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for PendingForwarding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse BungeeCord's legacy forwarding from a handshake's server address, in the form
+/// `host\0ip\0uuid\0properties`. Returns the original host and the forwarded information.
+pub fn parse_legacy(address: &str) -> Result<(&str, Forwarded), ForwardingError> {
+    let mut parts = address.split('\0');
+    let host = parts.next().unwrap_or_default();
+    let (Some(ip), Some(uuid)) = (parts.next(), parts.next()) else {
+        return Err(ForwardingError::Missing);
+    };
+
+    let ip = ip
+        .parse()
+        .map_err(|_| ForwardingError::Invalid("ip address"))?;
+    let uuid = u128::from_str_radix(&uuid.replace('-', ""), 16)
+        .map_err(|_| ForwardingError::Invalid("uuid"))?;
+    let properties = match parts.next() {
+        Some(json) => {
+            serde_json::from_str(json).map_err(|_| ForwardingError::Invalid("properties"))?
+        }
+        None => Vec::new(),
+    };
+
+    let forwarded = Forwarded {
+        ip,
+        uuid,
+        name: None,
+        properties,
+    };
+    Ok((host, forwarded))
+}
+
+/// Verify and parse Velocity's modern forwarding response, which starts with an HMAC-SHA256
+/// signature of the rest of the data using the shared secret.
+///
+/// See: <https://docs.papermc.io/velocity/player-information-forwarding>
+pub fn parse_modern(secret: &[u8], data: &[u8]) -> Result<Forwarded, ForwardingError> {
+    if data.len() < SIGNATURE_LENGTH {
+        return Err(ForwardingError::Invalid("too short"));
+    }
+    let (signature, mut payload) = data.split_at(SIGNATURE_LENGTH);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("hmac accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(signature)
+        .map_err(|_| ForwardingError::Signature)?;
+
+    futures::executor::block_on(async {
+        let version = VarInt::decode(&mut payload).await?;
+        if *version < 1 || *version > VELOCITY_VERSION as i32 {
+            return Err(ForwardingError::Version(version));
+        }
+
+        let ip = String::decode(&mut payload)
+            .await?
+            .parse()
+            .map_err(|_| ForwardingError::Invalid("ip address"))?;
+        let uuid = u128::decode(&mut payload).await?;
+        let name = String::decode(&mut payload).await?;
+
+        let count = VarInt::decode(&mut payload).await?;
+        let mut properties = Vec::new();
+        for _ in 0..*count {
+            properties.push(ProfileProperty {
+                name: String::decode(&mut payload).await?,
+                value: String::decode(&mut payload).await?,
+                signature: Option::<String>::decode(&mut payload).await?,
+            });
+        }
+
+        Ok(Forwarded {
+            ip,
+            uuid,
+            name: Some(name),
+            properties,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::{encode::Encode, types::VarInt};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{ForwardingError, parse_legacy, parse_modern};
+
+    #[test]
+    fn test_legacy() {
+        let address = [
+            "play.example.com",
+            "203.0.113.7",
+            "069a79f444e94726a5befca90e38aaf5",
+            r#"[{"name":"textures","value":"abc","signature":"def"}]"#,
+        ]
+        .join("\0");
+        let (host, forwarded) = parse_legacy(&address).unwrap();
+        assert_eq!(host, "play.example.com");
+        assert_eq!(forwarded.ip.to_string(), "203.0.113.7");
+        assert_eq!(forwarded.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        assert_eq!(forwarded.properties[0].name, "textures");
+        assert_eq!(forwarded.properties[0].signature.as_deref(), Some("def"));
+
+        assert!(matches!(
+            parse_legacy("play.example.com"),
+            Err(ForwardingError::Missing)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_modern() {
+        let mut payload = Vec::new();
+        VarInt(1).encode(&mut payload).await.unwrap();
+        "203.0.113.7"
+            .to_string()
+            .encode(&mut payload)
+            .await
+            .unwrap();
+        0x069a79f444e94726a5befca90e38aaf5u128
+            .encode(&mut payload)
+            .await
+            .unwrap();
+        "Notch".to_string().encode(&mut payload).await.unwrap();
+        VarInt(1).encode(&mut payload).await.unwrap();
+        "textures".to_string().encode(&mut payload).await.unwrap();
+        "abc".to_string().encode(&mut payload).await.unwrap();
+        None::<String>.encode(&mut payload).await.unwrap();
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(&payload);
+        let mut data = mac.finalize().into_bytes().to_vec();
+        data.extend(payload);
+
+        let forwarded = parse_modern(b"secret", &data).unwrap();
+        assert_eq!(forwarded.ip.to_string(), "203.0.113.7");
+        assert_eq!(forwarded.name.as_deref(), Some("Notch"));
+        assert_eq!(forwarded.properties[0].value, "abc");
+        assert_eq!(forwarded.properties[0].signature, None);
+
+        assert!(matches!(
+            parse_modern(b"wrong", &data),
+            Err(ForwardingError::Signature)
+        ));
+    }
+}
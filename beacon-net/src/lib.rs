@@ -2,14 +2,15 @@
 //!
 //! This crate contains Minecraft protocol packet definitions and utilities for encoding/decoding them.
 
-use beacon_codec::{ProtocolState, decode::Decode};
+use beacon_codec::ProtocolState;
+use beacon_config::Config;
 use bevy_ecs::prelude::*;
-use futures::executor::block_on;
 
 use crate::server::*;
 use crate::{
-    conn::{Despawn, PacketReceiver},
-    packet::PacketData,
+    conn::{CloseReason, ConnectionClosed, PacketReceiver, PacketSender},
+    registry::PacketRegistry,
+    text::Text,
 };
 
 #[macro_use]
@@ -17,29 +18,127 @@ extern crate derive_more;
 #[macro_use]
 extern crate tracing;
 
-macro_rules! dispatch {
-    ($packet:ident, $raw:expr, $entity:expr, $commands:expr) => {
-        match block_on($packet::decode(&mut $raw.data.as_ref())) {
-            Ok(packet) => $commands.trigger(packet.event($entity)),
-            Err(err) => {
-                $commands.entity($entity).despawn();
-                error!(%err, "failed to decode packet");
-            }
+/// System sets for networking systems.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NetworkSet {
+    /// Reads incoming packets, triggers their handlers, and despawns closed connections.
+    Receive,
+    /// Sends every packet queued during the tick to its connection, and closes connections the
+    /// server has closed.
+    Flush,
+}
+
+/// Register all networking systems and built-in packet handlers with the ECS.
+pub fn ecs(world: &mut World, schedule: &mut Schedule) {
+    world.init_resource::<PacketRegistry>();
+    world.init_resource::<StatusCache>();
+    register_packets(world);
+
+    schedule.add_systems((
+        (StatusCache::update, listen, despawn)
+            .chain()
+            .in_set(NetworkSet::Receive),
+        flush.in_set(NetworkSet::Flush),
+    ));
+}
+
+/// Despawn connections whose task has closed either of their channels.
+fn despawn(mut commands: Commands, query: Query<(Entity, &PacketReceiver, &PacketSender)>) {
+    for (entity, receiver, sender) in query.iter() {
+        if receiver.is_disconnected() || sender.is_disconnected() {
+            let reason = receiver
+                .close_reason()
+                .cloned()
+                .unwrap_or(CloseReason::Disconnected);
+            close(&mut commands, entity, reason);
         }
-    };
+    }
+}
+
+/// Send all packets queued during the tick, then despawn connections the server has closed, which
+/// lets the task finish writing and close the socket.
+fn flush(mut commands: Commands, mut query: Query<(Entity, &mut PacketSender)>) {
+    for (entity, mut sender) in query.iter_mut() {
+        if let Err(err) = sender.flush() {
+            // the connection is closed or not keeping up
+            debug!(%err, "failed to flush packets");
+            sender.close(CloseReason::Kicked("not keeping up with packets".into()));
+        }
+
+        if let Some(reason) = sender.closing() {
+            close(&mut commands, entity, reason.clone());
+        }
+    }
+}
+
+/// Kick every connection, including those still logging in, then send them their last packets and
+/// despawn them, so that their tasks can finish writing and close their sockets. Used when the
+/// server stops.
+pub fn close_all(world: &mut World, reason: Text) {
+    let mut query = world.query::<(&ProtocolState, &PacketSender)>();
+    for (state, sender) in query.iter(world) {
+        if !sender.is_closed() {
+            let _ = sender.kick(*state, reason.clone());
+        }
+    }
+    let _ = world.run_system_cached(flush);
 }
 
-/// Register all networking systems with the ECS.
-pub fn ecs(schedule: &mut Schedule) {
-    schedule.add_systems((listen, despawn));
+/// Notify observers that a connection has closed, and despawn it.
+fn close(commands: &mut Commands, entity: Entity, reason: CloseReason) {
+    debug!(%entity, %reason, "despawning connection");
+    commands.trigger(ConnectionClosed { entity, reason });
+    commands.entity(entity).despawn();
 }
 
-/// Despawn closed connections.
-fn despawn(mut commands: Commands, query: Query<(Entity, &Despawn)>) {
-    for (entity, cancel) in query.iter() {
-        if cancel.is_cancelled() {
-            commands.entity(entity).despawn();
+/// Listen for incoming packets and trigger events for them.
+///
+/// Connections take turns, one packet at a time, so that a connection sending lots of packets
+/// cannot starve the others. Each connection is limited to `packets-per-tick` packets every tick,
+/// and any others are left in its queue until the next tick.
+fn listen(
+    query: Query<(Entity, &ProtocolState, &PacketReceiver, &PacketSender)>,
+    registry: Res<PacketRegistry>,
+    config: Res<Config>,
+    mut commands: Commands,
+) {
+    let connections = query.iter().collect();
+    round_robin(
+        connections,
+        config.network.packets_per_tick,
+        |&mut (entity, state, rx, sender)| {
+            // ignore anything else sent by connections that are closing
+            if sender.is_closed() {
+                return false;
+            }
+            let Ok(packet) = rx.try_recv() else {
+                return false;
+            };
+            let Some(decode) = registry.decoder(*state, packet.id) else {
+                warn!(id = %packet.id, state = %state, "unknown packet");
+                return true;
+            };
+
+            if let Err(err) = decode(&packet, entity, &mut commands) {
+                error!(%err, "failed to decode packet");
+                sender.close(CloseReason::Kicked(format!("invalid packet: {err}")));
+                return false;
+            }
+
+            // handshake must be processed before any other subsequent packets.
+            *state != ProtocolState::Handshake
+        },
+    );
+}
+
+/// Call `read` for each connection in turn, until every connection has been read `budget` times or
+/// `read` has returned false for it.
+fn round_robin<T>(mut connections: Vec<T>, budget: u32, mut read: impl FnMut(&mut T) -> bool) {
+    for _ in 0..budget {
+        if connections.is_empty() {
+            break;
         }
+        connections.retain_mut(&mut read);
     }
 }
 
@@ -49,51 +148,21 @@ macro_rules! packets {
             $packet:ident
         )*
     ) => {
-        /// Listen for incoming packets and trigger events for them.
-        fn listen(
-            mut query: Query<(Entity, &ProtocolState, &PacketReceiver)>,
-            mut commands: Commands,
-        ) -> Result<()> {
-            // todo: take turns reading packets
-            for (entity, state, rx) in query.iter_mut() {
-                while let Ok(packet) = rx.try_recv() {
-                    match (state, packet.id) {
-                        (&Handshake::STATE, Handshake::ID) => {
-                            // handshake must be processed before any other subsequent packets.
-                            dispatch!(Handshake, packet, entity, commands);
-                            break;
-                        },
-                        $(
-                            (&$packet::STATE, $packet::ID) => {
-                                // todo: close connection on error
-                                dispatch!($packet, packet, entity, commands);
-                            },
-                        )*
-                        _ => {
-                            // unknown packet
-                            warn!(id = %packet.id, state = %state, "unknown packet");
-                        }
-                    }
-                }
-            }
-
-            Ok(())
-        }
-
-        /// Add handlers for all packets to an entity.
-        fn observe_packets(entity: &mut EntityWorldMut) {
-            entity.observe(Handshake::handle);
+        /// Register the built-in packets and their handlers.
+        fn register_packets(world: &mut World) {
             $(
-                entity.observe($packet::handle);
+                PacketRegistry::add_handler(world, $packet::handle);
             )*
         }
     };
 }
 
 packets! {
+    Handshake
     StatusRequest
     PingRequest
     LoginStart
+    LoginPluginResponse
 }
 
 /// Re-export everything from a module.
@@ -107,22 +176,69 @@ macro_rules! import {
 }
 
 /// Clientbound packets.
-mod client {
+pub mod client {
+    /// Bundle packets.
+    pub mod bundle;
+    /// Command and chat packets.
+    pub mod command;
+    /// Play state connection packets.
+    pub mod connection;
+    /// Login packets.
+    pub mod login;
+    /// Server list ping packets.
     pub mod status;
+    /// Tab list packets, which list the players online.
+    pub mod tab_list;
+    /// Tick rate packets.
+    pub mod tick;
+    /// World state packets, such as the time and weather.
+    pub mod world;
 }
 /// Serverbound packets.
-mod server {
-    import!(handshake, status, login);
+pub mod server {
+    import!(handshake, status, login, command);
 }
 /// Connection components.
 pub mod conn;
+/// Entity components.
+pub mod entity;
+/// Player information forwarded by Velocity or BungeeCord.
+pub mod forwarding;
 /// Packet definitions and utilities.
 pub mod packet;
 /// Player components.
 pub mod player;
+/// Runtime registry of serverbound packets and their handlers.
+pub mod registry;
+/// Text components.
+pub mod text;
+/// Writing outgoing packets to sockets.
+pub mod writer;
 
 mod prelude {
     pub use beacon_codec::types::*;
     pub use beacon_macros::{client, handler, server};
     pub use bevy_ecs::prelude::*;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::round_robin;
+
+    #[test]
+    fn test_round_robin() {
+        // (name, packets queued)
+        let connections = vec![('a', 5), ('b', 1), ('c', 3)];
+        let mut order = Vec::new();
+        round_robin(connections, 3, |(name, queued)| {
+            if *queued == 0 {
+                return false;
+            }
+            *queued -= 1;
+            order.push(*name);
+            true
+        });
+
+        assert_eq!(order, ['a', 'b', 'c', 'a', 'c', 'a', 'c']);
+    }
+}
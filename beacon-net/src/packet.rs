@@ -8,24 +8,49 @@ use bevy_ecs::prelude::*;
 use bytes::{Bytes, BytesMut};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+/// The largest a packet can be, not counting its length: the most a 3-byte VarInt can hold.
+const MAX_PACKET_SIZE: usize = (1 << 21) - 1;
+
 /// A raw packet, before any processing is done.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RawPacket {
     pub(crate) id: VarInt,
     pub(crate) data: Bytes,
 }
 
+impl RawPacket {
+    /// Create a raw packet from its ID and encoded data.
+    pub fn new(id: VarInt, data: Bytes) -> Self {
+        Self { id, data }
+    }
+
+    /// Returns the size of the packet's ID and data, as sent over the network.
+    pub fn size(&self) -> usize {
+        self.id.size() + self.data.len()
+    }
+
+    /// Encode the packet's length and ID, which come before its data, appending them to a buffer.
+    pub(crate) fn put_header(&self, buf: &mut Vec<u8>) {
+        VarInt(self.size() as i32).put(buf);
+        self.id.put(buf);
+    }
+}
+
 impl Decode for RawPacket {
     async fn decode<R: AsyncRead + Unpin>(read: &mut R) -> Result<Self, DecodeError> {
-        // read header
+        // read header, checking the length before anything is allocated for the data
         let length = VarInt::decode(read).await?;
+        if !(0..=MAX_PACKET_SIZE as i32).contains(&*length) {
+            return Err(DecodeError::InvalidPacketLength(length));
+        }
         let id = VarInt::decode(read).await?;
 
         // read data
-        let remaining = *length as usize - id.size();
-        let mut reader = read.take(remaining as u64);
-        let mut data = BytesMut::with_capacity(remaining);
-        reader.read_buf(&mut data).await?;
+        let remaining = (*length as usize)
+            .checked_sub(id.size())
+            .ok_or(DecodeError::InvalidPacketLength(length))?;
+        let mut data = BytesMut::zeroed(remaining);
+        read.read_exact(&mut data).await?;
 
         Ok(Self {
             id,
@@ -36,18 +61,26 @@ impl Decode for RawPacket {
 
 impl Encode for RawPacket {
     async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
-        let mut buf = Vec::new();
-        self.id.encode(&mut buf).await?;
+        let mut buf = Vec::with_capacity(10 + self.data.len());
+        self.put_header(&mut buf);
         buf.extend_from_slice(&self.data);
-
-        let length = VarInt(buf.len() as i32);
-        length.encode(write).await?;
         write.write_all(&buf).await?;
 
         Ok(())
     }
 }
 
+/// Event triggered on a connection entity when a packet is received from it.
+///
+/// See [PacketRegistry](crate::registry::PacketRegistry) for how to handle packets.
+#[derive(EntityEvent)]
+pub struct PacketEvent<P: Send + Sync + 'static> {
+    /// The connection the packet was received from.
+    pub entity: Entity,
+    /// The packet.
+    pub packet: P,
+}
+
 /// Trait containing packet metadata.
 pub trait PacketData {
     /// The packet ID.
@@ -56,3 +89,31 @@ pub trait PacketData {
     /// The protocol state this packet belongs to.
     const STATE: ProtocolState;
 }
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::{decode::*, types::VarInt};
+
+    use super::{MAX_PACKET_SIZE, RawPacket};
+
+    async fn decode(mut bytes: &[u8]) -> Result<RawPacket, DecodeError> {
+        RawPacket::decode(&mut bytes).await
+    }
+
+    #[tokio::test]
+    async fn test_decode_length() {
+        let packet = decode(&[3, 1, 2, 3]).await.unwrap();
+        assert_eq!(packet.id, VarInt(1));
+        assert_eq!(packet.data.as_ref(), [2, 3]);
+
+        // too big, negative, and too small for the id
+        let mut too_big = Vec::new();
+        VarInt(MAX_PACKET_SIZE as i32 + 1).put(&mut too_big);
+        for bytes in [&too_big[..], &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F], &[1, 0x80, 1]] {
+            assert!(matches!(
+                decode(bytes).await,
+                Err(DecodeError::InvalidPacketLength(_))
+            ));
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use bevy_ecs::prelude::*;
+use beacon_codec::ProtocolState;
+use bevy_ecs::{prelude::*, system::SystemParam};
+use rand::seq::IteratorRandom;
+use serde::Deserialize;
 
 /// The player's identity, containing their username and UUID.
 #[derive(Debug, Component)]
@@ -7,4 +10,94 @@ pub struct PlayerIdentity {
     pub name: String,
     /// The player's UUID.
     pub uuid: u128,
+    /// The player's profile properties, such as their skin.
+    pub properties: Vec<ProfileProperty>,
+}
+
+/// A property of a player's profile, such as their skin textures.
+///
+/// See: <https://minecraft.wiki/w/Mojang_API#Query_player's_skin_and_cape>
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProfileProperty {
+    /// The name of the property, e.g. "textures".
+    pub name: String,
+    /// The value of the property, usually base64 encoded.
+    pub value: String,
+    /// Mojang's signature of the value, if it has been signed.
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+/// A player's game mode.
+///
+/// See: <https://minecraft.wiki/w/Game_mode>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Component)]
+pub enum GameMode {
+    /// Players can be hurt, and must gather resources.
+    #[default]
+    Survival,
+    /// Players can fly, are invulnerable, and have unlimited resources.
+    Creative,
+    /// Like survival, but blocks can only be broken with the right tools.
+    Adventure,
+    /// Players fly through blocks and cannot interact with the world.
+    Spectator,
+}
+
+impl GameMode {
+    /// Every game mode, in the order of their IDs.
+    pub const ALL: [Self; 4] = [
+        Self::Survival,
+        Self::Creative,
+        Self::Adventure,
+        Self::Spectator,
+    ];
+
+    /// The game mode's ID in the protocol.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// The game mode's name, as used in commands.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Survival => "survival",
+            Self::Creative => "creative",
+            Self::Adventure => "adventure",
+            Self::Spectator => "spectator",
+        }
+    }
+
+    /// Find a game mode by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+/// The players currently online, which are the connections in the Play state that have logged in.
+///
+/// Status pings and connections that are still logging in are not counted.
+#[derive(SystemParam)]
+pub struct OnlinePlayers<'w, 's> {
+    query: Query<'w, 's, (&'static PlayerIdentity, &'static ProtocolState)>,
+}
+
+impl OnlinePlayers<'_, '_> {
+    /// Iterate over every online player.
+    pub fn iter(&self) -> impl Iterator<Item = &PlayerIdentity> {
+        self.query
+            .iter()
+            .filter(|(_, state)| **state == ProtocolState::Play)
+            .map(|(id, _)| id)
+    }
+
+    /// Returns how many players are online.
+    pub fn count(&self) -> u32 {
+        self.iter().count() as u32
+    }
+
+    /// Pick up to `amount` online players at random.
+    pub fn sample(&self, amount: usize) -> Vec<&PlayerIdentity> {
+        self.iter().choose_multiple(&mut rand::rng(), amount)
+    }
 }
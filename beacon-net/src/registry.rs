@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use beacon_codec::{ProtocolState, decode::*, types::VarInt};
+use bevy_ecs::{prelude::*, system::IntoObserverSystem};
+use futures::executor::block_on;
+
+use crate::packet::{PacketData, PacketEvent, RawPacket};
+
+/// Decodes a raw packet and triggers a [PacketEvent] for it.
+type Decoder = fn(&RawPacket, Entity, &mut Commands) -> Result<(), DecodeError>;
+
+/// Registry of the serverbound packets the server understands, keyed by protocol state and
+/// packet ID.
+///
+/// Packets that are not registered are ignored with a warning. For each registered packet, a
+/// [PacketEvent] is triggered on the connection entity when it is received, which handlers observe.
+#[derive(Resource, Default)]
+pub struct PacketRegistry {
+    decoders: HashMap<(ProtocolState, VarInt), Decoder>,
+    handlers: HashMap<(ProtocolState, VarInt), Vec<Entity>>,
+}
+
+impl PacketRegistry {
+    /// Register a serverbound packet, so that a [PacketEvent] is triggered when it is received.
+    /// Registering a packet twice has no effect.
+    pub fn register<P>(&mut self) -> &mut Self
+    where
+        P: PacketData + Decode + Send + Sync + 'static,
+    {
+        self.decoders.insert((P::STATE, P::ID), decode::<P>);
+        self
+    }
+
+    /// Whether a packet has been registered for the given protocol state and packet ID.
+    pub fn is_registered(&self, state: ProtocolState, id: VarInt) -> bool {
+        self.decoders.contains_key(&(state, id))
+    }
+
+    /// Get the decoder for a packet, if it has been registered.
+    pub(crate) fn decoder(&self, state: ProtocolState, id: VarInt) -> Option<Decoder> {
+        self.decoders.get(&(state, id)).copied()
+    }
+
+    /// Register a packet, and add a handler that observes every [PacketEvent] for it. Handlers run
+    /// alongside any other handlers for the same packet.
+    ///
+    /// Returns the entity of the observer.
+    pub fn add_handler<P, B: Bundle, M>(
+        world: &mut World,
+        handler: impl IntoObserverSystem<PacketEvent<P>, B, M>,
+    ) -> Entity
+    where
+        P: PacketData + Decode + Send + Sync + 'static,
+    {
+        let observer = world.add_observer(handler).id();
+
+        let mut registry = world.get_resource_or_init::<Self>();
+        registry.register::<P>();
+        registry
+            .handlers
+            .entry((P::STATE, P::ID))
+            .or_default()
+            .push(observer);
+
+        observer
+    }
+
+    /// Remove every existing handler for a packet, including the built-in ones, and replace them
+    /// with the given handler.
+    ///
+    /// Returns the entity of the observer.
+    pub fn replace_handlers<P, B: Bundle, M>(
+        world: &mut World,
+        handler: impl IntoObserverSystem<PacketEvent<P>, B, M>,
+    ) -> Entity
+    where
+        P: PacketData + Decode + Send + Sync + 'static,
+    {
+        let existing = world
+            .get_resource_mut::<Self>()
+            .and_then(|mut registry| registry.handlers.remove(&(P::STATE, P::ID)))
+            .unwrap_or_default();
+        for observer in existing {
+            world.despawn(observer);
+        }
+
+        Self::add_handler(world, handler)
+    }
+}
+
+fn decode<P>(raw: &RawPacket, entity: Entity, commands: &mut Commands) -> Result<(), DecodeError>
+where
+    P: Decode + Send + Sync + 'static,
+{
+    let packet = block_on(P::decode(&mut raw.data.as_ref()))?;
+    commands.trigger(PacketEvent { entity, packet });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use beacon_codec::{ProtocolState, decode::*, types::VarInt};
+    use beacon_config::Config;
+    use bevy_ecs::prelude::*;
+    use bytes::Bytes;
+    use tokio::io::AsyncRead;
+
+    use super::PacketRegistry;
+    use crate::{
+        conn::Connection,
+        packet::{PacketData, PacketEvent, RawPacket},
+        server::PingRequest,
+    };
+
+    /// A packet the server does not know about.
+    struct Custom(u8);
+
+    impl PacketData for Custom {
+        const ID: VarInt = VarInt(0x7F);
+        const STATE: ProtocolState = ProtocolState::Status;
+    }
+
+    impl Decode for Custom {
+        async fn decode<R: AsyncRead + Unpin>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(Self(u8::decode(read).await?))
+        }
+    }
+
+    /// The handlers that have run.
+    #[derive(Resource, Default)]
+    struct Handled(Vec<&'static str>);
+
+    #[tokio::test]
+    async fn test_handlers() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<Handled>();
+        crate::ecs(&mut world, &mut schedule);
+
+        // a new packet, with two handlers
+        assert!(
+            !world
+                .resource::<PacketRegistry>()
+                .is_registered(Custom::STATE, Custom::ID)
+        );
+        PacketRegistry::add_handler(
+            &mut world,
+            |event: On<PacketEvent<Custom>>, mut handled: ResMut<Handled>| {
+                assert_eq!(event.packet.0, 42);
+                handled.0.push("custom");
+            },
+        );
+        PacketRegistry::add_handler(
+            &mut world,
+            |_: On<PacketEvent<Custom>>, mut handled: ResMut<Handled>| {
+                handled.0.push("listener");
+            },
+        );
+        assert!(
+            world
+                .resource::<PacketRegistry>()
+                .is_registered(Custom::STATE, Custom::ID)
+        );
+
+        // the built-in ping handler, which would answer with a pong, is replaced
+        PacketRegistry::replace_handlers(
+            &mut world,
+            |_: On<PacketEvent<PingRequest>>, mut handled: ResMut<Handled>| {
+                handled.0.push("ping");
+            },
+        );
+
+        let (incoming, outgoing) =
+            Connection::spawn(&mut world, "127.0.0.1:25565".parse().unwrap());
+        let mut states = world.query::<&mut ProtocolState>();
+        *states.single_mut(&mut world).unwrap() = ProtocolState::Status;
+        incoming
+            .send(RawPacket::new(Custom::ID, Bytes::from_static(&[42])))
+            .await
+            .unwrap();
+        incoming
+            .send(RawPacket::new(PingRequest::ID, Bytes::from_static(&[0; 8])))
+            .await
+            .unwrap();
+        schedule.run(&mut world);
+
+        let mut handled = world.remove_resource::<Handled>().unwrap().0;
+        handled.sort();
+        assert_eq!(handled, ["custom", "listener", "ping"]);
+        assert!(outgoing.try_recv().is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use crate::prelude::*;
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Chat_Command>
+#[server(resource = "chat_command", state = Play)]
+#[derive(Debug)]
+pub struct ChatCommand {
+    command: String,
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Signed_Chat_Command>
+#[server(resource = "chat_command_signed", state = Play)]
+#[derive(Debug)]
+pub struct ChatCommandSigned {
+    command: String,
+    // the timestamp, salt, argument signatures and acknowledged messages, which are not checked
+    // since secure chat is not supported
+    signatures: Remaining,
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Command_Suggestions_Request>
+#[server(resource = "command_suggestion", state = Play)]
+#[derive(Debug)]
+pub struct CommandSuggestion {
+    transaction_id: VarInt,
+    text: String,
+}
@@ -1,6 +1,12 @@
 use beacon_codec::{ProtocolState};
+use beacon_config::{Config, ForwardingMode};
 
-use crate::prelude::*;
+use crate::{
+    client::login::LoginDisconnect,
+    conn::{CloseReason, HandshakeInfo, PacketSender, RemoteAddr},
+    forwarding,
+    prelude::*,
+};
 
 /// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Handshake>
 #[server(resource = "intention", state = Handshake)]
@@ -13,8 +19,51 @@ pub struct Handshake {
 }
 
 #[handler(Handshake)]
-fn handle(mut query: Query<&mut ProtocolState>) {
-    if let Ok(mut state) = query.get_mut(event.entity) {
-        *state = event.packet.intent;
+fn handle(
+    config: Res<Config>,
+    mut commands: Commands,
+    mut query: Query<(&mut ProtocolState, &mut RemoteAddr, &PacketSender)>,
+) -> Result<()> {
+    let (mut state, mut addr, sender) = query.get_mut(event.entity)?;
+    *state = event.packet.intent;
+
+    // bungeecord and forge append data to the hostname after a null byte, and some clients keep
+    // the trailing dot of a fully qualified name
+    let address = &event.packet.server_address;
+    let hostname = address.split('\0').next().unwrap_or_default().trim_end_matches('.');
+    commands.entity(event.entity).insert(HandshakeInfo {
+        protocol_version: *event.packet.protocol_version,
+        hostname: hostname.to_string(),
+        port: event.packet.server_port,
+    });
+
+    // refuse clients that connected with a hostname no virtual host matches
+    if config.host(Some(hostname)).is_none() {
+        if *state != ProtocolState::Status {
+            let reason = "Unknown hostname, please connect using a valid address.";
+            sender.send(LoginDisconnect::new(reason).blocking_raw()?)?;
+        }
+        sender.close(CloseReason::Kicked(format!("unknown hostname {hostname:?}")));
+        return Ok(());
+    }
+
+    // bungeecord forwards player information in the server address, when logging in
+    if config.forwarding.mode != ForwardingMode::Legacy || *state == ProtocolState::Status {
+        return Ok(());
+    }
+    match forwarding::parse_legacy(address) {
+        Ok((_, forwarded)) => {
+            addr.0.set_ip(forwarded.ip);
+            commands.entity(event.entity).insert(forwarded);
+        }
+        Err(err) => {
+            warn!(addr = %addr.0, %err, "refusing login without bungeecord forwarding");
+            let reason =
+                "If you wish to use IP forwarding, please enable it in your BungeeCord config as well!";
+            sender.send(LoginDisconnect::new(reason).blocking_raw()?)?;
+            sender.close(CloseReason::Kicked(err.to_string()));
+        }
     }
+
+    Ok(())
 }
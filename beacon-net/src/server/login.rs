@@ -1,4 +1,12 @@
-use crate::{player::PlayerIdentity, prelude::*};
+use beacon_config::{Config, ForwardingMode};
+
+use crate::{
+    client::login::{LoginDisconnect, LoginPluginRequest},
+    conn::{CloseReason, PacketSender, RemoteAddr},
+    forwarding::{self, Forwarded, ForwardingError, PendingForwarding},
+    player::PlayerIdentity,
+    prelude::*,
+};
 
 #[server(resource = "hello", state = Login)]
 pub struct LoginStart {
@@ -7,9 +15,92 @@ pub struct LoginStart {
 }
 
 #[handler(LoginStart)]
-fn handle(mut commands: Commands) {
-    let id = PlayerIdentity { name: event.packet.name.clone(), uuid: event.packet.uuid };
-    commands.entity(event.entity).insert(id);
+fn handle(
+    config: Res<Config>,
+    mut commands: Commands,
+    query: Query<(&PacketSender, Option<&Forwarded>)>,
+) -> Result<()> {
+    let (sender, forwarded) = query.get(event.entity)?;
+    let mut entity = commands.entity(event.entity);
+
+    // ask velocity for the player's information, and wait for the response
+    if config.forwarding.mode == ForwardingMode::Modern {
+        let pending = PendingForwarding::new();
+        let request = LoginPluginRequest {
+            message_id: pending.message_id,
+            channel: forwarding::VELOCITY_CHANNEL.to_string(),
+            data: Remaining(vec![forwarding::VELOCITY_VERSION]),
+        };
+        sender.send(request.blocking_raw()?)?;
+        entity.insert(pending);
+        return Ok(());
+    }
+
+    let id = match forwarded {
+        Some(forwarded) => PlayerIdentity {
+            name: event.packet.name.clone(),
+            uuid: forwarded.uuid,
+            properties: forwarded.properties.clone(),
+        },
+        None => PlayerIdentity {
+            name: event.packet.name.clone(),
+            uuid: event.packet.uuid,
+            properties: Vec::new(),
+        },
+    };
+    entity.remove::<Forwarded>().insert(id);
 
     // todo: respond with EncryptionRequest
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Login_Plugin_Response>
+#[server(resource = "custom_query_answer", state = Login)]
+pub struct LoginPluginResponse {
+    message_id: VarInt,
+    data: Option<Remaining>
+}
+
+#[handler(LoginPluginResponse)]
+fn handle(
+    config: Res<Config>,
+    mut commands: Commands,
+    mut query: Query<(&PendingForwarding, &mut RemoteAddr, &PacketSender)>,
+) -> Result<()> {
+    // only velocity's response is expected
+    let Ok((pending, mut addr, sender)) = query.get_mut(event.entity) else {
+        return Ok(());
+    };
+    if pending.message_id != event.packet.message_id {
+        return Ok(());
+    }
+    let mut entity = commands.entity(event.entity);
+    entity.remove::<PendingForwarding>();
+
+    let secret = config.forwarding.secret.as_bytes();
+    let forwarded = match &event.packet.data {
+        Some(data) => forwarding::parse_modern(secret, data),
+        None => Err(ForwardingError::Missing),
+    };
+    match forwarded {
+        Ok(forwarded) => {
+            addr.0.set_ip(forwarded.ip);
+            entity.insert(PlayerIdentity {
+                name: forwarded.name.unwrap_or_default(),
+                uuid: forwarded.uuid,
+                properties: forwarded.properties,
+            });
+        }
+        Err(err) => {
+            warn!(addr = %addr.0, %err, "refusing login without velocity forwarding");
+            let reason = match err {
+                ForwardingError::Missing => "This server requires you to connect with Velocity.",
+                _ => "Unable to verify player details.",
+            };
+            sender.send(LoginDisconnect::new(reason).blocking_raw()?)?;
+            sender.close(CloseReason::Kicked(err.to_string()));
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,26 @@
-use beacon_config::{Config, FAVICON};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::PoisonError,
+    time::{Duration, Instant},
+};
+
+use beacon_config::{Config, FAVICON, Host, favicon_generation};
 use beacon_data::{LATEST_SUPPORTED_VERSION, PROTOCOL_VERSION};
 
-use crate::{client::status::*, conn::{Despawn, PacketSender}, prelude::*};
+use crate::{
+    client::status::*,
+    conn::{CloseReason, HandshakeInfo, PacketSender, RemoteAddr},
+    packet::RawPacket,
+    player::OnlinePlayers,
+    prelude::*,
+};
+
+/// The most players listed in the status response's sample, like vanilla.
+const SAMPLE_SIZE: usize = 12;
+
+/// How often the sample is redrawn while players are online, like vanilla.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
 
 /// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Status_Request>
 #[server(resource = "status_request", state = Status)]
@@ -9,36 +28,169 @@ use crate::{client::status::*, conn::{Despawn, PacketSender}, prelude::*};
 pub struct StatusRequest;
 
 #[handler(StatusRequest)]
-fn handle(config: Res<Config>, query: Query<(&Despawn, &PacketSender)>) -> Result<()> {
-    // do not respond if status is disabled
-    let (despawn, sender) = query.get(event.entity)?;
-    if !config.server.status {
-        despawn.cancel();
+fn handle(
+    config: Res<Config>,
+    query: Query<(&PacketSender, Option<&HandshakeInfo>)>,
+    mut commands: Commands,
+) -> Result<()> {
+    // do not respond if status is disabled for the client's host
+    let (sender, handshake) = query.get(event.entity)?;
+    if !host(&config, handshake).is_some_and(|host| host.status) {
+        sender.close(CloseReason::Kicked("status is disabled".into()));
         return Ok(());
      }
 
-    let payload = StatusResponsePayload {
-        version: Version {
-            name: LATEST_SUPPORTED_VERSION.to_string(),
-            protocol: PROTOCOL_VERSION,
-        },
-        players: Players {
-            max: config.server.max_players,
-            // todo: change to actually online players, rather than connections
-            online: query.iter().count().saturating_sub(1) as u32,
-            sample: Vec::new()
-        },
-        description: Description { text: config.server.motd.clone() },
-        favicon: FAVICON.read().ok().and_then(|f| f.clone()),
-        secure_chat: false
+    let entity = event.entity;
+    commands.queue(move |world: &mut World| respond(world, entity));
+    Ok(())
+}
+
+/// Event triggered on a connection just before it is sent a status response, which observers can
+/// change, e.g. to show a different MOTD depending on the hostname the client used.
+#[derive(EntityEvent)]
+pub struct StatusResponseEvent {
+    /// The connection that requested the status.
+    pub entity: Entity,
+    /// The client's address.
+    pub addr: SocketAddr,
+    /// The client's handshake, containing the hostname it connected with and its protocol version.
+    pub handshake: HandshakeInfo,
+    /// The response that will be sent.
+    pub payload: StatusResponsePayload,
+}
+
+/// Find the virtual host settings for a client from its handshake.
+fn host<'a>(config: &'a Config, handshake: Option<&HandshakeInfo>) -> Option<Host<'a>> {
+    config.host(handshake.map(|handshake| handshake.hostname.as_str()))
+}
+
+/// Send the status response, letting any [StatusResponseEvent] observers change it first.
+fn respond(world: &mut World, entity: Entity) -> Result<()> {
+    let handshake = world.get::<HandshakeInfo>(entity);
+    let Some(host) = host(world.resource::<Config>(), handshake) else {
+        return Ok(());
+    };
+    let cache = world.resource::<StatusCache>();
+    let Some(cached) = cache.responses.get(&host.name.map(str::to_string)) else {
+        // the cache is always built before any packets are handled
+        return Ok(());
     };
-    let packet = StatusResponse::from(payload);
 
-    sender.send(packet.blocking_raw()?)?;
+    // without any observers, the cached response can be sent as is
+    let hooked = world
+        .event_key::<StatusResponseEvent>()
+        .and_then(|key| world.observers().try_get_observers(key))
+        .is_some_and(|observers| {
+            !observers.global_observers().is_empty() || !observers.entity_observers().is_empty()
+        });
+    let packet = match hooked {
+        false => cached.packet.clone(),
+        true => {
+            let addr = world.get::<RemoteAddr>(entity);
+            let (Some(addr), Some(handshake)) = (addr, handshake) else {
+                return Ok(());
+            };
+            let mut event = StatusResponseEvent {
+                entity,
+                addr: **addr,
+                handshake: handshake.clone(),
+                payload: cached.payload.clone(),
+            };
+            world.trigger_ref(&mut event);
+            StatusResponse::from(event.payload).blocking_raw()?
+        }
+    };
 
+    // the connection may have closed in the meantime
+    if let Some(sender) = world.get::<PacketSender>(entity) {
+        let _ = sender.send(packet);
+    }
     Ok(())
 }
 
+/// A status response, along with the payload it was encoded from.
+struct CachedResponse {
+    packet: RawPacket,
+    payload: StatusResponsePayload,
+}
+
+/// The status responses for the server and each virtual host, encoded ahead of time so that
+/// answering status requests costs almost nothing.
+///
+/// They are only rebuilt when the config, the favicon, or the number of online players changes,
+/// and every few seconds while players are online so that a different sample of them is shown.
+#[derive(Resource, Default)]
+pub struct StatusCache {
+    /// The responses keyed by virtual host name, or `None` for clients matching no virtual host.
+    responses: HashMap<Option<String>, CachedResponse>,
+    /// When the responses were last built.
+    built: Option<Instant>,
+    online: u32,
+    /// The [favicon generation](favicon_generation) the responses were built with.
+    favicon: u64,
+}
+
+impl StatusCache {
+    /// Rebuild the status responses if anything in them has changed.
+    pub(crate) fn update(
+        mut cache: ResMut<Self>,
+        config: Res<Config>,
+        players: OnlinePlayers,
+    ) -> Result<()> {
+        let online = players.count();
+        let generation = favicon_generation();
+        let sampled = online > 0 && !config.server.hide_online_players;
+        let changed = cache
+            .built
+            .is_none_or(|built| sampled && built.elapsed() >= SAMPLE_INTERVAL)
+            || config.is_changed()
+            || cache.online != online
+            || cache.favicon != generation;
+        if !changed {
+            return Ok(());
+        }
+
+        let favicon = FAVICON.read().unwrap_or_else(PoisonError::into_inner);
+
+        let sample: Vec<_> = match sampled {
+            false => Vec::new(),
+            true => players
+                .sample(SAMPLE_SIZE)
+                .into_iter()
+                .map(SamplePlayer::from)
+                .collect(),
+        };
+
+        let mut responses = HashMap::new();
+        for host in config.hosts() {
+            let payload = StatusResponsePayload {
+                version: Version {
+                    name: LATEST_SUPPORTED_VERSION.to_string(),
+                    protocol: PROTOCOL_VERSION,
+                },
+                players: Players {
+                    max: host.max_players,
+                    online,
+                    sample: sample.clone(),
+                },
+                description: Description { text: host.motd.to_string() },
+                favicon: host.favicon.map(str::to_string).or_else(|| favicon.clone()),
+                secure_chat: false
+            };
+            let packet = StatusResponse::from(payload.clone()).blocking_raw()?;
+            responses.insert(host.name.map(str::to_string), CachedResponse { packet, payload });
+        }
+
+        *cache = Self {
+            responses,
+            built: Some(Instant::now()),
+            online,
+            favicon: generation,
+        };
+        Ok(())
+    }
+}
+
 /// See: <https://minecraft.wiki/w/Java_Edition_protocol/Packets#Ping_Request>
 #[server(resource = "ping_request", state = Status)]
 #[derive(Clone, Copy)]
@@ -47,11 +199,11 @@ pub struct PingRequest {
 }
 
 #[handler(PingRequest)]
-fn handle(config: Res<Config>, query: Query<(&Despawn, &PacketSender)>) -> Result<()> {
-    // do not respond if status is disabled
-    let (despawn, sender) = query.get(event.entity)?;
-    if !config.server.status {
-        despawn.cancel();
+fn handle(config: Res<Config>, query: Query<(&PacketSender, Option<&HandshakeInfo>)>) -> Result<()> {
+    // do not respond if status is disabled for the client's host
+    let (sender, handshake) = query.get(event.entity)?;
+    if !host(&config, handshake).is_some_and(|host| host.status) {
+        sender.close(CloseReason::Kicked("status is disabled".into()));
         return Ok(());
      }
 
@@ -59,4 +211,59 @@ fn handle(config: Res<Config>, query: Query<(&Despawn, &PacketSender)>) -> Resul
     sender.send(packet.blocking_raw()?)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use beacon_codec::ProtocolState;
+    use beacon_config::{Config, set_favicon};
+    use bevy_ecs::prelude::*;
+
+    use super::{SAMPLE_INTERVAL, StatusCache};
+    use crate::{client::status::StatusResponsePayload, player::PlayerIdentity};
+
+    /// Update the cache, returning whether the responses were rebuilt.
+    fn update(world: &mut World) -> bool {
+        let built = world.resource::<StatusCache>().built;
+        let res: Result = world.run_system_cached(StatusCache::update).unwrap();
+        res.unwrap();
+        world.resource::<StatusCache>().built != built
+    }
+
+    fn response(world: &World) -> &StatusResponsePayload {
+        &world.resource::<StatusCache>().responses[&None].payload
+    }
+
+    #[test]
+    fn test_update() {
+        let mut world = World::new();
+        world.insert_resource(Config::load("missing.toml").unwrap());
+        world.init_resource::<StatusCache>();
+        assert!(update(&mut world));
+        assert!(!update(&mut world));
+
+        world.resource_mut::<Config>().server.motd = "Changed".into();
+        assert!(update(&mut world));
+        assert_eq!(response(&world).description.text, "Changed");
+
+        set_favicon(Some("data:image/png;base64,".into()));
+        assert!(update(&mut world));
+
+        world.spawn((
+            PlayerIdentity {
+                name: "Steve".into(),
+                uuid: 1,
+                properties: Vec::new(),
+            },
+            ProtocolState::Play,
+        ));
+        assert!(update(&mut world));
+        assert_eq!(response(&world).players.online, 1);
+        assert!(!update(&mut world));
+
+        // the sample is redrawn every few seconds
+        world.resource_mut::<StatusCache>().built = Some(Instant::now() - SAMPLE_INTERVAL);
+        assert!(update(&mut world));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+
+use beacon_codec::encode::{Encode, EncodeError};
+use serde::{Serialize, ser::SerializeMap};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+// todo: support the rest of the text component format
+// see: https://minecraft.wiki/w/Text_component_format
+
+/// NBT tag type that ends a compound.
+const TAG_END: u8 = 0;
+/// NBT tag type of a string.
+const TAG_STRING: u8 = 8;
+/// NBT tag type of a list.
+const TAG_LIST: u8 = 9;
+/// NBT tag type of a compound.
+const TAG_COMPOUND: u8 = 10;
+
+/// A text component: plain text, or a message that each client translates into its language.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Text {
+    /// The text to display, if the component is not translated.
+    pub text: String,
+    /// The translation key of the message to display instead, e.g. `commands.kick.success`.
+    pub translate: Option<String>,
+    /// The components that fill in the translated message's placeholders.
+    pub with: Vec<Text>,
+}
+
+impl Text {
+    /// A message that each client translates into its language, filling in its placeholders with
+    /// the given components.
+    pub fn translate<T: Into<Text>>(
+        key: impl Into<String>,
+        with: impl IntoIterator<Item = T>,
+    ) -> Self {
+        Self {
+            text: String::new(),
+            translate: Some(key.into()),
+            with: with.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Encode the component as an NBT tag's payload, which is a compound unless the component is
+    /// plain text.
+    fn put_nbt(&self, buf: &mut Vec<u8>) {
+        let Some(key) = &self.translate else {
+            put_string(buf, &self.text);
+            return;
+        };
+
+        buf.push(TAG_STRING);
+        put_string(buf, "translate");
+        put_string(buf, key);
+        if !self.with.is_empty() {
+            // lists can only hold one type of tag, so every argument is a compound
+            buf.push(TAG_LIST);
+            put_string(buf, "with");
+            buf.push(TAG_COMPOUND);
+            buf.extend_from_slice(&(self.with.len() as i32).to_be_bytes());
+            for arg in &self.with {
+                match arg.translate {
+                    Some(_) => arg.put_nbt(buf),
+                    None => {
+                        buf.push(TAG_STRING);
+                        put_string(buf, "text");
+                        put_string(buf, &arg.text);
+                        buf.push(TAG_END);
+                    }
+                }
+            }
+        }
+        buf.push(TAG_END);
+    }
+}
+
+impl<T: Into<String>> From<T> for Text {
+    fn from(text: T) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Shows the component in English, for the console and logs.
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(key) = &self.translate else {
+            return f.write_str(&self.text);
+        };
+        // unknown keys are shown as is, like the vanilla client does
+        let Some(format) = beacon_data::lang::english(key) else {
+            return f.write_str(key);
+        };
+
+        // placeholders are either `%s`, which take the next argument, or `%1$s`
+        let mut next = 0;
+        let mut rest = format;
+        while let Some(i) = rest.find('%') {
+            f.write_str(&rest[..i])?;
+            rest = &rest[i + 1..];
+            if let Some(after) = rest.strip_prefix('%') {
+                f.write_str("%")?;
+                rest = after;
+                continue;
+            }
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let index = match rest[digits..].strip_prefix("$s") {
+                Some(after) if digits > 0 => {
+                    let index = rest[..digits].parse::<usize>().unwrap_or(1) - 1;
+                    rest = after;
+                    index
+                }
+                _ => match rest.strip_prefix('s') {
+                    Some(after) => {
+                        rest = after;
+                        next += 1;
+                        next - 1
+                    }
+                    None => {
+                        f.write_str("%")?;
+                        continue;
+                    }
+                },
+            };
+            if let Some(arg) = self.with.get(index) {
+                write!(f, "{arg}")?;
+            }
+        }
+        f.write_str(rest)
+    }
+}
+
+/// Text is sent as JSON in the login state and in the server list.
+impl Serialize for Text {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match &self.translate {
+            Some(key) => {
+                map.serialize_entry("translate", key)?;
+                if !self.with.is_empty() {
+                    map.serialize_entry("with", &self.with)?;
+                }
+            }
+            None => map.serialize_entry("text", &self.text)?,
+        }
+        map.end()
+    }
+}
+
+/// Text is sent as NBT in the configuration and play states. Plain text can be sent as a single
+/// string tag, rather than a compound.
+///
+/// See: <https://minecraft.wiki/w/NBT_format#Network_NBT_(Java_Edition)>
+impl Encode for Text {
+    async fn encode<W: AsyncWrite + Unpin>(&self, write: &mut W) -> Result<(), EncodeError> {
+        let mut buf = vec![match self.translate {
+            Some(_) => TAG_COMPOUND,
+            None => TAG_STRING,
+        }];
+        self.put_nbt(&mut buf);
+        write.write_all(&buf).await?;
+        Ok(())
+    }
+}
+
+/// Encode a string tag's payload, which is its length then its modified UTF-8.
+fn put_string(buf: &mut Vec<u8>, text: &str) {
+    let mut encoded = modified_utf8(text);
+    encoded.truncate(u16::MAX as usize);
+    buf.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&encoded);
+}
+
+/// Encode a string as Java's modified UTF-8, which encodes null as two bytes, and characters
+/// outside the BMP as surrogate pairs.
+fn modified_utf8(text: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(text.len());
+    for unit in text.encode_utf16() {
+        match unit {
+            0x01..=0x7F => buf.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                buf.push(0xC0 | (unit >> 6) as u8);
+                buf.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                buf.push(0xE0 | (unit >> 12) as u8);
+                buf.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                buf.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Text, modified_utf8};
+
+    #[test]
+    fn test_modified_utf8() {
+        assert_eq!(modified_utf8("hé"), "hé".as_bytes());
+        assert_eq!(modified_utf8("\0"), [0xC0, 0x80]);
+        // a surrogate pair, rather than four bytes
+        assert_eq!(modified_utf8("😀"), [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+    }
+
+    #[test]
+    fn test_translate() {
+        let text = Text::translate("commands.kick.success", ["Steve", "Be nice"]);
+        assert_eq!(text.to_string(), "Kicked Steve: Be nice");
+        assert_eq!(
+            serde_json::to_string(&text).unwrap(),
+            r#"{"translate":"commands.kick.success","with":[{"text":"Steve"},{"text":"Be nice"}]}"#
+        );
+
+        let mut buf = Vec::new();
+        Text::translate("a", ["b"]).put_nbt(&mut buf);
+        #[rustfmt::skip]
+        assert_eq!(buf, [
+            8, 0, 9, b't', b'r', b'a', b'n', b's', b'l', b'a', b't', b'e', 0, 1, b'a',
+            9, 0, 4, b'w', b'i', b't', b'h', 10, 0, 0, 0, 1,
+            8, 0, 4, b't', b'e', b'x', b't', 0, 1, b'b', 0,
+            0,
+        ]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::io::{self, IoSlice};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::packet::RawPacket;
+
+/// The most slices passed to a single vectored write, as Linux refuses more than 1024.
+const MAX_SLICES: usize = 1024;
+
+/// Writes batches of outgoing packets to a socket.
+///
+/// Every packet's header is encoded into one reused buffer, then the headers and the packets' data
+/// are written together with vectored writes. A whole batch usually takes a single syscall, without
+/// copying any packet data.
+pub struct PacketWriter<W> {
+    inner: W,
+    headers: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl<W: AsyncWrite + Unpin> PacketWriter<W> {
+    /// Wrap a writer, such as the write half of a socket.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            headers: Vec::new(),
+            ends: Vec::new(),
+        }
+    }
+
+    /// Write a batch of packets, in order, and flush them.
+    pub async fn write_batch(&mut self, packets: &[RawPacket]) -> io::Result<()> {
+        self.headers.clear();
+        self.ends.clear();
+        for packet in packets {
+            packet.put_header(&mut self.headers);
+            self.ends.push(self.headers.len());
+        }
+
+        let mut slices = Vec::with_capacity(packets.len() * 2);
+        let mut start = 0;
+        for (packet, &end) in packets.iter().zip(&self.ends) {
+            slices.push(IoSlice::new(&self.headers[start..end]));
+            if !packet.data.is_empty() {
+                slices.push(IoSlice::new(&packet.data));
+            }
+            start = end;
+        }
+
+        for chunk in slices.chunks_mut(MAX_SLICES) {
+            write_all_vectored(&mut self.inner, chunk).await?;
+        }
+        self.inner.flush().await
+    }
+
+    /// Flush and shut down the writer.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        self.inner.shutdown().await
+    }
+
+    /// Unwrap the writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Write every slice, continuing after partial writes.
+async fn write_all_vectored<W: AsyncWrite + Unpin>(
+    write: &mut W,
+    mut slices: &mut [IoSlice<'_>],
+) -> io::Result<()> {
+    while !slices.is_empty() {
+        let written = write.write_vectored(slices).await?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut slices, written);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use beacon_codec::{encode::Encode, types::VarInt};
+    use tokio::io::AsyncWrite;
+
+    use super::PacketWriter;
+    use crate::packet::RawPacket;
+
+    /// Accepts at most 3 bytes per write, counting the writes.
+    #[derive(Default)]
+    struct Trickle {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let len = buf.len().min(3);
+            self.data.extend_from_slice(&buf[..len]);
+            self.writes += 1;
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_batch() {
+        let packets = [
+            RawPacket::new(VarInt(0), vec![1, 2, 3, 4].into()),
+            RawPacket::new(VarInt(300), Default::default()),
+            RawPacket::new(VarInt(2), vec![5; 200].into()),
+        ];
+
+        let mut expected = Vec::new();
+        for packet in &packets {
+            packet.encode(&mut expected).await.unwrap();
+        }
+
+        // partial writes still write everything, in order
+        let mut writer = PacketWriter::new(Trickle::default());
+        writer.write_batch(&packets).await.unwrap();
+        let trickle = writer.into_inner();
+        assert_eq!(trickle.data, expected);
+        assert!(trickle.writes > 1);
+
+        // a writer accepting everything at once
+        let mut writer = PacketWriter::new(Vec::new());
+        writer.write_batch(&packets).await.unwrap();
+        assert_eq!(writer.into_inner(), expected);
+    }
+}
@@ -0,0 +1,568 @@
+//! The server console, which runs commands typed into the terminal.
+//!
+//! When stdin is a terminal, the console reads keys one at a time so commands can be edited, and
+//! redraws its prompt below any log output. Otherwise, it reads whole lines without a prompt.
+
+use std::{
+    io::{self, BufRead, IsTerminal, Read, Write},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+
+use beacon_core::{CommandSender, WorldQueue, complete_command, run_command};
+use tokio::runtime::Handle;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Shown before the command being typed.
+const PROMPT: &str = "> ";
+
+/// How many commands to remember.
+const HISTORY_SIZE: usize = 100;
+
+/// The server console. It is also a [MakeWriter], so log output can be written around the
+/// prompt.
+#[derive(Clone, Default)]
+pub struct Console {
+    line: Arc<Mutex<Line>>,
+}
+
+impl Console {
+    /// Start reading commands from stdin on a new thread, and run them in the server's world.
+    ///
+    /// The terminal is restored when the returned guard is dropped.
+    pub fn spawn(&self, queue: WorldQueue) -> ConsoleGuard {
+        let raw = match io::stdin().is_terminal().then(RawMode::enable) {
+            Some(Ok(raw)) => Some(raw),
+            Some(Err(err)) => {
+                debug!(%err, "line editing is not available");
+                None
+            }
+            None => None,
+        };
+        let editing = raw.is_some();
+
+        let console = self.clone();
+        let runtime = Handle::current();
+        let res = thread::Builder::new()
+            .name("console".into())
+            .spawn(move || {
+                let run = |command: &str| {
+                    let command = command.to_string();
+                    let run =
+                        move |world: &mut _| run_command(world, CommandSender::Console, &command);
+                    for line in runtime.block_on(queue.run(run)).unwrap_or_default() {
+                        info!("{line}");
+                    }
+                };
+                let complete = |command: &str| {
+                    let command = command.to_string();
+                    let complete = move |world: &mut _| {
+                        complete_command(world, CommandSender::Console, &command)
+                    };
+                    runtime.block_on(queue.run(complete)).unwrap_or_default()
+                };
+
+                let res = if editing {
+                    console.read_keys(run, complete)
+                } else {
+                    read_lines(run)
+                };
+                match res {
+                    Ok(()) => debug!("console closed"),
+                    Err(err) => warn!(%err, "console closed"),
+                }
+            });
+        if let Err(err) = res {
+            warn!(%err, "failed to start the console");
+        }
+
+        ConsoleGuard {
+            console: self.clone(),
+            _raw: raw,
+        }
+    }
+
+    /// Read keys from the terminal, editing the line being typed, until stdin is closed.
+    fn read_keys(
+        &self,
+        run: impl Fn(&str),
+        complete: impl Fn(&str) -> Vec<String>,
+    ) -> io::Result<()> {
+        let mut history = History::default();
+        let mut stdin = io::stdin().lock();
+        self.update(|line| line.active = true);
+
+        while let Some(key) = Key::read(&mut stdin)? {
+            match key {
+                Key::Enter => {
+                    let command = self.update(|line| line.take());
+                    if !command.trim().is_empty() {
+                        history.push(&command);
+                        info!("{PROMPT}{command}");
+                        run(command.trim());
+                    }
+                }
+                Key::Up | Key::Down => self.update(|line| {
+                    let text = line.text();
+                    let recalled = match key {
+                        Key::Up => history.older(&text),
+                        _ => history.newer(),
+                    };
+                    if let Some(recalled) = recalled {
+                        line.set(recalled);
+                    }
+                }),
+                Key::Tab => {
+                    let command = self.update(|line| line.before_cursor());
+                    let suggestions = complete(&command);
+                    let shown = self.update(|line| line.complete(&suggestions));
+                    if shown {
+                        let mut writer = self.make_writer();
+                        writeln!(writer, "{}", suggestions.join("  "))?;
+                    }
+                }
+                key => self.update(|line| line.edit(key)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the line being typed, then redraw the prompt.
+    fn update<T>(&self, f: impl FnOnce(&mut Line) -> T) -> T {
+        let mut line = self.line.lock().unwrap_or_else(PoisonError::into_inner);
+        let res = f(&mut line);
+        let mut buf = Vec::new();
+        line.draw(&mut buf);
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(&buf).and_then(|_| stdout.flush());
+        res
+    }
+}
+
+impl<'a> MakeWriter<'a> for Console {
+    type Writer = ConsoleWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ConsoleWriter {
+            console: self,
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Hides the prompt and restores the terminal when dropped.
+pub struct ConsoleGuard {
+    console: Console,
+    _raw: Option<RawMode>,
+}
+
+impl Drop for ConsoleGuard {
+    fn drop(&mut self) {
+        let mut line = self
+            .console
+            .line
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if std::mem::take(&mut line.active) {
+            let mut stdout = io::stdout().lock();
+            let _ = stdout.write_all(b"\r\x1b[2K").and_then(|_| stdout.flush());
+        }
+    }
+}
+
+/// Writes output above the prompt. The output is buffered, then written when the writer is
+/// dropped, so the prompt is only redrawn once.
+pub struct ConsoleWriter<'a> {
+    console: &'a Console,
+    buf: Vec<u8>,
+}
+
+impl Write for ConsoleWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ConsoleWriter<'_> {
+    fn drop(&mut self) {
+        let line = self
+            .console
+            .line
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut buf = Vec::new();
+        if line.active {
+            // clear the prompt, write the output in its place, then draw it again below
+            buf.extend_from_slice(b"\r\x1b[2K");
+            buf.append(&mut self.buf);
+            line.draw(&mut buf);
+        } else {
+            buf.append(&mut self.buf);
+        }
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(&buf).and_then(|_| stdout.flush());
+    }
+}
+
+/// Read commands from stdin one line at a time, until it is closed.
+fn read_lines(run: impl Fn(&str)) -> io::Result<()> {
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            run(line.trim());
+        }
+    }
+    Ok(())
+}
+
+/// A key pressed in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// Delete everything before the cursor.
+    KillStart,
+    /// Delete everything after the cursor.
+    KillEnd,
+    /// Delete the word before the cursor.
+    KillWord,
+    /// A key the console does not use.
+    Ignored,
+}
+
+impl Key {
+    /// Read the next key, or `None` if stdin was closed.
+    fn read<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut next = || -> io::Result<Option<u8>> {
+            let mut buf = [0];
+            Ok((reader.read(&mut buf)? == 1).then_some(buf[0]))
+        };
+        let Some(byte) = next()? else {
+            return Ok(None);
+        };
+
+        let key = match byte {
+            b'\r' | b'\n' => Self::Enter,
+            b'\t' => Self::Tab,
+            0x7F | 0x08 => Self::Backspace,
+            0x01 => Self::Home,
+            0x05 => Self::End,
+            0x15 => Self::KillStart,
+            0x0B => Self::KillEnd,
+            0x17 => Self::KillWord,
+            0x1B => {
+                // an escape sequence, like "\x1b[A" or "\x1b[3~"
+                let Some(kind @ (b'[' | b'O')) = next()? else {
+                    return Ok(Some(Self::Ignored));
+                };
+                let mut param = 0;
+                loop {
+                    match next()? {
+                        Some(digit @ b'0'..=b'9') => param = param * 10 + (digit - b'0') as u32,
+                        Some(b';') => param = 0,
+                        Some(end) => break Self::escape(kind, param, end),
+                        None => return Ok(None),
+                    }
+                }
+            }
+            byte if byte < 0x20 => Self::Ignored,
+            byte => {
+                // a character, which may take several bytes
+                let len = match byte {
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF7 => 4,
+                    _ => 1,
+                };
+                let mut buf = vec![byte];
+                for _ in 1..len {
+                    buf.extend(next()?);
+                }
+                match std::str::from_utf8(&buf)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    Some(c) => Self::Char(c),
+                    None => Self::Ignored,
+                }
+            }
+        };
+        Ok(Some(key))
+    }
+
+    fn escape(kind: u8, param: u32, end: u8) -> Self {
+        match (kind, param, end) {
+            (_, _, b'A') => Self::Up,
+            (_, _, b'B') => Self::Down,
+            (_, _, b'C') => Self::Right,
+            (_, _, b'D') => Self::Left,
+            (_, _, b'H') | (b'[', 1 | 7, b'~') => Self::Home,
+            (_, _, b'F') | (b'[', 4 | 8, b'~') => Self::End,
+            (b'[', 3, b'~') => Self::Delete,
+            _ => Self::Ignored,
+        }
+    }
+}
+
+/// The line being typed.
+#[derive(Debug, Default)]
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+    /// Whether the prompt is shown.
+    active: bool,
+}
+
+impl Line {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn before_cursor(&self) -> String {
+        self.chars[..self.cursor].iter().collect()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn take(&mut self) -> String {
+        let text = self.text();
+        self.set("");
+        text
+    }
+
+    fn edit(&mut self, key: Key) {
+        match key {
+            Key::Char(c) => {
+                self.chars.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.chars.len(),
+            Key::KillStart => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::KillEnd => self.chars.truncate(self.cursor),
+            Key::KillWord => {
+                let before = &self.chars[..self.cursor];
+                let trimmed = before.iter().rposition(|c| *c != ' ').map_or(0, |i| i + 1);
+                let start = before[..trimmed]
+                    .iter()
+                    .rposition(|c| *c == ' ')
+                    .map_or(0, |i| i + 1);
+                self.chars.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            _ => {}
+        }
+    }
+
+    /// Complete the word before the cursor with the longest prefix every suggestion shares.
+    /// Returns whether the suggestions should be shown, because there is more than one and the
+    /// word could not be completed any further.
+    fn complete(&mut self, suggestions: &[String]) -> bool {
+        let Some(first) = suggestions.first() else {
+            return false;
+        };
+        let mut common: Vec<char> = first.chars().collect();
+        for suggestion in &suggestions[1..] {
+            let len = common
+                .iter()
+                .zip(suggestion.chars())
+                .take_while(|(a, b)| **a == *b)
+                .count();
+            common.truncate(len);
+        }
+
+        let start = self.chars[..self.cursor]
+            .iter()
+            .rposition(|c| *c == ' ')
+            .map_or(0, |i| i + 1);
+        let unchanged = self.chars[start..self.cursor] == common[..];
+        self.chars
+            .splice(start..self.cursor, common.iter().copied());
+        self.cursor = start + common.len();
+        if suggestions.len() == 1 && self.chars.get(self.cursor) != Some(&' ') {
+            self.chars.insert(self.cursor, ' ');
+            self.cursor += 1;
+        }
+        suggestions.len() > 1 && unchanged
+    }
+
+    /// Draw the prompt over the current terminal line.
+    fn draw(&self, buf: &mut Vec<u8>) {
+        if !self.active {
+            return;
+        }
+        buf.extend_from_slice(b"\r\x1b[2K");
+        buf.extend_from_slice(PROMPT.as_bytes());
+        buf.extend_from_slice(self.text().as_bytes());
+        buf.push(b'\r');
+        let column = PROMPT.len() + self.cursor;
+        buf.extend_from_slice(format!("\x1b[{column}C").as_bytes());
+    }
+}
+
+/// Commands that have been run, which can be recalled with the arrow keys.
+#[derive(Debug, Default)]
+struct History {
+    commands: Vec<String>,
+    /// The command being recalled, if any.
+    index: Option<usize>,
+    /// The line that was being typed before recalling a command.
+    draft: String,
+}
+
+impl History {
+    fn push(&mut self, command: &str) {
+        if self.commands.last().is_none_or(|last| last != command) {
+            self.commands.push(command.to_string());
+            if self.commands.len() > HISTORY_SIZE {
+                self.commands.remove(0);
+            }
+        }
+        self.index = None;
+    }
+
+    /// Recall an older command.
+    fn older(&mut self, line: &str) -> Option<&str> {
+        let index = match self.index {
+            None => {
+                self.draft = line.to_string();
+                self.commands.len().checked_sub(1)?
+            }
+            Some(index) => index.checked_sub(1)?,
+        };
+        self.index = Some(index);
+        Some(&self.commands[index])
+    }
+
+    /// Recall a newer command, or the line that was being typed.
+    fn newer(&mut self) -> Option<&str> {
+        let index = self.index? + 1;
+        if index == self.commands.len() {
+            self.index = None;
+            return Some(&self.draft);
+        }
+        self.index = Some(index);
+        Some(&self.commands[index])
+    }
+}
+
+/// Puts the terminal in non-canonical mode while it is alive, so keys can be read as they are
+/// pressed. Signals are left alone, so Ctrl-C still stops the server.
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let mut termios = std::mem::MaybeUninit::uninit();
+        // SAFETY: tcgetattr initializes termios when it succeeds
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: raw is a valid termios
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: original is the termios read when raw mode was enabled
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Terminals other than unix ones are read a line at a time, as if they were not terminals.
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "line editing is only supported on unix",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, Key, Line};
+
+    #[test]
+    fn test_keys() {
+        let mut input: &[u8] = b"say h\xc3\xa9\x1b[D\x1b[3~\x1b[Hx\x17";
+        let mut line = Line::default();
+        while let Some(key) = Key::read(&mut input).unwrap() {
+            line.edit(key);
+        }
+        // typed "say hé", deleted the "é", typed "x" at the start, then deleted the word "x"
+        assert_eq!(line.text(), "say h");
+        assert_eq!(line.cursor, 0);
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut line = Line::default();
+        line.set("gamemode cr");
+        assert!(!line.complete(&["creative".into()]));
+        assert_eq!(line.text(), "gamemode creative ");
+
+        line.set("s");
+        assert!(!line.complete(&["save-all".into(), "save-off".into()]));
+        assert_eq!(line.text(), "save-");
+        assert!(line.complete(&["save-all".into(), "save-off".into()]));
+
+        let mut history = History::default();
+        history.push("list");
+        history.push("stop");
+        assert_eq!(history.older("draft"), Some("stop"));
+        assert_eq!(history.older(""), Some("list"));
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer(), Some("stop"));
+        assert_eq!(history.newer(), Some("draft"));
+    }
+}
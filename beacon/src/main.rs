@@ -8,12 +8,17 @@ use beacon_core::BeaconServer;
 use miette::Result;
 use tracing_subscriber::{fmt::time::ChronoLocal, layer::SubscriberExt, util::SubscriberInitExt};
 
+use crate::console::Console;
+
 #[macro_use]
 extern crate tracing;
 
+mod console;
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // configure tracing
+    // configure tracing, writing around the console's prompt
+    let console = Console::default();
     tracing_subscriber::registry()
         // env filter
         .with(tracing_subscriber::EnvFilter::from("debug"))
@@ -21,6 +26,7 @@ async fn main() -> Result<()> {
         .with(
             tracing_subscriber::fmt::layer()
                 .with_target(false)
+                .with_writer(console.clone())
                 .with_timer(ChronoLocal::new("%d/%m/%Y %H:%M:%S".to_string())),
         )
         .init();
@@ -44,6 +50,10 @@ async fn main() -> Result<()> {
         ))
     );
 
-    let mut server = BeaconServer::new("beacon.toml").await?;
+    let builder = BeaconServer::builder().add_default_plugins("beacon.toml");
+    #[cfg(feature = "commands")]
+    let builder = builder.add_plugin(beacon_commands::CommandsPlugin);
+    let mut server = builder.build().await?;
+    let _console = console.spawn(server.queue());
     server.start().await
 }